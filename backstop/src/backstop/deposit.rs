@@ -4,6 +4,9 @@ use soroban_sdk::{panic_with_error, Address, Env};
 
 use super::require_is_from_pool_factory;
 
+#[cfg(feature = "retroshades")]
+use crate::contract::retroshades::BackstopDepositInfo;
+
 /// Perform a deposit into the backstop module
 pub fn execute_deposit(e: &Env, from: &Address, pool_address: &Address, amount: i128) -> i128 {
     require_nonnegative(e, amount);
@@ -29,6 +32,19 @@ pub fn execute_deposit(e: &Env, from: &Address, pool_address: &Address, amount:
     storage::set_pool_balance(e, pool_address, &pool_balance);
     storage::set_user_balance(e, pool_address, from, &user_balance);
 
+    // Retroshades logic
+    #[cfg(feature = "retroshades")]
+    BackstopDepositInfo::new(
+        e,
+        pool_address,
+        from,
+        amount,
+        to_mint,
+        &pool_balance,
+        &user_balance,
+    )
+    .emit(e);
+
     to_mint
 }
 
@@ -8,6 +8,9 @@ use soroban_sdk::{panic_with_error, unwrap::UnwrapOptimized, Address, Env};
 
 use super::require_is_from_pool_factory;
 
+#[cfg(feature = "retroshades")]
+use crate::contract::retroshades::{BackstopDonateInfo, BackstopDrawInfo};
+
 /// Perform a draw from a pool's backstop
 ///
 /// `pool_address` MUST be authenticated before calling
@@ -21,6 +24,10 @@ pub fn execute_draw(e: &Env, pool_address: &Address, amount: i128, to: &Address)
 
     let backstop_token = TokenClient::new(e, &storage::get_backstop_token(e));
     backstop_token.transfer(&e.current_contract_address(), to, &amount);
+
+    // Retroshades logic
+    #[cfg(feature = "retroshades")]
+    BackstopDrawInfo::new(e, pool_address, to, amount, &pool_balance).emit(e);
 }
 
 /// Perform a donation to a pool's backstop
@@ -38,6 +45,10 @@ pub fn execute_donate(e: &Env, from: &Address, pool_address: &Address, amount: i
 
     pool_balance.deposit(amount, 0);
     storage::set_pool_balance(e, pool_address, &pool_balance);
+
+    // Retroshades logic
+    #[cfg(feature = "retroshades")]
+    BackstopDonateInfo::new(e, pool_address, from, amount, &pool_balance).emit(e);
 }
 
 /// Perform an update to the Comet LP token underlying value
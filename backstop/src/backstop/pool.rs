@@ -15,14 +15,7 @@ pub struct PoolBackstopData {
 
 pub fn load_pool_backstop_data(e: &Env, address: &Address) -> PoolBackstopData {
     let pool_balance = storage::get_pool_balance(e, address);
-    let q4w_pct = if pool_balance.shares > 0 {
-        pool_balance
-            .q4w
-            .fixed_div_ceil(pool_balance.shares, SCALAR_7)
-            .unwrap_optimized()
-    } else {
-        0
-    };
+    let q4w_pct = pool_balance.q4w_pct();
 
     let (blnd_per_tkn, usdc_per_tkn) = storage::get_lp_token_val(e);
     let blnd = pool_balance
@@ -126,6 +119,17 @@ impl PoolBalance {
         self.tokens - self.convert_to_tokens(self.q4w)
     }
 
+    /// Determine the percentage of shares queued for withdrawal (7 decimals)
+    pub fn q4w_pct(&self) -> i128 {
+        if self.shares > 0 {
+            self.q4w
+                .fixed_div_ceil(self.shares, SCALAR_7)
+                .unwrap_optimized()
+        } else {
+            0
+        }
+    }
+
     /// Deposit tokens and shares into the pool
     ///
     /// ### Arguments
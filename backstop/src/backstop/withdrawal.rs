@@ -4,6 +4,11 @@ use soroban_sdk::{panic_with_error, unwrap::UnwrapOptimized, Address, Env};
 
 use super::Q4W;
 
+#[cfg(feature = "retroshades")]
+use crate::contract::retroshades::{
+    BackstopDequeueWithdrawalInfo, BackstopQueueWithdrawalInfo, BackstopWithdrawInfo,
+};
+
 /// Perform a queue for withdraw from the backstop module
 pub fn execute_queue_withdrawal(
     e: &Env,
@@ -25,7 +30,22 @@ pub fn execute_queue_withdrawal(
     storage::set_user_balance(e, pool_address, from, &user_balance);
     storage::set_pool_balance(e, pool_address, &pool_balance);
 
-    user_balance.q4w.last().unwrap_optimized()
+    let new_q4w = user_balance.q4w.last().unwrap_optimized();
+
+    // Retroshades logic
+    #[cfg(feature = "retroshades")]
+    BackstopQueueWithdrawalInfo::new(
+        e,
+        pool_address,
+        from,
+        amount,
+        new_q4w.exp,
+        &pool_balance,
+        &user_balance,
+    )
+    .emit(e);
+
+    new_q4w
 }
 
 /// Perform a dequeue of queued for withdraw deposits from the backstop module
@@ -44,6 +64,11 @@ pub fn execute_dequeue_withdrawal(e: &Env, from: &Address, pool_address: &Addres
 
     storage::set_user_balance(e, pool_address, from, &user_balance);
     storage::set_pool_balance(e, pool_address, &pool_balance);
+
+    // Retroshades logic
+    #[cfg(feature = "retroshades")]
+    BackstopDequeueWithdrawalInfo::new(e, pool_address, from, amount, &pool_balance, &user_balance)
+        .emit(e);
 }
 
 /// Perform a withdraw from the backstop module
@@ -67,6 +92,19 @@ pub fn execute_withdraw(e: &Env, from: &Address, pool_address: &Address, amount:
     let backstop_token_client = TokenClient::new(e, &storage::get_backstop_token(e));
     backstop_token_client.transfer(&e.current_contract_address(), from, &to_return);
 
+    // Retroshades logic
+    #[cfg(feature = "retroshades")]
+    BackstopWithdrawInfo::new(
+        e,
+        pool_address,
+        from,
+        amount,
+        to_return,
+        &pool_balance,
+        &user_balance,
+    )
+    .emit(e);
+
     to_return
 }
 
@@ -9,12 +9,19 @@ use soroban_sdk::{
     contract, contractclient, contractimpl, panic_with_error, Address, Env, Symbol, Vec,
 };
 
+#[cfg(feature = "retroshades")]
 pub(crate) mod retroshades {
+    #[cfg(not(any(test, feature = "testutils")))]
     use retroshade_sdk::Retroshade;
-    use soroban_sdk::{contracttype, Address, Symbol, Vec};
+    use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
+
+    use crate::{
+        backstop::{PoolBalance, UserBalance},
+        storage,
+    };
 
     #[contracttype]
-    #[derive(Retroshade)]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
     pub struct CollateralActionInfo {
         pub pool: Address,
         pub reserve_address: Address,
@@ -38,7 +45,7 @@ pub(crate) mod retroshades {
     }
 
     #[contracttype]
-    #[derive(Retroshade)]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
     pub struct BorrowActionInfo {
         pub pool: Address,
         pub reserve_address: Address,
@@ -65,7 +72,7 @@ pub(crate) mod retroshades {
     }
 
     #[contracttype]
-    #[derive(Retroshade)]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
     pub struct FillAuctionActionInfo {
         pub pool: Address,
         pub liquidator_address: Address,
@@ -97,7 +104,7 @@ pub(crate) mod retroshades {
     }
 
     #[contracttype]
-    #[derive(Retroshade)]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
     pub struct ClaimActionInfo {
         pub pool: Address,
 
@@ -113,6 +120,349 @@ pub(crate) mod retroshades {
         pub ledger: u32,
         pub timestamp: u64,
     }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BackstopDepositInfo {
+        pub pool: Address,
+        pub user: Address,
+        pub amount: i128,
+        pub shares: i128,
+
+        pub user_shares: i128,
+        pub user_tokens: i128,
+        pub user_q4w: i128,
+        pub pool_shares: i128,
+        pub pool_tokens: i128,
+        pub pool_q4w: i128,
+        pub q4w_pct: i128,
+        pub lp_blnd: i128,
+        pub lp_usdc: i128,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BackstopQueueWithdrawalInfo {
+        pub pool: Address,
+        pub user: Address,
+        pub shares: i128,
+        pub expiration: u64,
+
+        pub user_shares: i128,
+        pub user_tokens: i128,
+        pub user_q4w: i128,
+        pub pool_shares: i128,
+        pub pool_tokens: i128,
+        pub pool_q4w: i128,
+        pub q4w_pct: i128,
+        pub lp_blnd: i128,
+        pub lp_usdc: i128,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BackstopDequeueWithdrawalInfo {
+        pub pool: Address,
+        pub user: Address,
+        pub shares: i128,
+
+        pub user_shares: i128,
+        pub user_tokens: i128,
+        pub user_q4w: i128,
+        pub pool_shares: i128,
+        pub pool_tokens: i128,
+        pub pool_q4w: i128,
+        pub q4w_pct: i128,
+        pub lp_blnd: i128,
+        pub lp_usdc: i128,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BackstopWithdrawInfo {
+        pub pool: Address,
+        pub user: Address,
+        pub shares: i128,
+        pub amount: i128,
+
+        pub user_shares: i128,
+        pub user_tokens: i128,
+        pub user_q4w: i128,
+        pub pool_shares: i128,
+        pub pool_tokens: i128,
+        pub pool_q4w: i128,
+        pub q4w_pct: i128,
+        pub lp_blnd: i128,
+        pub lp_usdc: i128,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BackstopDrawInfo {
+        pub pool: Address,
+        pub to: Address,
+        pub amount: i128,
+
+        pub pool_shares: i128,
+        pub pool_tokens: i128,
+        pub pool_q4w: i128,
+        pub q4w_pct: i128,
+        pub lp_blnd: i128,
+        pub lp_usdc: i128,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BackstopDonateInfo {
+        pub pool: Address,
+        pub from: Address,
+        pub amount: i128,
+
+        pub pool_shares: i128,
+        pub pool_tokens: i128,
+        pub pool_q4w: i128,
+        pub q4w_pct: i128,
+        pub lp_blnd: i128,
+        pub lp_usdc: i128,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    /// The user's share balance, the token value of those shares, and the shares queued for withdrawal
+    fn user_state(pool_balance: &PoolBalance, user_balance: &UserBalance) -> (i128, i128, i128) {
+        let mut q4w = 0;
+        for entry in user_balance.q4w.iter() {
+            q4w += entry.amount;
+        }
+        (
+            user_balance.shares,
+            pool_balance.convert_to_tokens(user_balance.shares),
+            q4w,
+        )
+    }
+
+    /// The LP token's (blnd_per_tkn, usdc_per_tkn) value, or zero if it has not been computed yet
+    fn lp_token_val(e: &Env) -> (i128, i128) {
+        if storage::has_lp_token_val(e) {
+            storage::get_lp_token_val(e)
+        } else {
+            (0, 0)
+        }
+    }
+
+    impl BackstopDepositInfo {
+        pub fn new(
+            e: &Env,
+            pool: &Address,
+            user: &Address,
+            amount: i128,
+            shares: i128,
+            pool_balance: &PoolBalance,
+            user_balance: &UserBalance,
+        ) -> Self {
+            let (user_shares, user_tokens, user_q4w) = user_state(pool_balance, user_balance);
+            let (lp_blnd, lp_usdc) = lp_token_val(e);
+            BackstopDepositInfo {
+                pool: pool.clone(),
+                user: user.clone(),
+                amount,
+                shares,
+                user_shares,
+                user_tokens,
+                user_q4w,
+                pool_shares: pool_balance.shares,
+                pool_tokens: pool_balance.tokens,
+                pool_q4w: pool_balance.q4w,
+                q4w_pct: pool_balance.q4w_pct(),
+                lp_blnd,
+                lp_usdc,
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+    }
+
+    impl BackstopQueueWithdrawalInfo {
+        pub fn new(
+            e: &Env,
+            pool: &Address,
+            user: &Address,
+            shares: i128,
+            expiration: u64,
+            pool_balance: &PoolBalance,
+            user_balance: &UserBalance,
+        ) -> Self {
+            let (user_shares, user_tokens, user_q4w) = user_state(pool_balance, user_balance);
+            let (lp_blnd, lp_usdc) = lp_token_val(e);
+            BackstopQueueWithdrawalInfo {
+                pool: pool.clone(),
+                user: user.clone(),
+                shares,
+                expiration,
+                user_shares,
+                user_tokens,
+                user_q4w,
+                pool_shares: pool_balance.shares,
+                pool_tokens: pool_balance.tokens,
+                pool_q4w: pool_balance.q4w,
+                q4w_pct: pool_balance.q4w_pct(),
+                lp_blnd,
+                lp_usdc,
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+    }
+
+    impl BackstopDequeueWithdrawalInfo {
+        pub fn new(
+            e: &Env,
+            pool: &Address,
+            user: &Address,
+            shares: i128,
+            pool_balance: &PoolBalance,
+            user_balance: &UserBalance,
+        ) -> Self {
+            let (user_shares, user_tokens, user_q4w) = user_state(pool_balance, user_balance);
+            let (lp_blnd, lp_usdc) = lp_token_val(e);
+            BackstopDequeueWithdrawalInfo {
+                pool: pool.clone(),
+                user: user.clone(),
+                shares,
+                user_shares,
+                user_tokens,
+                user_q4w,
+                pool_shares: pool_balance.shares,
+                pool_tokens: pool_balance.tokens,
+                pool_q4w: pool_balance.q4w,
+                q4w_pct: pool_balance.q4w_pct(),
+                lp_blnd,
+                lp_usdc,
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+    }
+
+    impl BackstopWithdrawInfo {
+        pub fn new(
+            e: &Env,
+            pool: &Address,
+            user: &Address,
+            shares: i128,
+            amount: i128,
+            pool_balance: &PoolBalance,
+            user_balance: &UserBalance,
+        ) -> Self {
+            let (user_shares, user_tokens, user_q4w) = user_state(pool_balance, user_balance);
+            let (lp_blnd, lp_usdc) = lp_token_val(e);
+            BackstopWithdrawInfo {
+                pool: pool.clone(),
+                user: user.clone(),
+                shares,
+                amount,
+                user_shares,
+                user_tokens,
+                user_q4w,
+                pool_shares: pool_balance.shares,
+                pool_tokens: pool_balance.tokens,
+                pool_q4w: pool_balance.q4w,
+                q4w_pct: pool_balance.q4w_pct(),
+                lp_blnd,
+                lp_usdc,
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+    }
+
+    impl BackstopDrawInfo {
+        pub fn new(
+            e: &Env,
+            pool: &Address,
+            to: &Address,
+            amount: i128,
+            pool_balance: &PoolBalance,
+        ) -> Self {
+            let (lp_blnd, lp_usdc) = lp_token_val(e);
+            BackstopDrawInfo {
+                pool: pool.clone(),
+                to: to.clone(),
+                amount,
+                pool_shares: pool_balance.shares,
+                pool_tokens: pool_balance.tokens,
+                pool_q4w: pool_balance.q4w,
+                q4w_pct: pool_balance.q4w_pct(),
+                lp_blnd,
+                lp_usdc,
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+    }
+
+    impl BackstopDonateInfo {
+        pub fn new(
+            e: &Env,
+            pool: &Address,
+            from: &Address,
+            amount: i128,
+            pool_balance: &PoolBalance,
+        ) -> Self {
+            let (lp_blnd, lp_usdc) = lp_token_val(e);
+            BackstopDonateInfo {
+                pool: pool.clone(),
+                from: from.clone(),
+                amount,
+                pool_shares: pool_balance.shares,
+                pool_tokens: pool_balance.tokens,
+                pool_q4w: pool_balance.q4w,
+                q4w_pct: pool_balance.q4w_pct(),
+                lp_blnd,
+                lp_usdc,
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+    }
+
+    // the Retroshade host function only exists in the indexing VM, so native builds drop the events
+    #[cfg(any(test, feature = "testutils"))]
+    macro_rules! impl_native_emit {
+        ($($name:ident),*) => {
+            $(
+                impl $name {
+                    pub fn emit(&self, _e: &soroban_sdk::Env) {}
+                }
+            )*
+        };
+    }
+
+    #[cfg(any(test, feature = "testutils"))]
+    impl_native_emit!(
+        BackstopDepositInfo,
+        BackstopQueueWithdrawalInfo,
+        BackstopDequeueWithdrawalInfo,
+        BackstopWithdrawInfo,
+        BackstopDrawInfo,
+        BackstopDonateInfo
+    );
 }
 
 /// ### Backstop
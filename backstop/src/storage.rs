@@ -502,6 +502,14 @@ pub fn get_lp_token_val(e: &Env) -> (i128, i128) {
         .unwrap_optimized()
 }
 
+/// Check if the token value for the LP pool has been set
+#[cfg(feature = "retroshades")]
+pub fn has_lp_token_val(e: &Env) -> bool {
+    e.storage()
+        .persistent()
+        .has(&Symbol::new(e, LP_TOKEN_VAL_KEY))
+}
+
 /// Set the reward zone
 ///
 /// ### Arguments
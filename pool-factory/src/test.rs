@@ -11,6 +11,15 @@ mod pool {
     soroban_sdk::contractimport!(file = "../target/wasm32-unknown-unknown/optimized/pool.wasm");
 }
 
+fn default_auction_curve() -> pool::AuctionCurve {
+    pool::AuctionCurve {
+        ramp: 200,
+        decay: 200,
+        step: 1,
+        start_premium: None,
+    }
+}
+
 fn create_pool_factory(e: &Env) -> (Address, PoolFactoryClient) {
     let contract_id = e.register_contract(None, PoolFactoryContract {});
     (contract_id.clone(), PoolFactoryClient::new(e, &contract_id))
@@ -104,7 +113,20 @@ fn test_pool_factory() {
                 oracle: oracle,
                 bstop_rate: backstop_rate,
                 status: 6,
-                max_positions: 6
+                max_positions: 6,
+                max_price_age: 86400,
+                auction_config: pool::AuctionConfig {
+                    liquidation: default_auction_curve(),
+                    bad_debt: default_auction_curve(),
+                    interest: default_auction_curve(),
+                },
+                status_config: pool::StatusConfig {
+                    on_ice_q4w: 0_3000000,
+                    frozen_q4w: 0_6000000,
+                    admin_on_ice_q4w: 0_5000000,
+                    admin_frozen_q4w: 0_7500000,
+                    backstop_pc: 200_000,
+                },
             }
         );
         assert_eq!(
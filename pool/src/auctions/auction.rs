@@ -1,20 +1,24 @@
 use crate::{
-    constants::SCALAR_7,
+    constants::{MAX_LIQUIDATION_BATCH, SCALAR_7},
     errors::PoolError,
     pool::{Pool, User},
-    reflector_oracle, storage,
+    storage::{self, AuctionConfig, AuctionCurve},
+};
+#[cfg(feature = "retroshades")]
+use crate::{
+    reflector_oracle,
+    retroshades::{AuctionCreatedInfo, AuctionDeletedInfo, AuctionFilledInfo},
 };
 use cast::i128;
 use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::{
-    contracttype, map, panic_with_error, token::TokenClient, unwrap::UnwrapOptimized, Address, Env,
-    Map, String, Vec,
+    contracttype, map, panic_with_error, unwrap::UnwrapOptimized, Address, Env, Map, String, Vec,
 };
 
 use super::{
     backstop_interest_auction::{create_interest_auction_data, fill_interest_auction},
     bad_debt_auction::{create_bad_debt_auction_data, fill_bad_debt_auction},
-    user_liquidation_auction::{create_user_liq_auction_data, fill_user_liq_auction},
+    user_liquidation_auction::{fill_user_liq_auction, try_create_user_liq_auction_data},
 };
 
 #[derive(Clone, PartialEq)]
@@ -34,6 +38,15 @@ impl AuctionType {
             _ => panic_with_error!(e, PoolError::BadRequest),
         }
     }
+
+    /// Fetch the auction curve for the auction type from the pool's auction config
+    pub fn curve(&self, config: &AuctionConfig) -> AuctionCurve {
+        match self {
+            AuctionType::UserLiquidation => config.liquidation.clone(),
+            AuctionType::BadDebtAuction => config.bad_debt.clone(),
+            AuctionType::InterestAuction => config.interest.clone(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -42,6 +55,7 @@ pub struct AuctionData {
     pub bid: Map<Address, i128>,
     pub lot: Map<Address, i128>,
     pub block: u32,
+    pub curve: AuctionCurve,
 }
 
 /// Create a bad debt auction. Stores the resulting auction to the ledger to begin on the next block
@@ -60,6 +74,13 @@ pub fn create_bad_debt_auction(e: &Env) -> AuctionData {
         &backstop,
         &auction_data,
     );
+    #[cfg(feature = "retroshades")]
+    emit_auction_created(
+        e,
+        AuctionType::BadDebtAuction as u32,
+        &backstop,
+        &auction_data,
+    );
 
     auction_data
 }
@@ -83,27 +104,75 @@ pub fn create_interest_auction(e: &Env, assets: &Vec<Address>) -> AuctionData {
         &backstop,
         &auction_data,
     );
+    #[cfg(feature = "retroshades")]
+    emit_auction_created(
+        e,
+        AuctionType::InterestAuction as u32,
+        &backstop,
+        &auction_data,
+    );
 
     auction_data
 }
 
 /// Create a liquidation auction. Stores the resulting auction to the ledger to begin on the next block
 ///
-/// Returns the AuctionData object created.
+/// Returns the percent of the user's liabilities liquidated and the AuctionData object created.
 ///
 /// ### Arguments
 /// * `user` - The user being liquidated
-/// * `liq_data` - The liquidation metadata
+/// * `percent_liquidated` - The percent of the user's liabilities being liquidated, or 0 to
+///                          compute the smallest valid percent
 ///
 /// ### Panics
 /// If the auction is unable to be created
-pub fn create_liquidation(e: &Env, user: &Address, percent_liquidated: u64) -> AuctionData {
-    let user_clone = user.clone();
-    if user_clone == e.current_contract_address() || user_clone == storage::get_backstop(e) {
-        panic_with_error!(e, PoolError::InvalidLiquidation);
+pub fn create_liquidation(e: &Env, user: &Address, percent_liquidated: u64) -> (u64, AuctionData) {
+    match try_create_liquidation(e, user, percent_liquidated) {
+        Ok(liquidation) => liquidation,
+        Err(error) => panic_with_error!(e, error),
+    }
+}
+
+/// Create liquidation auctions for a batch of users. Stores the resulting auctions to the ledger
+/// to begin on the next block. Users that cannot be liquidated, such as healthy users or users
+/// with an auction in progress, are skipped. Prices are not checked up front, so a stale or
+/// missing price for any reserve held by a user in the batch fails the whole batch.
+///
+/// Returns a map of the liquidated users to the AuctionData objects created.
+///
+/// ### Arguments
+/// * `users` - The users being liquidated and the percent of their liabilities being liquidated,
+///             or 0 to compute the smallest valid percent
+///
+/// ### Panics
+/// If no users or more than `MAX_LIQUIDATION_BATCH` users are supplied, or a price needed to value
+/// a user's positions is stale or missing
+pub fn create_liquidations(e: &Env, users: &Vec<(Address, u64)>) -> Map<Address, AuctionData> {
+    if users.is_empty() || users.len() > MAX_LIQUIDATION_BATCH {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+
+    let mut auctions = map![e];
+    for (user, percent_liquidated) in users.iter() {
+        if let Ok((_, auction_data)) = try_create_liquidation(e, &user, percent_liquidated) {
+            auctions.set(user, auction_data);
+        }
     }
+    auctions
+}
 
-    let auction_data = create_user_liq_auction_data(e, user, percent_liquidated);
+/// Create a liquidation auction, or return the reason the user cannot be liquidated
+fn try_create_liquidation(
+    e: &Env,
+    user: &Address,
+    percent_liquidated: u64,
+) -> Result<(u64, AuctionData), PoolError> {
+    if *user == e.current_contract_address() || *user == storage::get_backstop(e) {
+        return Err(PoolError::InvalidLiquidation);
+    }
+
+    let (percent_liquidated, auction_data) =
+        try_create_user_liq_auction_data(e, user, percent_liquidated)?;
 
     storage::set_auction(
         e,
@@ -111,46 +180,65 @@ pub fn create_liquidation(e: &Env, user: &Address, percent_liquidated: u64) -> A
         user,
         &auction_data,
     );
+    #[cfg(feature = "retroshades")]
+    emit_auction_created(e, AuctionType::UserLiquidation as u32, user, &auction_data);
 
-    auction_data
+    Ok((percent_liquidated, auction_data))
 }
 
-/// Delete a liquidation auction if the user being liquidated
+/// Delete a liquidation auction, or return the reason it cannot be deleted
 ///
 /// NOTE: Does not verify if the user's positions are healthy. This must be done before calling.
 ///
 /// ### Arguments
-/// * `auction_type` - The type of auction being created
-///
-/// ### Panics
-/// If no auction exists for the user
-pub fn delete_liquidation(e: &Env, user: &Address) {
+/// * `user` - The user being liquidated
+/// * `preview` - If the deletion is only being previewed, and nothing should be deleted
+pub fn delete_liquidation(e: &Env, user: &Address, preview: bool) -> Result<(), PoolError> {
     if !storage::has_auction(e, &(AuctionType::UserLiquidation as u32), user) {
-        panic_with_error!(e, PoolError::BadRequest);
+        return Err(PoolError::BadRequest);
+    }
+    if preview {
+        return Ok(());
     }
     storage::del_auction(e, &(AuctionType::UserLiquidation as u32), user);
+
+    #[cfg(feature = "retroshades")]
+    AuctionDeletedInfo {
+        pool: e.current_contract_address(),
+        auction_type: AuctionType::UserLiquidation as u32,
+        user: user.clone(),
+        ledger: e.ledger().sequence(),
+        timestamp: e.ledger().timestamp(),
+    }
+    .emit(e);
+    Ok(())
 }
 
 #[derive(Clone)]
 pub(crate) struct AuctionResult {
     pub to_fill_auction_bid_assets: Vec<Address>,
     pub to_fill_auction_bid_amounts: Vec<i128>,
+    #[cfg(feature = "retroshades")]
     pub to_fill_auction_bid_usdc_amounts: Vec<i128>,
     pub to_fill_auction_lot_assets: Vec<Address>,
     pub to_fill_auction_lot_amounts: Vec<i128>,
+    #[cfg(feature = "retroshades")]
     pub to_fill_auction_lot_usdc_amounts: Vec<i128>,
     pub remaining_auction_bid_assets: Vec<Address>,
     pub remaining_auction_bid_amounts: Vec<i128>,
+    #[cfg(feature = "retroshades")]
     pub remaining_auction_bid_usdc_amounts: Vec<i128>,
     pub remaining_auction_lot_assets: Vec<Address>,
     pub remaining_auction_lot_amounts: Vec<i128>,
+    #[cfg(feature = "retroshades")]
     pub remaining_auction_lot_usdc_amounts: Vec<i128>,
     pub backstop_balance_change: i128,
     pub asset_changes: Vec<Address>,
     pub amount_changes: Vec<i128>,
 }
 
-/// Fills the auction from the invoker.
+/// Fills the auction from the invoker, or returns the reason the auction cannot be filled.
+/// Filling against a preview pool only updates the filler's positions and the cached reserves.
 ///
 /// ### Arguments
 /// * `pool` - The pool
@@ -160,8 +248,7 @@ pub(crate) struct AuctionResult {
 /// * `percent_filled` - The percentage being filled as a number (i.e. 15 => 15%)
 ///
 /// ### Panics
-/// If the auction does not exist, or if the pool is unable to fulfill either side
-/// of the auction quote
+/// If the pool is unable to fulfill either side of the auction quote
 pub fn fill(
     e: &Env,
     pool: &mut Pool,
@@ -169,9 +256,18 @@ pub fn fill(
     user: &Address,
     filler_state: &mut User,
     percent_filled: u64,
-) -> AuctionResult {
+) -> Result<AuctionResult, PoolError> {
     if user.clone() == filler_state.address {
-        panic_with_error!(e, PoolError::InvalidLiquidation);
+        return Err(PoolError::InvalidLiquidation);
+    }
+    if auction_type != AuctionType::UserLiquidation as u32
+        && filler_state.address == storage::get_backstop(e)
+    {
+        return Err(PoolError::BadRequest);
+    }
+    if !storage::has_auction(e, &auction_type, user) || percent_filled > 100 || percent_filled == 0
+    {
+        return Err(PoolError::BadRequest);
     }
 
     let auction_data = storage::get_auction(e, &auction_type, user);
@@ -192,24 +288,30 @@ pub fn fill(
             }
         };
 
-    if let Some(auction_to_store) = remaining_auction.clone() {
-        storage::set_auction(e, &auction_type, user, &auction_to_store);
-    } else {
-        storage::del_auction(e, &auction_type, user);
+    if !pool.is_preview() {
+        if let Some(auction_to_store) = remaining_auction.clone() {
+            storage::set_auction(e, &auction_type, user, &auction_to_store);
+        } else {
+            storage::del_auction(e, &auction_type, user);
+        }
     }
 
     let mut result = AuctionResult {
         to_fill_auction_bid_assets: Vec::new(&e),
         to_fill_auction_bid_amounts: Vec::new(&e),
+        #[cfg(feature = "retroshades")]
         to_fill_auction_bid_usdc_amounts: Vec::new(&e),
         to_fill_auction_lot_assets: Vec::new(&e),
         to_fill_auction_lot_amounts: Vec::new(&e),
+        #[cfg(feature = "retroshades")]
         to_fill_auction_lot_usdc_amounts: Vec::new(&e),
         remaining_auction_bid_assets: Vec::new(&e),
         remaining_auction_bid_amounts: Vec::new(&e),
+        #[cfg(feature = "retroshades")]
         remaining_auction_bid_usdc_amounts: Vec::new(&e),
         remaining_auction_lot_assets: Vec::new(&e),
         remaining_auction_lot_amounts: Vec::new(&e),
+        #[cfg(feature = "retroshades")]
         remaining_auction_lot_usdc_amounts: Vec::new(&e),
         backstop_balance_change,
         asset_changes,
@@ -219,67 +321,123 @@ pub fn fill(
     for (asset, amount) in to_fill_auction.bid.iter() {
         result.to_fill_auction_bid_assets.push_back(asset.clone());
         result.to_fill_auction_bid_amounts.push_back(amount);
-
-        let usdc_amount = reflector_oracle::get_token_amount_in_usdc_value(
-            &e,
-            &pool.config.oracle,
-            &TokenClient::new(&e, &asset),
-            amount,
+        #[cfg(feature = "retroshades")]
+        result.to_fill_auction_bid_usdc_amounts.push_back(
+            reflector_oracle::get_token_amount_in_usdc_value(e, &asset, amount),
         );
-        result
-            .to_fill_auction_bid_usdc_amounts
-            .push_back(usdc_amount);
     }
 
     for (asset, amount) in to_fill_auction.lot.iter() {
         result.to_fill_auction_lot_assets.push_back(asset.clone());
         result.to_fill_auction_lot_amounts.push_back(amount);
-        let usdc_amount = reflector_oracle::get_token_amount_in_usdc_value(
-            &e,
-            &pool.config.oracle,
-            &TokenClient::new(&e, &asset),
-            amount,
+        #[cfg(feature = "retroshades")]
+        result.to_fill_auction_lot_usdc_amounts.push_back(
+            reflector_oracle::get_token_amount_in_usdc_value(e, &asset, amount),
         );
-        result
-            .to_fill_auction_lot_usdc_amounts
-            .push_back(usdc_amount);
     }
 
     let remaining = remaining_auction.unwrap_or(AuctionData {
         bid: map![e],
         lot: map![e],
         block: auction_data.block,
+        curve: auction_data.curve.clone(),
     });
 
     for (asset, amount) in remaining.bid.iter() {
         result.remaining_auction_bid_assets.push_back(asset.clone());
         result.remaining_auction_bid_amounts.push_back(amount);
-        let usdc_amount = reflector_oracle::get_token_amount_in_usdc_value(
-            &e,
-            &pool.config.oracle,
-            &TokenClient::new(&e, &asset),
-            amount,
+        #[cfg(feature = "retroshades")]
+        result.remaining_auction_bid_usdc_amounts.push_back(
+            reflector_oracle::get_token_amount_in_usdc_value(e, &asset, amount),
         );
-        result
-            .remaining_auction_bid_usdc_amounts
-            .push_back(usdc_amount);
     }
 
     for (asset, amount) in remaining.lot.iter() {
         result.remaining_auction_lot_assets.push_back(asset.clone());
         result.remaining_auction_lot_amounts.push_back(amount);
-        let usdc_amount = reflector_oracle::get_token_amount_in_usdc_value(
-            &e,
-            &pool.config.oracle,
-            &TokenClient::new(&e, &asset),
-            amount,
+        #[cfg(feature = "retroshades")]
+        result.remaining_auction_lot_usdc_amounts.push_back(
+            reflector_oracle::get_token_amount_in_usdc_value(e, &asset, amount),
         );
-        result
-            .remaining_auction_lot_usdc_amounts
-            .push_back(usdc_amount);
     }
 
-    result
+    #[cfg(feature = "retroshades")]
+    if !pool.is_preview() {
+        let (bid_modifier, lot_modifier) = get_block_modifiers(e, &auction_data);
+        AuctionFilledInfo {
+            pool: e.current_contract_address(),
+            auction_type,
+            user: user.clone(),
+            filler: filler_state.address.clone(),
+            percent_filled,
+            auction_block: auction_data.block,
+            bid_modifier,
+            lot_modifier,
+            backstop_balance_change: result.backstop_balance_change,
+            asset_changes: result.asset_changes.clone(),
+            amount_changes: result.amount_changes.clone(),
+            to_fill_bid_assets: result.to_fill_auction_bid_assets.clone(),
+            to_fill_bid_amounts: result.to_fill_auction_bid_amounts.clone(),
+            to_fill_bid_usdc_amounts: result.to_fill_auction_bid_usdc_amounts.clone(),
+            to_fill_lot_assets: result.to_fill_auction_lot_assets.clone(),
+            to_fill_lot_amounts: result.to_fill_auction_lot_amounts.clone(),
+            to_fill_lot_usdc_amounts: result.to_fill_auction_lot_usdc_amounts.clone(),
+            remaining_bid_assets: result.remaining_auction_bid_assets.clone(),
+            remaining_bid_amounts: result.remaining_auction_bid_amounts.clone(),
+            remaining_bid_usdc_amounts: result.remaining_auction_bid_usdc_amounts.clone(),
+            remaining_lot_assets: result.remaining_auction_lot_assets.clone(),
+            remaining_lot_amounts: result.remaining_auction_lot_amounts.clone(),
+            remaining_lot_usdc_amounts: result.remaining_auction_lot_usdc_amounts.clone(),
+            ledger: e.ledger().sequence(),
+            timestamp: e.ledger().timestamp(),
+        }
+        .emit(e);
+    }
+
+    Ok(result)
+}
+
+/// Determine the block based auction modifiers as (bid_modifier, lot_modifier) in 7 decimals
+/// from the auction's curve
+///
+/// ### Arguments
+/// * `auction_data` - The auction data
+pub(crate) fn get_block_modifiers(e: &Env, auction_data: &AuctionData) -> (i128, i128) {
+    let bid_modifier: i128;
+    let lot_modifier: i128;
+    let curve = &auction_data.curve;
+    let ramp = i128(curve.ramp);
+    let decay = i128(curve.decay);
+    let block_dif = i128(e.ledger().sequence() - auction_data.block);
+    // modifiers only move once every `step` blocks
+    let block_dif = block_dif - block_dif % i128(curve.step);
+
+    if block_dif >= ramp {
+        // lot 100%, bid scaling down from 100% to 0%
+        lot_modifier = SCALAR_7;
+        if block_dif - ramp < decay {
+            bid_modifier = SCALAR_7
+                - (block_dif - ramp)
+                    .fixed_mul_floor(SCALAR_7, decay)
+                    .unwrap_optimized();
+        } else {
+            bid_modifier = 0;
+        }
+    } else {
+        // lot scaling from the start premium to 100%, bid 100%
+        let lot_start = match curve.start_premium {
+            Some(premium) => SCALAR_7
+                .fixed_div_floor(SCALAR_7 + i128(premium), SCALAR_7)
+                .unwrap_optimized(),
+            None => 0,
+        };
+        lot_modifier = lot_start
+            + (SCALAR_7 - lot_start)
+                .fixed_mul_floor(block_dif, ramp)
+                .unwrap_optimized();
+        bid_modifier = SCALAR_7;
+    }
+    (bid_modifier, lot_modifier)
 }
 
 /// Scale the auction based on the percent being filled and the amount of blocks that have passed
@@ -309,31 +467,15 @@ pub(crate) fn scale_auction(
         bid: map![e],
         lot: map![e],
         block: auction_data.block,
+        curve: auction_data.curve.clone(),
     };
     let mut remaining_auction = AuctionData {
         bid: map![e],
         lot: map![e],
         block: auction_data.block,
+        curve: auction_data.curve.clone(),
     };
-    // determine block based auction modifiers
-    let bid_modifier: i128;
-    let lot_modifier: i128;
-    let per_block_scalar: i128 = 0_0050000; // modifier moves 0.5% every block
-    let block_dif = i128(e.ledger().sequence() - auction_data.block);
-
-    if block_dif > 200 {
-        // lot 100%, bid scaling down from 100% to 0%
-        lot_modifier = SCALAR_7;
-        if block_dif < 400 {
-            bid_modifier = SCALAR_7 - (block_dif - 200) * per_block_scalar;
-        } else {
-            bid_modifier = 0;
-        }
-    } else {
-        // lot scaling from 0% to 100%, bid 100%
-        lot_modifier = block_dif * per_block_scalar;
-        bid_modifier = SCALAR_7;
-    }
+    let (bid_modifier, lot_modifier) = get_block_modifiers(e, auction_data);
 
     // scale the auction
     let percent_filled_i128 = i128(percent_filled) * 1_00000; // scale to decimal form in 7 decimals from percentage
@@ -381,6 +523,30 @@ pub(crate) fn scale_auction(
     }
 }
 
+/// Emit the Retroshade for a newly created auction
+#[cfg(feature = "retroshades")]
+fn emit_auction_created(e: &Env, auction_type: u32, user: &Address, auction_data: &AuctionData) {
+    let (bid_assets, bid_amounts, bid_usdc_amounts) =
+        reflector_oracle::split_amounts_with_usdc_value(e, &auction_data.bid);
+    let (lot_assets, lot_amounts, lot_usdc_amounts) =
+        reflector_oracle::split_amounts_with_usdc_value(e, &auction_data.lot);
+    AuctionCreatedInfo {
+        pool: e.current_contract_address(),
+        auction_type,
+        user: user.clone(),
+        bid_assets,
+        bid_amounts,
+        bid_usdc_amounts,
+        lot_assets,
+        lot_amounts,
+        lot_usdc_amounts,
+        auction_block: auction_data.block,
+        ledger: e.ledger().sequence(),
+        timestamp: e.ledger().timestamp(),
+    }
+    .emit(e);
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -522,13 +688,29 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: AuctionConfig {
+                liquidation: Default::default(),
+                bad_debt: AuctionCurve {
+                    ramp: 50,
+                    decay: 100,
+                    step: 5,
+                    start_premium: None,
+                },
+                interest: Default::default(),
+            },
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
             storage::set_user_positions(&e, &backstop_address, &positions);
 
-            create_bad_debt_auction(&e);
+            let auction_data = create_bad_debt_auction(&e);
             assert!(storage::has_auction(&e, &1, &backstop_address));
+            assert_eq!(auction_data.curve.ramp, 50);
+            assert_eq!(auction_data.curve.decay, 100);
+            assert_eq!(auction_data.curve.step, 5);
+            assert_eq!(auction_data.curve.start_premium, None);
         });
     }
 
@@ -632,6 +814,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -737,6 +922,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_backstop(&e, &Address::generate(&e));
@@ -749,6 +937,283 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_create_liquidations() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 50,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_100_000_000;
+        reserve_config_0.c_factor = 0_8500000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_data_1.b_rate = 1_200_000_000;
+        reserve_config_1.c_factor = 0_7500000;
+        reserve_config_1.l_factor = 0_7500000;
+        reserve_data_1.last_time = 12345;
+        reserve_config_1.index = 1;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_2, reserve_data_2) = testutils::default_reserve_meta();
+        reserve_config_2.c_factor = 0_0000000;
+        reserve_config_2.l_factor = 0_7000000;
+        reserve_config_2.index = 2;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_2,
+            &reserve_config_2,
+            &reserve_data_2,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0),
+                Asset::Stellar(underlying_1),
+                Asset::Stellar(underlying_2),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 2_0000000, 4_0000000, 50_0000000]);
+
+        let liq_pct = 45;
+        let positions: Positions = Positions {
+            collateral: map![
+                &e,
+                (reserve_config_0.index, 90_9100000),
+                (reserve_config_1.index, 04_5800000),
+            ],
+            liabilities: map![&e, (reserve_config_2.index, 02_7500000),],
+            supply: map![&e],
+        };
+        let healthy_positions: Positions = Positions {
+            collateral: map![&e, (reserve_config_0.index, 90_9100000),],
+            liabilities: map![&e, (reserve_config_2.index, 00_5000000),],
+            supply: map![&e],
+        };
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_backstop(&e, &Address::generate(&e));
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_user_positions(&e, &frodo, &healthy_positions);
+            storage::set_pool_config(&e, &pool_config);
+
+            e.budget().reset_unlimited();
+            let auctions = create_liquidations(
+                &e,
+                &vec![&e, (frodo.clone(), liq_pct), (samwise.clone(), liq_pct)],
+            );
+            assert_eq!(auctions.len(), 1);
+            assert!(auctions.contains_key(samwise.clone()));
+            assert!(storage::has_auction(&e, &0, &samwise));
+            assert!(!storage::has_auction(&e, &0, &frodo));
+
+            // users with an auction in progress are skipped
+            let auctions = create_liquidations(&e, &vec![&e, (samwise.clone(), liq_pct)]);
+            assert_eq!(auctions.len(), 0);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1210)")]
+    fn test_create_liquidations_stale_price_fails_batch() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 50,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_100_000_000;
+        reserve_config_0.c_factor = 0_8500000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_data_1.b_rate = 1_200_000_000;
+        reserve_config_1.c_factor = 0_7500000;
+        reserve_config_1.l_factor = 0_7500000;
+        reserve_data_1.last_time = 12345;
+        reserve_config_1.index = 1;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_2, reserve_data_2) = testutils::default_reserve_meta();
+        reserve_config_2.c_factor = 0_0000000;
+        reserve_config_2.l_factor = 0_7000000;
+        reserve_config_2.index = 2;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_2,
+            &reserve_config_2,
+            &reserve_data_2,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0),
+                Asset::Stellar(underlying_1),
+                Asset::Stellar(underlying_2),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 2_0000000, 4_0000000, 50_0000000], &12345);
+
+        let liq_pct = 45;
+        let positions: Positions = Positions {
+            collateral: map![
+                &e,
+                (reserve_config_0.index, 90_9100000),
+                (reserve_config_1.index, 04_5800000),
+            ],
+            liabilities: map![&e, (reserve_config_2.index, 02_7500000),],
+            supply: map![&e],
+        };
+        let healthy_positions: Positions = Positions {
+            collateral: map![&e, (reserve_config_0.index, 90_9100000),],
+            liabilities: map![&e, (reserve_config_2.index, 00_5000000),],
+            supply: map![&e],
+        };
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_backstop(&e, &Address::generate(&e));
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_user_positions(&e, &frodo, &healthy_positions);
+            storage::set_pool_config(&e, &pool_config);
+
+            // a stale price for a reserve held by any user fails the whole batch
+            e.ledger().with_mut(|li| li.timestamp += 86400 + 1);
+            e.budget().reset_unlimited();
+            create_liquidations(
+                &e,
+                &vec![&e, (frodo.clone(), liq_pct), (samwise.clone(), liq_pct)],
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_create_liquidations_empty_batch() {
+        let e = Env::default();
+        let pool_address = create_pool(&e);
+
+        e.as_contract(&pool_address, || {
+            create_liquidations(&e, &vec![&e]);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_create_liquidations_over_max_batch() {
+        let e = Env::default();
+        let pool_address = create_pool(&e);
+
+        let mut users = vec![&e];
+        for _ in 0..(MAX_LIQUIDATION_BATCH + 1) {
+            users.push_back((Address::generate(&e), 50));
+        }
+        e.as_contract(&pool_address, || {
+            create_liquidations(&e, &users);
+        });
+    }
+
     #[test]
     #[should_panic(expected = "Error(Contract, #1211)")]
     fn test_create_liquidation_for_pool() {
@@ -843,6 +1308,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_backstop(&e, &Address::generate(&e));
@@ -948,6 +1416,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_backstop(&e, &backstop);
@@ -970,6 +1441,7 @@ mod tests {
             bid: map![&e],
             lot: map![&e],
             block: 100,
+            curve: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_auction(
@@ -979,7 +1451,7 @@ mod tests {
                 &auction_data,
             );
 
-            delete_liquidation(&e, &samwise);
+            delete_liquidation(&e, &samwise, false).unwrap();
             assert!(!storage::has_auction(
                 &e,
                 &(AuctionType::UserLiquidation as u32),
@@ -989,7 +1461,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1200)")]
     fn test_delete_user_liquidation_does_not_exist() {
         let e = Env::default();
         e.mock_all_auths();
@@ -998,7 +1469,8 @@ mod tests {
         let samwise = Address::generate(&e);
 
         e.as_contract(&pool_id, || {
-            delete_liquidation(&e, &samwise);
+            let result = delete_liquidation(&e, &samwise, false);
+            assert_eq!(result.err(), Some(PoolError::BadRequest));
         });
     }
 
@@ -1070,12 +1542,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1104,7 +1580,7 @@ mod tests {
             e.budget().reset_unlimited();
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 100);
+            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 100).unwrap();
             let has_auction = storage::has_auction(&e, &0, &samwise);
             assert_eq!(has_auction, false);
         });
@@ -1178,12 +1654,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1212,7 +1692,7 @@ mod tests {
             e.budget().reset_unlimited();
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 25);
+            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 25).unwrap();
 
             let expected_new_auction_data = AuctionData {
                 bid: map![&e, (underlying_2.clone(), 9281250)],
@@ -1222,6 +1702,7 @@ mod tests {
                     (underlying_1.clone(), 1_1546805)
                 ],
                 block: 176,
+                curve: Default::default(),
             };
             let new_auction = storage::get_auction(&e, &0, &samwise);
             assert_eq!(new_auction.bid, expected_new_auction_data.bid);
@@ -1299,12 +1780,16 @@ mod tests {
                 (underlying_1.clone(), 1_000_0000)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1333,7 +1818,7 @@ mod tests {
             });
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 25);
+            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 25).unwrap();
 
             let expected_new_auction_data = AuctionData {
                 bid: map![&e, (underlying_2.clone(), 75_000_0000)],
@@ -1343,6 +1828,7 @@ mod tests {
                     (underlying_1.clone(), 750_0000)
                 ],
                 block: 176,
+                curve: Default::default(),
             };
 
             // Partial fill 2 - 66% @ 100% mods
@@ -1363,7 +1849,7 @@ mod tests {
             });
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 67);
+            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 67).unwrap();
 
             let expected_new_auction_data = AuctionData {
                 bid: map![&e, (underlying_2.clone(), 24_7500000)],
@@ -1373,6 +1859,7 @@ mod tests {
                     (underlying_1.clone(), 0_2475000)
                 ],
                 block: 176,
+                curve: Default::default(),
             };
             let new_auction = storage::get_auction(&e, &0, &samwise);
             assert_eq!(new_auction.bid, expected_new_auction_data.bid);
@@ -1392,7 +1879,7 @@ mod tests {
             });
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 100);
+            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 100).unwrap();
             let new_auction = storage::has_auction(&e, &0, &samwise);
             assert_eq!(new_auction, false);
             let samwise_positions = storage::get_user_positions(&e, &samwise);
@@ -1421,7 +1908,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1200)")]
     fn test_fill_fails_pct_too_large() {
         let e = Env::default();
 
@@ -1488,12 +1974,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1522,26 +2012,18 @@ mod tests {
             e.budget().reset_unlimited();
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 101);
+            let result = fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 101);
+            assert_eq!(result.err(), Some(PoolError::BadRequest));
 
-            let expected_new_auction_data = AuctionData {
-                bid: map![&e, (underlying_2.clone(), 9281250)],
-                lot: map![
-                    &e,
-                    (underlying_0.clone(), 22_9196497),
-                    (underlying_1.clone(), 1_1546805)
-                ],
-                block: 176,
-            };
+            // the auction is unchanged
             let new_auction = storage::get_auction(&e, &0, &samwise);
-            assert_eq!(new_auction.bid, expected_new_auction_data.bid);
-            assert_eq!(new_auction.lot, expected_new_auction_data.lot);
-            assert_eq!(new_auction.block, expected_new_auction_data.block);
+            assert_eq!(new_auction.bid, auction_data.bid);
+            assert_eq!(new_auction.lot, auction_data.lot);
+            assert_eq!(new_auction.block, auction_data.block);
         });
     }
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1200)")]
     fn test_fill_fails_pct_too_small() {
         let e = Env::default();
 
@@ -1610,12 +2092,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1644,21 +2130,14 @@ mod tests {
             e.budget().reset_unlimited();
             let mut pool = Pool::load(&e);
             let mut frodo_state = User::load(&e, &frodo);
-            fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 0);
+            let result = fill(&e, &mut pool, 0, &samwise, &mut frodo_state, 0);
+            assert_eq!(result.err(), Some(PoolError::BadRequest));
 
-            let expected_new_auction_data = AuctionData {
-                bid: map![&e, (underlying_2.clone(), 9281250)],
-                lot: map![
-                    &e,
-                    (underlying_0.clone(), 22_9196497),
-                    (underlying_1.clone(), 1_1546805)
-                ],
-                block: 176,
-            };
+            // the auction is unchanged
             let new_auction = storage::get_auction(&e, &0, &samwise);
-            assert_eq!(new_auction.bid, expected_new_auction_data.bid);
-            assert_eq!(new_auction.lot, expected_new_auction_data.lot);
-            assert_eq!(new_auction.block, expected_new_auction_data.block);
+            assert_eq!(new_auction.bid, auction_data.bid);
+            assert_eq!(new_auction.lot, auction_data.lot);
+            assert_eq!(new_auction.block, auction_data.block);
         });
     }
 
@@ -1673,6 +2152,7 @@ mod tests {
             bid: map![&e, (underlying_0.clone(), 100_0000000)],
             lot: map![&e, (underlying_1.clone(), 100_0000000)],
             block: 1000,
+            curve: Default::default(),
         };
 
         // 0 blocks
@@ -1781,7 +2261,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1211)")]
     fn test_fill_liquidation_same_address() {
         let e = Env::default();
 
@@ -1848,12 +2327,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1882,7 +2365,8 @@ mod tests {
             e.budget().reset_unlimited();
             let mut pool = Pool::load(&e);
             let mut samwise_state = User::load(&e, &samwise);
-            fill(&e, &mut pool, 0, &samwise, &mut samwise_state, 100);
+            let result = fill(&e, &mut pool, 0, &samwise, &mut samwise_state, 100);
+            assert_eq!(result.err(), Some(PoolError::InvalidLiquidation));
         });
     }
 
@@ -1898,6 +2382,7 @@ mod tests {
             bid: map![&e, (underlying_0.clone(), 25_0000005)],
             lot: map![&e, (underlying_1.clone(), 25_0000005)],
             block: 1000,
+            curve: Default::default(),
         };
 
         // 0 blocks
@@ -2028,6 +2513,7 @@ mod tests {
             bid: map![&e, (underlying_0.clone(), 25_0000005)],
             lot: map![&e, (underlying_1.clone(), 25_0000005)],
             block: 1000,
+            curve: Default::default(),
         };
 
         // 0 blocks
@@ -2056,6 +2542,7 @@ mod tests {
             bid: map![&e, (underlying_0.clone(), 25_0000005)],
             lot: map![&e, (underlying_1.clone(), 25_0000005)],
             block: 1000,
+            curve: Default::default(),
         };
 
         // 0 blocks
@@ -2072,4 +2559,99 @@ mod tests {
 
         let (_, _) = scale_auction(&e, &base_auction_data, 101);
     }
+
+    #[test]
+    fn test_scale_auction_custom_curve() {
+        let e = Env::default();
+        let underlying_0 = Address::generate(&e);
+        let underlying_1 = Address::generate(&e);
+
+        let base_auction_data = AuctionData {
+            bid: map![&e, (underlying_0.clone(), 100_0000000)],
+            lot: map![&e, (underlying_1.clone(), 100_0000000)],
+            block: 1000,
+            curve: AuctionCurve {
+                ramp: 100,
+                decay: 50,
+                step: 10,
+                start_premium: Some(0_2500000),
+            },
+        };
+        let mut ledger_info = LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 1000,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 172800,
+            min_persistent_entry_ttl: 172800,
+            max_entry_ttl: 9999999,
+        };
+
+        // 0 blocks - lot starts at 1 / (1 + premium)
+        e.ledger().set(ledger_info.clone());
+        let (scaled_auction, _) = scale_auction(&e, &base_auction_data, 100);
+        assert_eq!(
+            scaled_auction.bid.get_unchecked(underlying_0.clone()),
+            100_0000000
+        );
+        assert_eq!(
+            scaled_auction.lot.get_unchecked(underlying_1.clone()),
+            80_0000000
+        );
+        assert_eq!(scaled_auction.curve.ramp, 100);
+        assert_eq!(scaled_auction.curve.start_premium, Some(0_2500000));
+
+        // 15 blocks - modifiers move once every 10 blocks
+        ledger_info.sequence_number = 1015;
+        e.ledger().set(ledger_info.clone());
+        let (scaled_auction, remaining_auction) = scale_auction(&e, &base_auction_data, 50);
+        assert_eq!(
+            scaled_auction.bid.get_unchecked(underlying_0.clone()),
+            50_0000000
+        );
+        assert_eq!(
+            scaled_auction.lot.get_unchecked(underlying_1.clone()),
+            41_0000000
+        );
+        let remaining_auction = remaining_auction.unwrap();
+        assert_eq!(remaining_auction.curve.step, 10);
+        assert_eq!(remaining_auction.curve.decay, 50);
+
+        // 105 blocks
+        ledger_info.sequence_number = 1105;
+        e.ledger().set(ledger_info.clone());
+        let (scaled_auction, _) = scale_auction(&e, &base_auction_data, 100);
+        assert_eq!(
+            scaled_auction.bid.get_unchecked(underlying_0.clone()),
+            100_0000000
+        );
+        assert_eq!(
+            scaled_auction.lot.get_unchecked(underlying_1.clone()),
+            100_0000000
+        );
+
+        // 137 blocks
+        ledger_info.sequence_number = 1137;
+        e.ledger().set(ledger_info.clone());
+        let (scaled_auction, _) = scale_auction(&e, &base_auction_data, 100);
+        assert_eq!(
+            scaled_auction.bid.get_unchecked(underlying_0.clone()),
+            40_0000000
+        );
+        assert_eq!(
+            scaled_auction.lot.get_unchecked(underlying_1.clone()),
+            100_0000000
+        );
+
+        // 150 blocks
+        ledger_info.sequence_number = 1150;
+        e.ledger().set(ledger_info);
+        let (scaled_auction, _) = scale_auction(&e, &base_auction_data, 100);
+        assert_eq!(scaled_auction.bid.len(), 0);
+        assert_eq!(
+            scaled_auction.lot.get_unchecked(underlying_1.clone()),
+            100_0000000
+        );
+    }
 }
@@ -23,6 +23,7 @@ pub fn create_interest_auction_data(
         lot: map![e],
         bid: map![e],
         block: e.ledger().sequence() + 1,
+        curve: AuctionType::InterestAuction.curve(&pool.config.auction_config),
     };
 
     let mut interest_value = 0; // expressed in the oracle's decimals
@@ -85,11 +86,13 @@ pub fn fill_interest_auction(
     let backstop_token: Address = backstop_client.backstop_token();
     let backstop_token_bid_amount = auction_data.bid.get_unchecked(backstop_token);
 
-    backstop_client.donate(
-        &filler,
-        &e.current_contract_address(),
-        &backstop_token_bid_amount,
-    );
+    if !pool.is_preview() {
+        backstop_client.donate(
+            &filler,
+            &e.current_contract_address(),
+            &backstop_token_bid_amount,
+        );
+    }
 
     let mut assets = Vec::new(&e);
     let mut amounts = Vec::new(&e);
@@ -103,11 +106,13 @@ pub fn fill_interest_auction(
         assets.push_back(res_asset_address.clone());
         amounts.push_back(lot_amount);
 
-        TokenClient::new(e, &res_asset_address).transfer(
-            &e.current_contract_address(),
-            filler,
-            &lot_amount,
-        );
+        if !pool.is_preview() {
+            TokenClient::new(e, &res_asset_address).transfer(
+                &e.current_contract_address(),
+                filler,
+                &lot_amount,
+            );
+        }
     }
 
     (backstop_token_bid_amount, assets, amounts)
@@ -152,6 +157,7 @@ mod tests {
             bid: map![&e],
             lot: map![&e],
             block: 50,
+            curve: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_auction(
@@ -283,6 +289,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -390,6 +399,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -513,6 +525,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -631,6 +646,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -743,6 +761,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let mut auction_data = AuctionData {
             bid: map![&e, (backstop_token_id.clone(), 75_0000000)],
@@ -752,6 +773,7 @@ mod tests {
                 (underlying_1.clone(), 25_0000000)
             ],
             block: 51,
+            curve: Default::default(),
         };
         e.as_contract(&pool_address, || {
             e.mock_all_auths_allowing_non_root_auth();
@@ -846,6 +868,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let mut auction_data = AuctionData {
             bid: map![&e, (usdc_id.clone(), 95_0000000)],
@@ -855,6 +880,7 @@ mod tests {
                 (underlying_1.clone(), 25_0000000)
             ],
             block: 51,
+            curve: Default::default(),
         };
         usdc_client.mint(&samwise, &100_0000000);
         e.as_contract(&pool_address, || {
@@ -7,7 +7,12 @@ use crate::{
 };
 use cast::i128;
 use soroban_fixed_point_math::FixedPoint;
-use soroban_sdk::{map, panic_with_error, unwrap::UnwrapOptimized, Address, Env, Symbol, Vec};
+use soroban_sdk::{map, panic_with_error, unwrap::UnwrapOptimized, vec, Address, Env, Symbol, Vec};
+
+#[cfg(feature = "retroshades")]
+use crate::retroshades::BadDebtInfo;
+#[cfg(feature = "retroshades")]
+use soroban_sdk::symbol_short;
 
 use super::{AuctionData, AuctionType};
 
@@ -16,13 +21,14 @@ pub fn create_bad_debt_auction_data(e: &Env, backstop: &Address) -> AuctionData
         panic_with_error!(e, PoolError::AuctionInProgress);
     }
 
+    let mut pool = Pool::load(e);
     let mut auction_data = AuctionData {
         bid: map![e],
         lot: map![e],
         block: e.ledger().sequence() + 1,
+        curve: AuctionType::BadDebtAuction.curve(&pool.config.auction_config),
     };
 
-    let mut pool = Pool::load(e);
     let oracle_scalar = 10i128.pow(pool.load_price_decimals(e));
     let backstop_positions = storage::get_user_positions(e, backstop);
     let reserve_list = storage::get_res_list(e);
@@ -88,6 +94,13 @@ pub fn fill_bad_debt_auction(
     let backstop_client = BackstopClient::new(e, &backstop_address);
     let backstop_token_id = backstop_client.backstop_token();
     let lot_amount = auction_data.lot.get(backstop_token_id).unwrap_optimized();
+    if pool.is_preview() {
+        return (
+            lot_amount,
+            vec![e, e.current_contract_address()],
+            vec![e, 0],
+        );
+    }
     backstop_client.draw(
         &e.current_contract_address(),
         &lot_amount,
@@ -100,14 +113,26 @@ pub fn fill_bad_debt_auction(
     // If the backstop still has liabilities and less than 10% of the backstop threshold burn bad debt
     if !backstop_state.positions.liabilities.is_empty() {
         let pool_backstop_data = backstop_client.pool_data(&e.current_contract_address());
-        let threshold = calc_pool_backstop_threshold(&pool_backstop_data);
+        let threshold = calc_pool_backstop_threshold(
+            &pool_backstop_data,
+            pool.config.status_config.backstop_pc,
+        );
         if threshold < 0_0000003 {
             // ~5% of threshold
             let reserve_list = storage::get_res_list(e);
             let mut rm_liabilities = map![e];
+            #[cfg(feature = "retroshades")]
+            let mut bad_debt_info =
+                BadDebtInfo::new(e, &backstop_address, symbol_short!("socialize"));
             for (reserve_index, liability_balance) in backstop_state.positions.liabilities.iter() {
                 let res_asset_address = reserve_list.get_unchecked(reserve_index);
                 rm_liabilities.set(res_asset_address.clone(), liability_balance);
+                #[cfg(feature = "retroshades")]
+                bad_debt_info.push(
+                    e,
+                    &pool.load_reserve(e, &res_asset_address, false),
+                    liability_balance,
+                );
 
                 socialized_debt_assets.push_back(res_asset_address.clone());
                 socialized_debt_liabilities.push_back(liability_balance);
@@ -121,6 +146,9 @@ pub fn fill_bad_debt_auction(
             // remove liability debtTokens from backstop resulting in a shared loss for
             // token suppliers
             backstop_state.rm_positions(e, pool, map![e], rm_liabilities);
+
+            #[cfg(feature = "retroshades")]
+            bad_debt_info.emit(e);
         }
     }
     backstop_state.store(e);
@@ -209,6 +237,7 @@ mod tests {
             bid: map![&e],
             lot: map![&e],
             block: 50,
+            curve: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_auction(
@@ -340,6 +369,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -480,6 +512,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -614,6 +649,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -749,6 +787,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -857,11 +898,15 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let mut auction_data = AuctionData {
             bid: map![&e, (underlying_0, 10_0000000), (underlying_1, 2_5000000)],
             lot: map![&e, (lp_token.clone(), 47_6000000)],
             block: 51,
+            curve: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![&e],
@@ -1001,6 +1046,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let mut auction_data = AuctionData {
             bid: map![
@@ -1010,6 +1058,7 @@ mod tests {
             ],
             lot: map![&e, (lp_token.clone(), 47_6000000)],
             block: 51,
+            curve: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![&e],
@@ -1154,6 +1203,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let mut auction_data = AuctionData {
             bid: map![
@@ -1163,6 +1215,7 @@ mod tests {
             ],
             lot: map![&e, (lp_token.clone(), 47_6000000)],
             block: 51,
+            curve: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![&e],
@@ -1319,11 +1372,15 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let mut auction_data = AuctionData {
             bid: map![&e, (underlying_0, 10_0000000), (underlying_1, 2_5000000)],
             lot: map![&e, (lp_token.clone(), 47_6000000)],
             block: 51,
+            curve: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![&e],
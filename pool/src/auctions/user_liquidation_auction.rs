@@ -1,44 +1,57 @@
 use cast::i128;
 use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::unwrap::UnwrapOptimized;
-use soroban_sdk::{map, panic_with_error, vec, Address, Env, Vec};
+use soroban_sdk::{map, vec, Address, Env, Vec};
 
 use crate::auctions::auction::AuctionData;
-use crate::pool::{Pool, PositionData, User};
+use crate::constants::{MAX_POST_LIQ_HF, MIN_POST_LIQ_HF, SCALAR_7};
+use crate::pool::{load_user_emode, update_isolated_debt, Pool, PositionData, PriceMode, User};
+use crate::storage::EModeCategory;
 use crate::{errors::PoolError, storage};
 
 use super::AuctionType;
 
-pub fn create_user_liq_auction_data(
+/// Build the auction data for a user liquidation, or return the reason the user cannot be
+/// liquidated
+///
+/// Returns the percent of the user's liabilities being liquidated and the auction data
+///
+/// ### Arguments
+/// * `user` - The user being liquidated
+/// * `percent_liquidated` - The percent of the user's liabilities to liquidate (15 => 15%), or 0
+///                          to use the smallest percent that returns the user's health factor to
+///                          the post-liquidation target range
+pub fn try_create_user_liq_auction_data(
     e: &Env,
     user: &Address,
     percent_liquidated: u64,
-) -> AuctionData {
+) -> Result<(u64, AuctionData), PoolError> {
     if storage::has_auction(e, &(AuctionType::UserLiquidation as u32), user) {
-        panic_with_error!(e, PoolError::AuctionInProgress);
+        return Err(PoolError::AuctionInProgress);
     }
-    if percent_liquidated > 100 || percent_liquidated == 0 {
-        panic_with_error!(e, PoolError::InvalidLiquidation);
+    if percent_liquidated > 100 {
+        return Err(PoolError::InvalidLiquidation);
     }
 
-    let mut liquidation_quote = AuctionData {
-        bid: map![e],
-        lot: map![e],
-        block: e.ledger().sequence() + 1,
-    };
     let mut pool = Pool::load(e);
-
-    let mut user_state = User::load(e, user);
-    let reserve_list = storage::get_res_list(e);
-    let position_data = PositionData::calculate_from_positions(e, &mut pool, &user_state.positions);
+    let user_state = User::load(e, user);
+    let emode = load_user_emode(e, user);
+    let position_data = PositionData::calculate_from_positions(
+        e,
+        &mut pool,
+        &user_state.positions,
+        &emode,
+        PriceMode::Liquidation,
+    );
 
     // ensure the user has less collateral than liabilities
-    if position_data.liability_base < position_data.collateral_base {
-        panic_with_error!(e, PoolError::InvalidLiquidation);
+    // users without collateral have bad debt and cannot be liquidated
+    if position_data.liability_base < position_data.collateral_base
+        || position_data.collateral_raw == 0
+    {
+        return Err(PoolError::InvalidLiquidation);
     }
 
-    let percent_liquidated_i128_scaled = i128(percent_liquidated) * position_data.scalar / 100; // scale to decimal form with scalar decimals
-
     // ensure liquidation size is fair and the collateral is large enough to allow for the auction to price the liquidation
     let avg_cf = position_data
         .collateral_base
@@ -57,6 +70,129 @@ pub fn create_user_liq_auction_data(
     .unwrap_optimized()
         + position_data.scalar;
 
+    if percent_liquidated != 0 {
+        let liquidation_quote = build_user_liq_auction_data(
+            e,
+            &mut pool,
+            &user_state,
+            &emode,
+            &position_data,
+            est_incentive,
+            percent_liquidated,
+        )?;
+        return Ok((percent_liquidated, liquidation_quote));
+    }
+
+    // estimate the percent liquidated that returns the user to the minimum post-liq health factor.
+    // Liquidating a percent `p` of the liabilities removes `p * liability_base` of effective
+    // liabilities and an estimated `p * liability_raw * est_incentive * avg_cf` of effective
+    // collateral, so `p` is solved from the post-liq health factor.
+    let min_hf = position_data
+        .scalar
+        .fixed_mul_floor(MIN_POST_LIQ_HF, SCALAR_7)
+        .unwrap_optimized();
+    let target_liabilities = position_data
+        .liability_base
+        .fixed_mul_ceil(min_hf, position_data.scalar)
+        .unwrap_optimized();
+    let est_collateral_removed = position_data
+        .liability_raw
+        .fixed_mul_ceil(est_incentive, position_data.scalar)
+        .unwrap_optimized()
+        .fixed_mul_ceil(avg_cf, position_data.scalar)
+        .unwrap_optimized();
+    let mut percent = 100;
+    if target_liabilities > est_collateral_removed {
+        let est_percent = (target_liabilities - position_data.collateral_base)
+            .fixed_div_ceil(
+                target_liabilities - est_collateral_removed,
+                position_data.scalar,
+            )
+            .unwrap_optimized()
+            * 100;
+        let est_percent =
+            est_percent / position_data.scalar + (est_percent % position_data.scalar != 0) as i128;
+        // start one below the estimate to absorb rounding in the estimate
+        percent = (est_percent - 1).clamp(1, 100) as u64;
+    }
+
+    search_liquidation_percent(percent, |percent| {
+        build_user_liq_auction_data(
+            e,
+            &mut pool,
+            &user_state,
+            &emode,
+            &position_data,
+            est_incentive,
+            percent,
+        )
+    })
+}
+
+/// Binary search [1, 100] for the smallest percent liquidated that builds a valid liquidation,
+/// starting from an estimate. Percents below the valid range are too small and percents above it
+/// are too large, so the search builds at most 8 liquidations.
+///
+/// Returns the percent liquidated and the result of `build` for it, or the last error hit if no
+/// percent is valid
+///
+/// ### Arguments
+/// * `estimate` - The first percent to try
+/// * `build` - Builds the liquidation for a percent
+fn search_liquidation_percent<T>(
+    estimate: u64,
+    mut build: impl FnMut(u64) -> Result<T, PoolError>,
+) -> Result<(u64, T), PoolError> {
+    let mut low = 1;
+    let mut high = 100;
+    let mut percent = estimate.clamp(low, high);
+    let mut found = None;
+    let mut last_error = PoolError::InvalidLiqTooSmall;
+    loop {
+        match build(percent) {
+            Ok(quote) => {
+                found = Some((percent, quote));
+                high = percent - 1;
+            }
+            Err(PoolError::InvalidLiqTooSmall) => {
+                last_error = PoolError::InvalidLiqTooSmall;
+                low = percent + 1;
+            }
+            Err(PoolError::InvalidLiqTooLarge) => {
+                last_error = PoolError::InvalidLiqTooLarge;
+                high = percent - 1;
+            }
+            Err(error) => return Err(error),
+        }
+        if low > high {
+            break;
+        }
+        percent = low + (high - low) / 2;
+    }
+    found.ok_or(last_error)
+}
+
+/// Build the auction data for liquidating a percent of a user's liabilities, and validate the
+/// resulting positions
+fn build_user_liq_auction_data(
+    e: &Env,
+    pool: &mut Pool,
+    user_state: &User,
+    emode: &Option<EModeCategory>,
+    position_data: &PositionData,
+    est_incentive: i128,
+    percent_liquidated: u64,
+) -> Result<AuctionData, PoolError> {
+    let mut liquidation_quote = AuctionData {
+        bid: map![e],
+        lot: map![e],
+        block: e.ledger().sequence() + 1,
+        curve: AuctionType::UserLiquidation.curve(&pool.config.auction_config),
+    };
+    let reserve_list = storage::get_res_list(e);
+
+    let percent_liquidated_i128_scaled = i128(percent_liquidated) * position_data.scalar / 100; // scale to decimal form with scalar decimals
+
     let est_withdrawn_collateral = position_data
         .liability_raw
         .fixed_mul_floor(percent_liquidated_i128_scaled, position_data.scalar)
@@ -95,28 +231,35 @@ pub fn create_user_liq_auction_data(
     if percent_liquidated == 100 {
         // ensure that there isn't enough collateral to fill without fully liquidating
         if est_withdrawn_collateral < position_data.collateral_raw {
-            panic_with_error!(e, PoolError::InvalidLiqTooLarge);
+            return Err(PoolError::InvalidLiqTooLarge);
         }
     } else {
-        user_state.rm_positions(
+        let mut new_user_state = user_state.clone();
+        new_user_state.rm_positions(
             e,
-            &mut pool,
+            pool,
             liquidation_quote.lot.clone(),
             liquidation_quote.bid.clone(),
         );
-        let new_data = PositionData::calculate_from_positions(e, &mut pool, &user_state.positions);
+        let new_data = PositionData::calculate_from_positions(
+            e,
+            pool,
+            &new_user_state.positions,
+            emode,
+            PriceMode::Liquidation,
+        );
 
         // Post-liq health factor must be under 1.15
-        if new_data.is_hf_over(1_1500000) {
-            panic_with_error!(e, PoolError::InvalidLiqTooLarge)
+        if new_data.is_hf_over(MAX_POST_LIQ_HF) {
+            return Err(PoolError::InvalidLiqTooLarge);
         };
 
         // Post-liq heath factor must be over 1.03
-        if new_data.is_hf_under(1_0300000) {
-            panic_with_error!(e, PoolError::InvalidLiqTooSmall)
+        if new_data.is_hf_under(MIN_POST_LIQ_HF) {
+            return Err(PoolError::InvalidLiqTooSmall);
         };
     }
-    liquidation_quote
+    Ok(liquidation_quote)
 }
 
 pub fn fill_user_liq_auction(
@@ -130,7 +273,10 @@ pub fn fill_user_liq_auction(
 
     user_state.rm_positions(e, pool, auction_data.lot.clone(), auction_data.bid.clone());
     filler_state.add_positions(e, pool, auction_data.lot.clone(), auction_data.bid.clone());
-    user_state.store(e);
+    if !pool.is_preview() {
+        update_isolated_debt(e, user, pool, &user_state.positions);
+        user_state.store(e);
+    }
 
     (0, vec![&e, e.current_contract_address()], vec![&e, 0])
 }
@@ -141,7 +287,7 @@ mod tests {
     use crate::{
         auctions::auction::AuctionType,
         pool::Positions,
-        storage::{self, PoolConfig},
+        storage::{self, PoolConfig, ReservePriceConfig},
         testutils::{self, create_pool},
     };
 
@@ -153,7 +299,6 @@ mod tests {
     };
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1212)")]
     fn test_create_interest_auction_already_in_progress() {
         let e = Env::default();
         e.mock_all_auths();
@@ -180,12 +325,16 @@ mod tests {
             bid: map![&e],
             lot: map![&e],
             block: 50,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_pool_config(&e, &pool_config);
@@ -195,7 +344,8 @@ mod tests {
                 &samwise,
                 &auction_data,
             );
-            create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let result = try_create_user_liq_auction_data(&e, &samwise, liq_pct);
+            assert_eq!(result.err(), Some(PoolError::AuctionInProgress));
         });
     }
 
@@ -295,12 +445,16 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_user_positions(&e, &samwise, &positions);
             storage::set_pool_config(&e, &pool_config);
 
-            let result = create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let (_, result) =
+                try_create_user_liq_auction_data(&e, &samwise, liq_pct).unwrap_optimized();
             assert_eq!(result.block, 51);
             assert_eq!(result.bid.get_unchecked(underlying_2), 1_2375000);
             assert_eq!(result.bid.len(), 1);
@@ -387,12 +541,16 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_user_positions(&e, &samwise, &positions);
             storage::set_pool_config(&e, &pool_config);
 
-            let result = create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let (_, result) =
+                try_create_user_liq_auction_data(&e, &samwise, liq_pct).unwrap_optimized();
 
             assert_eq!(result.block, 51);
             assert_eq!(result.bid.get_unchecked(underlying_1), 731_0913452);
@@ -480,11 +638,110 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_address, || {
             storage::set_user_positions(&e, &samwise, &positions);
             storage::set_pool_config(&e, &pool_config);
-            let result = create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let (_, result) =
+                try_create_user_liq_auction_data(&e, &samwise, liq_pct).unwrap_optimized();
+            assert_eq!(result.block, 51);
+            assert_eq!(result.bid.get_unchecked(underlying_1), 10_0000000);
+            assert_eq!(result.bid.len(), 1);
+            assert_eq!(result.lot.get_unchecked(underlying_0), 8_0000000);
+            assert_eq!(result.lot.len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_create_user_liquidation_auction_auto_percent_full_liquidation() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 50,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_000_206_159;
+        reserve_config_0.c_factor = 0_9000000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_config_1.c_factor = 0_0000000;
+        reserve_config_1.l_factor = 0_9000000;
+        reserve_config_1.index = 1;
+        reserve_config_1.decimals = 6;
+        reserve_data_1.d_rate = 1000201748;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &5,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 1_00000, 1_00000]);
+
+        let positions: Positions = Positions {
+            collateral: map![&e, (reserve_config_0.index, 8_000_0000),],
+            liabilities: map![&e, (reserve_config_1.index, 100_000_000),],
+            supply: map![&e],
+        };
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_pool_config(&e, &pool_config);
+            let (liq_pct, result) =
+                try_create_user_liq_auction_data(&e, &samwise, 0).unwrap_optimized();
+            assert_eq!(liq_pct, 100);
             assert_eq!(result.block, 51);
             assert_eq!(result.bid.get_unchecked(underlying_1), 10_0000000);
             assert_eq!(result.bid.len(), 1);
@@ -494,7 +751,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1213)")]
     fn test_create_user_liquidation_auction_bad_full_liq() {
         let e = Env::default();
 
@@ -582,6 +838,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -596,11 +855,11 @@ mod tests {
             storage::set_user_positions(&e, &samwise, &positions);
             storage::set_pool_config(&e, &pool_config);
 
-            create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let result = try_create_user_liq_auction_data(&e, &samwise, liq_pct);
+            assert_eq!(result.err(), Some(PoolError::InvalidLiqTooLarge));
         });
     }
     #[test]
-    #[should_panic(expected = "Error(Contract, #1213)")]
     fn test_create_user_liquidation_auction_too_large() {
         let e = Env::default();
 
@@ -688,6 +947,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -702,12 +964,12 @@ mod tests {
             storage::set_user_positions(&e, &samwise, &positions);
             storage::set_pool_config(&e, &pool_config);
 
-            create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let result = try_create_user_liq_auction_data(&e, &samwise, liq_pct);
+            assert_eq!(result.err(), Some(PoolError::InvalidLiqTooLarge));
         });
     }
 
     #[test]
-    #[should_panic(expected = "Error(Contract, #1214)")]
     fn test_create_user_liquidation_auction_too_small() {
         let e = Env::default();
 
@@ -795,6 +1057,118 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let positions: Positions = Positions {
+            collateral: map![
+                &e,
+                (reserve_config_0.index, 90_9100000),
+                (reserve_config_1.index, 04_5800000),
+            ],
+            liabilities: map![&e, (reserve_config_2.index, 02_7500000),],
+            supply: map![&e],
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_pool_config(&e, &pool_config);
+
+            let result = try_create_user_liq_auction_data(&e, &samwise, liq_pct);
+            assert_eq!(result.err(), Some(PoolError::InvalidLiqTooSmall));
+        });
+    }
+
+    #[test]
+    fn test_create_user_liquidation_auction_auto_percent() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 50,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_100_000_000;
+        reserve_config_0.c_factor = 0_8500000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_data_1.b_rate = 1_200_000_000;
+        reserve_config_1.c_factor = 0_7500000;
+        reserve_config_1.l_factor = 0_7500000;
+        reserve_data_1.last_time = 12345;
+        reserve_config_1.index = 1;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_2, reserve_data_2) = testutils::default_reserve_meta();
+        reserve_config_2.c_factor = 0_0000000;
+        reserve_config_2.l_factor = 0_7000000;
+        reserve_config_2.index = 2;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_2,
+            &reserve_config_2,
+            &reserve_data_2,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0),
+                Asset::Stellar(underlying_1),
+                Asset::Stellar(underlying_2),
+            ],
+            &5,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 2_00000, 4_00000, 50_00000]);
+
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -809,7 +1183,155 @@ mod tests {
             storage::set_user_positions(&e, &samwise, &positions);
             storage::set_pool_config(&e, &pool_config);
 
-            create_user_liq_auction_data(&e, &samwise, liq_pct);
+            let (liq_pct, result) =
+                try_create_user_liq_auction_data(&e, &samwise, 0).unwrap_optimized();
+            assert_eq!(liq_pct, 26);
+            assert_eq!(result.block, 51);
+
+            // the chosen percent matches an explicit liquidation of the same size
+            let (explicit_pct, explicit_result) =
+                try_create_user_liq_auction_data(&e, &samwise, liq_pct).unwrap_optimized();
+            assert_eq!(explicit_pct, liq_pct);
+            assert_eq!(explicit_result.bid, result.bid);
+            assert_eq!(explicit_result.lot, result.lot);
+
+            // and is the smallest valid percent
+            let smaller = try_create_user_liq_auction_data(&e, &samwise, liq_pct - 1);
+            assert_eq!(smaller.err(), Some(PoolError::InvalidLiqTooSmall));
+        });
+    }
+
+    #[test]
+    fn test_create_user_liquidation_auction_twap_ignores_spot_drop() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 50,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_100_000_000;
+        reserve_config_0.c_factor = 0_8500000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_data_1.b_rate = 1_200_000_000;
+        reserve_config_1.c_factor = 0_7500000;
+        reserve_config_1.l_factor = 0_7500000;
+        reserve_data_1.last_time = 12345;
+        reserve_config_1.index = 1;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_2, reserve_data_2) = testutils::default_reserve_meta();
+        reserve_config_2.c_factor = 0_0000000;
+        reserve_config_2.l_factor = 0_7000000;
+        reserve_config_2.index = 2;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_2,
+            &reserve_config_2,
+            &reserve_data_2,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1),
+                Asset::Stellar(underlying_2),
+            ],
+            &5,
+            &300,
+        );
+        // the collateral price halves in the latest record
+        oracle_client.set_price(&vec![&e, 4_00000, 4_00000, 50_00000], &(12345 - 600));
+        oracle_client.set_price(&vec![&e, 4_00000, 4_00000, 50_00000], &(12345 - 300));
+        oracle_client.set_price(&vec![&e, 2_00000, 4_00000, 50_00000], &12345);
+
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let positions: Positions = Positions {
+            collateral: map![
+                &e,
+                (reserve_config_0.index, 90_9100000),
+                (reserve_config_1.index, 04_5800000),
+            ],
+            liabilities: map![&e, (reserve_config_2.index, 02_7500000),],
+            supply: map![&e],
+        };
+        let price_config = ReservePriceConfig {
+            max_age: 0,
+            oracles: vec![&e],
+            min_quorum: 1,
+            max_deviation: 0,
+            twap_records: 3,
+            twap_borrow: true,
+            twap_liquidation: false,
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_pool_config(&e, &pool_config);
+
+            // borrow TWAP pricing does not affect liquidations
+            storage::set_res_price_config(&e, &underlying_0, &price_config);
+            assert!(try_create_user_liq_auction_data(&e, &samwise, 0).is_ok());
+
+            // the collateral is valued at the TWAP of 4 instead of the spot price of 2
+            storage::set_res_price_config(
+                &e,
+                &underlying_0,
+                &ReservePriceConfig {
+                    twap_liquidation: true,
+                    ..price_config.clone()
+                },
+            );
+            let result = try_create_user_liq_auction_data(&e, &samwise, 0);
+            assert_eq!(result.err(), Some(PoolError::InvalidLiquidation));
         });
     }
 
@@ -907,12 +1429,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1081,12 +1607,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1115,10 +1645,68 @@ mod tests {
             let mut frodo_state = User::load(&e, &frodo);
             fill_user_liq_auction(&e, &mut pool, &mut auction_data, &samwise, &mut frodo_state);
             let samwise_positions = storage::get_user_positions(&e, &samwise);
-            let samwise_hf =
-                PositionData::calculate_from_positions(&e, &mut pool, &samwise_positions)
-                    .as_health_factor();
+            let samwise_hf = PositionData::calculate_from_positions(
+                &e,
+                &mut pool,
+                &samwise_positions,
+                &None,
+                PriceMode::Liquidation,
+            )
+            .as_health_factor();
             assert_eq!(samwise_hf, 1_1458977);
         });
     }
+
+    /// Build a liquidation that is too small under 30%, too large over 40%, and count the builds
+    fn build_in_range(builds: &mut u32, percent: u64) -> Result<u64, PoolError> {
+        *builds += 1;
+        if percent < 30 {
+            Err(PoolError::InvalidLiqTooSmall)
+        } else if percent > 40 {
+            Err(PoolError::InvalidLiqTooLarge)
+        } else {
+            Ok(percent)
+        }
+    }
+
+    #[test]
+    fn test_search_liquidation_percent_estimate_overshoots() {
+        let mut builds = 0;
+        let result = search_liquidation_percent(80, |percent| build_in_range(&mut builds, percent));
+        assert_eq!(result, Ok((30, 30)));
+        assert!(builds <= 8);
+
+        // an estimate inside the valid range still finds the smallest valid percent
+        let mut builds = 0;
+        let result = search_liquidation_percent(38, |percent| build_in_range(&mut builds, percent));
+        assert_eq!(result, Ok((30, 30)));
+        assert!(builds <= 8);
+    }
+
+    #[test]
+    fn test_search_liquidation_percent_estimate_undershoots() {
+        let mut builds = 0;
+        let result = search_liquidation_percent(2, |percent| build_in_range(&mut builds, percent));
+        assert_eq!(result, Ok((30, 30)));
+        assert!(builds <= 8);
+    }
+
+    #[test]
+    fn test_search_liquidation_percent_no_valid_percent() {
+        let mut builds = 0;
+        let result = search_liquidation_percent(50, |percent| {
+            builds += 1;
+            if percent < 50 {
+                Err::<u64, PoolError>(PoolError::InvalidLiqTooSmall)
+            } else {
+                Err(PoolError::InvalidLiqTooLarge)
+            }
+        });
+        assert!(result.is_err());
+        assert!(builds <= 8);
+
+        let result =
+            search_liquidation_percent(50, |_| Err::<u64, PoolError>(PoolError::StalePrice));
+        assert_eq!(result, Err(PoolError::StalePrice));
+    }
 }
@@ -0,0 +1,168 @@
+//! Catch-up execution mode.
+//!
+//! An indexer replaying historical ledgers only sees the entries in each transaction's
+//! footprint, so some invariants can't be evaluated during a replay. Every transaction being
+//! replayed already succeeded on chain, so the pool can skip them. When the `catchup` feature
+//! is enabled, each invariant listed in [`Relaxed`] is skipped instead of panicking, and a
+//! `catchup_relaxed` event is published for every skipped check. Without the feature every
+//! invariant is enforced.
+//!
+//! Health checks need oracle prices, which are not in a replayed footprint, so they are relaxed
+//! before the health factor is computed. A `catchup_relaxed` event is published for every
+//! health check, including ones that would have passed.
+
+use sep_41_token::TokenClient;
+use soroban_sdk::{Address, Env, Symbol};
+
+/// If the pool was built in catch-up mode
+pub const ENABLED: bool = cfg!(feature = "catchup");
+
+/// An invariant that is relaxed in catch-up mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Relaxed {
+    /// The health factor check after a submit, relaxed before any prices are loaded
+    HealthCheck,
+    /// A token transfer that failed
+    TokenTransfer,
+    /// The maximum number of positions a user can hold
+    MaxPositions,
+    /// A user emission index ahead of the reserve emission index
+    EmissionIndex,
+    /// A reserve emission update older than the last one
+    EmissionTime,
+    /// An interest accrual older than the last one
+    AccrualTime,
+    /// A flash loan that was not repaid
+    FlashLoanRepayment,
+}
+
+impl Relaxed {
+    fn name(&self) -> &'static str {
+        match self {
+            Relaxed::HealthCheck => "health_check",
+            Relaxed::TokenTransfer => "token_transfer",
+            Relaxed::MaxPositions => "max_positions",
+            Relaxed::EmissionIndex => "emission_index",
+            Relaxed::EmissionTime => "emission_time",
+            Relaxed::AccrualTime => "accrual_time",
+            Relaxed::FlashLoanRepayment => "flash_loan_repayment",
+        }
+    }
+}
+
+/// Relax an invariant if the pool is in catch-up mode.
+///
+/// Returns true and records the relaxation if the invariant should be skipped, false if
+/// it must be enforced.
+///
+/// ### Arguments
+/// * `invariant` - The invariant being relaxed
+pub fn relax(e: &Env, invariant: Relaxed) -> bool {
+    if ENABLED {
+        e.events().publish(
+            (Symbol::new(e, "catchup_relaxed"),),
+            Symbol::new(e, invariant.name()),
+        );
+    }
+    ENABLED
+}
+
+/// Transfer tokens. In catch-up mode a failed transfer is recorded instead of panicking.
+///
+/// ### Arguments
+/// * `token` - The address of the token
+/// * `from` - The address sending the tokens
+/// * `to` - The address receiving the tokens
+/// * `amount` - The amount of tokens to transfer
+pub fn transfer(e: &Env, token: &Address, from: &Address, to: &Address, amount: i128) {
+    let token_client = TokenClient::new(e, token);
+    if ENABLED {
+        if token_client.try_transfer(from, to, &amount).is_err() {
+            relax(e, Relaxed::TokenTransfer);
+        }
+    } else {
+        token_client.transfer(from, to, &amount);
+    }
+}
+
+/// Transfer tokens using an allowance. In catch-up mode a failed transfer is recorded
+/// instead of panicking.
+///
+/// ### Arguments
+/// * `token` - The address of the token
+/// * `spender` - The address spending the allowance
+/// * `from` - The address sending the tokens
+/// * `to` - The address receiving the tokens
+/// * `amount` - The amount of tokens to transfer
+pub fn transfer_from(
+    e: &Env,
+    token: &Address,
+    spender: &Address,
+    from: &Address,
+    to: &Address,
+    amount: i128,
+) {
+    let token_client = TokenClient::new(e, token);
+    if ENABLED {
+        if token_client
+            .try_transfer_from(spender, from, to, &amount)
+            .is_err()
+        {
+            relax(e, Relaxed::TokenTransfer);
+        }
+    } else {
+        token_client.transfer_from(spender, from, to, &amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils;
+    use soroban_sdk::{
+        testutils::{Address as _, Events},
+        vec, IntoVal,
+    };
+
+    #[test]
+    fn test_relax() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        e.as_contract(&pool, || {
+            assert_eq!(relax(&e, Relaxed::HealthCheck), ENABLED);
+        });
+        let events = e.events().all();
+        if ENABLED {
+            assert_eq!(
+                events,
+                vec![
+                    &e,
+                    (
+                        pool.clone(),
+                        (Symbol::new(&e, "catchup_relaxed"),).into_val(&e),
+                        Symbol::new(&e, "health_check").into_val(&e)
+                    )
+                ]
+            );
+        } else {
+            assert_eq!(events.len(), 0);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "catchup"), should_panic)]
+    fn test_transfer_failed() {
+        let e = Env::default();
+        e.mock_all_auths();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let (token, _) = testutils::create_token_contract(&e, &bombadil);
+
+        e.as_contract(&pool, || {
+            // samwise has no tokens to transfer
+            transfer(&e, &token, &samwise, &pool, 1_0000000);
+        });
+    }
+}
@@ -11,3 +11,76 @@ pub const SECONDS_PER_YEAR: i128 = 31536000;
 
 // approximate week in blocks assuming 5 seconds per block
 pub const SECONDS_PER_WEEK: u64 = 604800;
+
+// the default maximum age of an oracle price in seconds
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 24 * 60 * 60;
+
+// the maximum number of oracles a reserve can aggregate prices from
+pub const MAX_RESERVE_ORACLES: u32 = 5;
+
+// the maximum number of oracle records a reserve can average into a TWAP
+pub const MAX_TWAP_RECORDS: u32 = 12;
+
+// the maximum number of users that can be liquidated in a single batch
+pub const MAX_LIQUIDATION_BATCH: u32 = 10;
+
+// the minimum health factor of a user after a partial liquidation
+pub const MIN_POST_LIQ_HF: i128 = 1_0300000;
+
+// the maximum health factor of a user after a partial liquidation
+pub const MAX_POST_LIQ_HF: i128 = 1_1500000;
+
+// the maximum backstop product constant, in whole tokens, a pool can require
+pub const MAX_BACKSTOP_PC: i128 = 10_000_000;
+
+/********** Interest Rate Models **********/
+
+// the three kink curve scaled by a rate modifier that reacts to the utilization
+pub const RATE_MODEL_REACTIVE: u32 = 0;
+
+// a fixed rate of `r_base`, regardless of utilization
+pub const RATE_MODEL_FIXED: u32 = 1;
+
+// a two slope curve with a configurable kink
+pub const RATE_MODEL_TWO_SLOPE: u32 = 2;
+
+// the three kink curve without a rate modifier
+pub const RATE_MODEL_STATIC: u32 = 3;
+
+/********** Pool Status Triggers **********/
+
+// no backstop condition triggered, so the pool is active or kept its admin set status
+pub const STATUS_TRIGGER_NONE: u32 = 0;
+
+// the backstop is below the minimum deposit threshold
+pub const STATUS_TRIGGER_THRESHOLD: u32 = 1;
+
+// the backstop's queued withdrawals are over the on-ice limit
+pub const STATUS_TRIGGER_Q4W_ON_ICE: u32 = 2;
+
+// the backstop's queued withdrawals are over the frozen limit
+pub const STATUS_TRIGGER_Q4W_FROZEN: u32 = 3;
+
+/********** Reserve Flags **********/
+
+// disables borrowing from the reserve
+pub const RESERVE_BORROW_DISABLED: u32 = 1 << 0;
+
+// disables supplying to the reserve, both as supply and as collateral
+pub const RESERVE_SUPPLY_DISABLED: u32 = 1 << 1;
+
+// disables supplying the reserve as collateral
+pub const RESERVE_COLLATERAL_DISABLED: u32 = 1 << 2;
+
+// disables supplying to and borrowing from the reserve
+pub const RESERVE_FROZEN: u32 = 1 << 3;
+
+// set while the reserve is being sunset, which disables supplying to and borrowing from the
+// reserve and ramps its collateral factor down to zero. Cannot be set by the admin.
+pub const RESERVE_SUNSET: u32 = 1 << 4;
+
+// all valid reserve flags
+pub const RESERVE_FLAGS: u32 = RESERVE_BORROW_DISABLED
+    | RESERVE_SUPPLY_DISABLED
+    | RESERVE_COLLATERAL_DISABLED
+    | RESERVE_FROZEN;
@@ -1,17 +1,25 @@
+#[cfg(feature = "retroshades")]
+use crate::storage::{ReflectorFeeds, ReserveOracleConfig};
 use crate::{
     auctions::{self, AuctionData},
     emissions::{self, ReserveEmissionMetadata},
-    pool::{self, Positions, Request},
-    storage::{self, ReserveConfig},
+    pool::{self, FlashLoan, Positions, Request, ReserveView, SubmitPreview, UserPositionData},
+    storage::{self, EModeCategory, PoolConfig, ReserveConfig, ReservePriceConfig},
+};
+use soroban_sdk::{
+    contract, contractclient, contractimpl, vec, Address, Env, Map, String, Symbol, Vec,
 };
-use soroban_sdk::{contract, contractclient, contractimpl, vec, Address, Env, String, Symbol, Vec};
 
+#[cfg(feature = "retroshades")]
 pub(crate) mod retroshades {
+    #[cfg(not(any(test, feature = "testutils")))]
     use retroshade_sdk::Retroshade;
-    use soroban_sdk::{contracttype, Address, Symbol};
+    use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
+
+    use crate::{pool::Reserve, reflector_oracle};
 
     #[contracttype]
-    #[derive(Retroshade)]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
     pub struct CollateralActionInfo {
         pub pool: Address,
         pub reserve_address: Address,
@@ -35,7 +43,7 @@ pub(crate) mod retroshades {
     }
 
     #[contracttype]
-    #[derive(Retroshade)]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
     pub struct BorrowActionInfo {
         pub pool: Address,
         pub reserve_address: Address,
@@ -60,6 +68,139 @@ pub(crate) mod retroshades {
         pub ledger: u32,
         pub timestamp: u64,
     }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct AuctionCreatedInfo {
+        pub pool: Address,
+        pub auction_type: u32,
+        pub user: Address,
+
+        pub bid_assets: Vec<Address>,
+        pub bid_amounts: Vec<i128>,
+        pub bid_usdc_amounts: Vec<i128>,
+        pub lot_assets: Vec<Address>,
+        pub lot_amounts: Vec<i128>,
+        pub lot_usdc_amounts: Vec<i128>,
+        pub auction_block: u32,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct AuctionDeletedInfo {
+        pub pool: Address,
+        pub auction_type: u32,
+        pub user: Address,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct AuctionFilledInfo {
+        pub pool: Address,
+        pub auction_type: u32,
+        pub user: Address,
+        pub filler: Address,
+
+        pub percent_filled: u64,
+        pub auction_block: u32,
+        pub bid_modifier: i128,
+        pub lot_modifier: i128,
+
+        pub backstop_balance_change: i128,
+        pub asset_changes: Vec<Address>,
+        pub amount_changes: Vec<i128>,
+
+        pub to_fill_bid_assets: Vec<Address>,
+        pub to_fill_bid_amounts: Vec<i128>,
+        pub to_fill_bid_usdc_amounts: Vec<i128>,
+        pub to_fill_lot_assets: Vec<Address>,
+        pub to_fill_lot_amounts: Vec<i128>,
+        pub to_fill_lot_usdc_amounts: Vec<i128>,
+        pub remaining_bid_assets: Vec<Address>,
+        pub remaining_bid_amounts: Vec<i128>,
+        pub remaining_bid_usdc_amounts: Vec<i128>,
+        pub remaining_lot_assets: Vec<Address>,
+        pub remaining_lot_amounts: Vec<i128>,
+        pub remaining_lot_usdc_amounts: Vec<i128>,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    #[contracttype]
+    #[cfg_attr(not(any(test, feature = "testutils")), derive(Retroshade))]
+    pub struct BadDebtInfo {
+        pub pool: Address,
+        pub user: Address,
+        pub action_type: Symbol,
+
+        pub assets: Vec<Address>,
+        pub d_tokens: Vec<i128>,
+        pub amounts: Vec<i128>,
+        pub usdc_amounts: Vec<i128>,
+
+        pub ledger: u32,
+        pub timestamp: u64,
+    }
+
+    impl BadDebtInfo {
+        /// Create an empty record of bad debt moved away from a user
+        pub fn new(e: &Env, user: &Address, action_type: Symbol) -> Self {
+            BadDebtInfo {
+                pool: e.current_contract_address(),
+                user: user.clone(),
+                action_type,
+                assets: Vec::new(e),
+                d_tokens: Vec::new(e),
+                amounts: Vec::new(e),
+                usdc_amounts: Vec::new(e),
+                ledger: e.ledger().sequence(),
+                timestamp: e.ledger().timestamp(),
+            }
+        }
+
+        /// Record the bad debt of a reserve
+        pub fn push(&mut self, e: &Env, reserve: &Reserve, d_tokens: i128) {
+            let amount = reserve.to_asset_from_d_token(d_tokens);
+            self.assets.push_back(reserve.asset.clone());
+            self.d_tokens.push_back(d_tokens);
+            self.amounts.push_back(amount);
+            self.usdc_amounts
+                .push_back(reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve.asset,
+                    amount,
+                ));
+        }
+    }
+
+    // the Retroshade host function only exists in the indexing VM, so native builds drop the events
+    #[cfg(any(test, feature = "testutils"))]
+    macro_rules! impl_native_emit {
+        ($($name:ident),*) => {
+            $(
+                impl $name {
+                    pub fn emit(&self, _e: &soroban_sdk::Env) {}
+                }
+            )*
+        };
+    }
+
+    #[cfg(any(test, feature = "testutils"))]
+    impl_native_emit!(
+        CollateralActionInfo,
+        BorrowActionInfo,
+        AuctionCreatedInfo,
+        AuctionDeletedInfo,
+        AuctionFilledInfo,
+        BadDebtInfo
+    );
 }
 
 /// ### Pool
@@ -95,42 +236,105 @@ pub trait Pool {
         blnd_id: Address,
     );
 
-    /// (Admin only) Set a new address as the admin of this pool
+    /// (Admin only) Propose a new address as the admin of this pool. The admin is not changed
+    /// until the proposed address calls `accept_admin`.
+    ///
+    /// ### Arguments
+    /// * `new_admin` - The proposed admin address
+    ///
+    /// ### Panics
+    /// If the caller is not the admin
+    fn propose_admin(e: Env, new_admin: Address);
+
+    /// (Proposed admin only) Accept the proposal to become the admin of this pool
+    ///
+    /// ### Panics
+    /// If no admin has been proposed or the caller is not the proposed admin
+    fn accept_admin(e: Env);
+
+    /// (Admin only) Set a new address as the risk manager of this pool. The risk manager
+    /// manages reserve configs, caps, flags and efficiency mode categories.
+    ///
+    /// ### Arguments
+    /// * `risk_manager` - The new risk manager address
+    ///
+    /// ### Panics
+    /// If the caller is not the admin
+    fn set_risk_manager(e: Env, risk_manager: Address);
+
+    /// (Admin only) Set a new address as the emissions manager of this pool. The emissions
+    /// manager sets the emission configuration for the pool.
     ///
     /// ### Arguments
-    /// * `new_admin` - The new admin address
+    /// * `emissions_manager` - The new emissions manager address
     ///
     /// ### Panics
     /// If the caller is not the admin
-    fn set_admin(e: Env, new_admin: Address);
+    fn set_emissions_manager(e: Env, emissions_manager: Address);
 
-    /// (Admin only) Update the pool
+    /// (Admin only) Set a new address as the guardian of this pool. The guardian can only
+    /// freeze the pool with `freeze`.
     ///
     /// ### Arguments
-    /// * `backstop_take_rate` - The new take rate for the backstop (7 decimals)
-    /// * `max_positions` - The new maximum number of allowed positions for a single user's account
+    /// * `guardian` - The new guardian address
+    ///
+    /// ### Panics
+    /// If the caller is not the admin
+    fn set_guardian(e: Env, guardian: Address);
+
+    /// (Admin only) Queues an update to the pool's config, including the oracle, backstop take
+    /// rate, maximum positions, maximum price age, auction curves and status thresholds. The update
+    /// can be applied with `update_pool` after a week, or immediately while the pool is in setup.
+    /// The config's status is ignored.
+    ///
+    /// ### Arguments
+    /// * `config` - The new PoolConfig for the pool
+    ///
+    /// ### Panics
+    /// If the caller is not the admin, an update is already queued, the config is invalid, or the
+    /// new oracle does not report prices with the same decimals as the current oracle
+    ///
+    /// Maximum positions must be at least 2.
+    ///
+    /// Auction curves are invalid if the step is 0, the decay is 0, the ramp or decay is not a
+    /// multiple of the step, or a start premium is set without a ramp.
+    ///
+    /// Status thresholds are invalid if any q4w limit is 0 or over 100%, the on-ice limit is not
+    /// under the frozen limit, the backstop triggered limits are over the admin limits, or the
+    /// backstop product constant is 0 or over 10,000,000.
+    fn queue_update_pool(e: Env, config: PoolConfig);
+
+    /// (Admin only) Cancels the queued update to the pool's config
     ///
     /// ### Panics
     /// If the caller is not the admin
-    fn update_pool(e: Env, backstop_take_rate: u32, max_positions: u32);
+    fn cancel_update_pool(e: Env);
 
-    /// (Admin only) Queues setting data for a reserve in the pool
+    /// Executes the queued update to the pool's config
+    ///
+    /// Returns the new PoolConfig
+    ///
+    /// ### Panics
+    /// If no update is queued or the update is still timelocked
+    fn update_pool(e: Env) -> PoolConfig;
+
+    /// (Risk manager only) Queues setting data for a reserve in the pool
     ///
     /// ### Arguments
     /// * `asset` - The underlying asset to add as a reserve
     /// * `config` - The ReserveConfig for the reserve
     ///
     /// ### Panics
-    /// If the caller is not the admin
+    /// If the caller is not the risk manager
     fn queue_set_reserve(e: Env, asset: Address, metadata: ReserveConfig);
 
-    /// (Admin only) Cancels the queued set of a reserve in the pool
+    /// (Risk manager only) Cancels the queued set of a reserve in the pool
     ///
     /// ### Arguments
     /// * `asset` - The underlying asset to add as a reserve
     ///
     /// ### Panics
-    /// If the caller is not the admin or the reserve is not queued for initialization
+    /// If the caller is not the risk manager or the reserve is not queued for initialization
     fn cancel_set_reserve(e: Env, asset: Address);
 
     /// (Admin only) Executes the queued set of a reserve in the pool
@@ -144,15 +348,234 @@ pub trait Pool {
     /// or has invalid metadata
     fn set_reserve(e: Env, asset: Address) -> u32;
 
+    /// (Risk manager only) Set the price config for a reserve. If the config lists oracles, the
+    /// reserve is priced at the median of the fresh prices they report instead of by the pool's
+    /// oracle. If the config sets TWAP records, the TWAP flags opt the reserve into TWAP pricing:
+    /// * `twap_borrow` - borrow health checks value collateral at the lower and liabilities at the
+    ///                   higher of the spot price and the TWAP
+    /// * `twap_liquidation` - liquidation checks value collateral at the higher and liabilities at
+    ///                        the lower of the spot price and the TWAP, so a one-block spot move
+    ///                        cannot make a user liquidatable on its own
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    /// * `config` - The ReservePriceConfig for the reserve
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager, the reserve does not exist, or the config is invalid
+    fn set_reserve_price_config(e: Env, asset: Address, config: ReservePriceConfig);
+
+    /// (Risk manager only) Set the status flags for a reserve. Unlike reserve config updates, the
+    /// flags take effect immediately so a single reserve can be frozen in an emergency.
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    /// * `flags` - The status flags for the reserve, a bitmask where
+    ///             1 = borrowing disabled
+    ///             2 = supplying disabled
+    ///             4 = supplying collateral disabled
+    ///             8 = frozen, disabling supplying and borrowing
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager, the reserve does not exist, or the flags are invalid
+    fn set_reserve_flags(e: Env, asset: Address, flags: u32);
+
+    /// (Risk manager only) Queue a reserve to be sunset. Once the timelock passes, anyone can start
+    /// the sunset with `sunset_reserve`.
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    /// * `duration` - The time to ramp the reserve's collateral factor down to zero in seconds
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager, the reserve does not exist, the reserve is already
+    /// queued or being sunset, or the duration is zero
+    fn queue_sunset_reserve(e: Env, asset: Address, duration: u64);
+
+    /// (Risk manager only) Cancel a queued reserve sunset before it starts
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager, the reserve is not queued to be sunset, or the sunset
+    /// has already started
+    fn cancel_sunset_reserve(e: Env, asset: Address);
+
+    /// Start the sunset of a queued reserve. Supplying to and borrowing from the reserve is
+    /// disabled, and its collateral factor ramps down linearly to zero over the queued duration.
+    /// Positions that become unhealthy as the collateral factor ramps down can be liquidated by
+    /// anyone with `new_liquidation_auction`.
+    ///
+    /// Returns the time the collateral factor reaches zero
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    ///
+    /// ### Panics
+    /// If the reserve is not queued to be sunset, the sunset has already started, or the
+    /// timelock has not passed
+    fn sunset_reserve(e: Env, asset: Address) -> u64;
+
+    /// Retire a reserve whose sunset has ended. Positions in a retired reserve no longer count
+    /// towards the pool's maximum positions.
+    ///
+    /// Returns the index of the retired reserve
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    ///
+    /// ### Panics
+    /// If the reserve's sunset has not ended, or the reserve is already retired
+    fn retire_reserve(e: Env, asset: Address) -> u32;
+
+    /// (Risk manager only) Set an efficiency mode category of correlated reserves. Users that opt
+    /// into the category have its factors applied in place of each reserve's own factors, as long
+    /// as all of their collateral and liabilities are in the category.
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the category, starting at 1
+    /// * `category` - The factors and reserves of the category
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager, the id is 0, an asset is not a reserve, or the
+    /// factors are invalid or lower than the factors of a reserve in the category
+    fn set_emode_category(e: Env, id: u32, category: EModeCategory);
+
+    /// Fetch an efficiency mode category, if it exists
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the category
+    fn get_emode_category(e: Env, id: u32) -> Option<EModeCategory>;
+
+    /// Opt a user into an efficiency mode category, or out of one with an id of 0. While opted in,
+    /// the user cannot supply collateral or borrow from reserves outside of the category.
+    ///
+    /// ### Arguments
+    /// * `user` - The address of the user
+    /// * `id` - The id of the category, or 0 to opt out
+    ///
+    /// ### Panics
+    /// If the category does not exist, the user holds collateral or liabilities outside of the
+    /// category, or the user's health factor would be too low
+    fn set_emode(e: Env, user: Address, id: u32);
+
+    /// Fetch the efficiency mode category a user has opted into, or 0 if none
+    ///
+    /// ### Arguments
+    /// * `user` - The address of the user
+    fn get_emode(e: Env, user: Address) -> u32;
+
     /// Fetch the positions for an address
     ///
     /// ### Arguments
     /// * `address` - The address to fetch positions for
     fn get_positions(e: Env, address: Address) -> Positions;
 
+    /// Fetch a reserve's config and its data with interest accrued to the current ledger timestamp
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    ///
+    /// ### Panics
+    /// If the reserve does not exist
+    fn get_reserve(e: Env, asset: Address) -> ReserveView;
+
+    /// Fetch the underlying assets of the pool's reserves, ordered by reserve index
+    fn get_reserve_list(e: Env) -> Vec<Address>;
+
+    /// Fetch the pool's config
+    fn get_config(e: Env) -> PoolConfig;
+
+    /// Fetch a user's collateral and liabilities valued in the oracle's base asset, and their
+    /// health factor
+    ///
+    /// ### Arguments
+    /// * `user` - The address of the user
+    ///
+    /// ### Panics
+    /// If a price for one of the user's positions cannot be loaded
+    fn get_user_position_data(e: Env, user: Address) -> UserPositionData;
+
+    /// Fetch the emissions a user has accrued for each reserve token up to the current ledger
+    /// timestamp that have not been claimed
+    ///
+    /// ### Arguments
+    /// * `user` - The address of the user
+    /// * `reserve_token_ids` - The reserve tokens => (reserve index * 2 + (0 for debtToken or 1 for blendToken))
+    ///
+    /// ### Panics
+    /// If a reserve token does not exist
+    fn get_user_emissions(e: Env, user: Address, reserve_token_ids: Vec<u32>) -> Vec<i128>;
+
+    /// Approve an operator to manage all of the owner's positions until the expiration ledger.
+    /// The operator can submit any requests for the owner, including withdrawing the owner's
+    /// funds to any address.
+    ///
+    /// ### Arguments
+    /// * `owner` - The address whose positions can be managed
+    /// * `operator` - The address approved to manage the positions
+    /// * `expiration_ledger` - The last ledger the approval can be used
+    ///
+    /// ### Panics
+    /// If the owner is the operator, or the expiration ledger has passed or exceeds the maximum TTL
+    fn approve_operator(e: Env, owner: Address, operator: Address, expiration_ledger: u32);
+
+    /// Revoke an operator's approval to manage the owner's positions
+    ///
+    /// ### Arguments
+    /// * `owner` - The address whose positions can be managed
+    /// * `operator` - The address approved to manage the positions
+    fn revoke_operator(e: Env, owner: Address, operator: Address);
+
+    /// Check if an operator is approved to manage the owner's positions
+    ///
+    /// ### Arguments
+    /// * `owner` - The address whose positions can be managed
+    /// * `operator` - The address approved to manage the positions
+    fn is_operator(e: Env, owner: Address, operator: Address) -> bool;
+
+    /// Approve a delegatee to borrow up to an amount of a reserve against the owner's collateral
+    /// until the expiration ledger, replacing any existing allowance. The debt is taken on by the
+    /// owner. An amount of 0 revokes the allowance.
+    ///
+    /// ### Arguments
+    /// * `owner` - The address whose collateral backs the borrow
+    /// * `delegatee` - The address approved to borrow
+    /// * `asset` - The underlying asset of the reserve
+    /// * `amount` - The amount of underlying tokens that can be borrowed
+    /// * `expiration_ledger` - The last ledger the allowance can be used
+    ///
+    /// ### Panics
+    /// If the reserve does not exist, the amount is negative, or the expiration ledger has passed
+    /// or exceeds the maximum TTL
+    fn approve_borrow(
+        e: Env,
+        owner: Address,
+        delegatee: Address,
+        asset: Address,
+        amount: i128,
+        expiration_ledger: u32,
+    );
+
+    /// Fetch the amount of a reserve a delegatee can borrow against the owner's collateral
+    ///
+    /// ### Arguments
+    /// * `owner` - The address whose collateral backs the borrow
+    /// * `delegatee` - The address approved to borrow
+    /// * `asset` - The underlying asset of the reserve
+    fn get_borrow_allowance(e: Env, owner: Address, delegatee: Address, asset: Address) -> i128;
+
     /// Submit a set of requests to the pool where 'from' takes on the position, 'sender' sends any
     /// required tokens to the pool and 'to' receives any tokens sent from the pool
     ///
+    /// 'spender' must always authorize the submission. If 'from' is not 'spender', 'from' must also
+    /// authorize it, unless 'spender' is an approved operator of 'from', or the requests only
+    /// borrow reserves that 'from' has approved 'spender' to borrow.
+    ///
+    /// Fills of several users' liquidation auctions can be batched into a single submission. The
+    /// health of the filler is only checked once, after all requests are processed.
+    ///
     /// Returns the new positions for 'from'
     ///
     /// ### Arguments
@@ -171,13 +594,52 @@ pub trait Pool {
         requests: Vec<Request>,
     ) -> Positions;
 
+    /// Preview submitting a set of requests for 'from' without any transfers or storage writes.
+    /// Requests are processed in order until one fails.
+    ///
+    /// Returns the positions for 'from' and the token transfers after the requests that succeed,
+    /// the resulting health factor, and the index and error code of the first failed request
+    ///
+    /// ### Arguments
+    /// * `from` - The address of the user whose positions are being modified
+    /// * `requests` - A vec of requests to be processed
+    ///
+    /// ### Panics
+    /// If a price for one of the resulting positions cannot be loaded
+    fn preview_submit(e: Env, from: Address, requests: Vec<Request>) -> SubmitPreview;
+
+    /// Take a flash loan from the pool and submit a set of requests for 'from' before the loan
+    /// is repaid. The requests are processed with 'from' acting as the spender, and the loaned
+    /// tokens pay for the tokens the requests send to the pool before 'from' does. The rest of the
+    /// loan and any tokens the requests withdraw are sent to the flash loan contract, and then
+    /// `exec_op` is invoked on the flash loan contract, which must repay the loaned amount plus
+    /// the reserve's flash loan fee before returning. The flash loan contract cannot call back
+    /// into the pool.
+    ///
+    /// Returns the new positions for 'from'
+    ///
+    /// ### Arguments
+    /// * `from` - The address of the user taking the flash loan and whose positions are being modified
+    /// * `flash_loan` - The flash loan contract, asset, and amount to loan
+    /// * `requests` - A vec of requests to be processed
+    ///
+    /// ### Panics
+    /// If the loan exceeds the available liquidity, the requests are not able to be completed,
+    /// or the loan and fee are not repaid
+    fn flash_loan(
+        e: Env,
+        from: Address,
+        flash_loan: FlashLoan,
+        requests: Vec<Request>,
+    ) -> Positions;
+
     /// Manage bad debt. Debt is considered "bad" if there is no longer has any collateral posted.
     ///
     /// To manage a user's bad debt, all collateralized reserves for the user must be liquidated
     /// before debt can be transferred to the backstop.
     ///
-    /// To manage a backstop's bad debt, the backstop module must be below a critical threshold
-    /// to allow bad debt to be burnt.
+    /// Bad debt transferred to the backstop is auctioned off with `new_bad_debt_auction`, or burnt
+    /// with `default_bad_debt` once the backstop can no longer cover it.
     ///
     /// ### Arguments
     /// * `user` - The user who currently possesses bad debt
@@ -186,7 +648,28 @@ pub trait Pool {
     /// If the user has collateral posted
     fn bad_debt(e: Env, user: Address);
 
-    /// Update the pool status based on the backstop state - backstop triggered status' are odd numbers
+    /// (Risk manager only) Set the backstop token balance below which the backstop is considered
+    /// exhausted, allowing its bad debt to be defaulted with `default_bad_debt`. A floor of 0
+    /// disables defaulting.
+    ///
+    /// ### Arguments
+    /// * `floor` - The backstop token balance
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager or the floor is negative
+    fn set_bad_debt_floor(e: Env, floor: i128);
+
+    /// Default on the backstop's bad debt once the backstop's token balance for the pool falls
+    /// below the bad debt floor. All of the backstop's liabilities are burnt, and the loss is
+    /// socialized to each reserve's suppliers by reducing the reserve's b_rate.
+    ///
+    /// ### Panics
+    /// If the backstop holds at least the bad debt floor in tokens, the backstop does not have any
+    /// liabilities, or a bad debt auction is in progress
+    fn default_bad_debt(e: Env);
+
+    /// Update the pool status based on the backstop state - backstop triggered status' are odd numbers.
+    /// The limits below are the defaults, and are set per pool through the pool config.
     /// * 1 = backstop active - if the minimum backstop deposit has been reached
     ///                and 30% of backstop deposits are not queued for withdrawal
     ///                then all pool operations are permitted
@@ -198,15 +681,23 @@ pub trait Pool {
     ///                or 75% of backstop deposits are queued for withdrawal
     ///                then all borrowing, cancelling liquidations, and supplying are not permitted
     ///
+    /// Returns the new pool status and the backstop condition that triggered it
+    /// * 0 = none - the backstop is healthy, or the admin set status was kept
+    /// * 1 = threshold - the minimum backstop deposit has not been reached
+    /// * 2 = q4w on-ice - the backstop's queued withdrawals are over the on-ice limit
+    /// * 3 = q4w frozen - the backstop's queued withdrawals are over the frozen limit
+    ///
     /// ### Panics
     /// If the pool is currently on status 4, "admin-freeze", where only the admin
     /// can perform a status update via `set_status`
-    fn update_status(e: Env) -> u32;
+    fn update_status(e: Env) -> (u32, u32);
 
     /// (Admin only) Pool status is changed to "pool_status"
     /// * 0 = admin active - requires that the backstop threshold is met
-    ///                 and less than 50% of backstop deposits are queued for withdrawal
-    /// * 2 = admin on-ice - requires that less than 75% of backstop deposits are queued for withdrawal
+    ///                 and less than the admin on-ice limit (default 50%) of backstop deposits are
+    ///                 queued for withdrawal
+    /// * 2 = admin on-ice - requires that less than the admin frozen limit (default 75%) of backstop
+    ///                 deposits are queued for withdrawal
     /// * 4 = admin frozen - can always be set
     ///
     /// ### Arguments
@@ -217,6 +708,12 @@ pub trait Pool {
     /// If the specified conditions are not met for the status to be set
     fn set_status(e: Env, pool_status: u32);
 
+    /// (Guardian only) Pool status is changed to 4, "admin-freeze"
+    ///
+    /// ### Panics
+    /// If the caller is not the guardian
+    fn freeze(e: Env);
+
     /********* Emission Functions **********/
 
     /// Consume emissions from the backstop and distribute to the reserves based
@@ -225,7 +722,7 @@ pub trait Pool {
     /// Returns amount of new tokens emitted
     fn gulp_emissions(e: Env) -> i128;
 
-    /// (Admin only) Set the emission configuration for the pool
+    /// (Emissions manager only) Set the emission configuration for the pool
     ///
     /// Changes will be applied in the next pool `update_emissions`, and affect the next emission cycle
     ///
@@ -233,7 +730,7 @@ pub trait Pool {
     /// * `res_emission_metadata` - A vector of ReserveEmissionMetadata to update metadata to
     ///
     /// ### Panics
-    /// * If the caller is not the admin
+    /// * If the caller is not the emissions manager
     /// * If the sum of ReserveEmissionMetadata shares is greater than 1
     fn set_emissions_config(e: Env, res_emission_metadata: Vec<ReserveEmissionMetadata>);
 
@@ -253,12 +750,42 @@ pub trait Pool {
     ///
     /// ### Arguments
     /// * `user` - The user getting liquidated through the auction
-    /// * `percent_liquidated` - The percent of the user's position being liquidated as a percentage (15 => 15%)
+    /// * `percent_liquidated` - The percent of the user's position being liquidated as a percentage (15 => 15%),
+    ///                          or 0 to liquidate the smallest valid percentage
     ///
     /// ### Panics
     /// If the user liquidation auction was unable to be created
     fn new_liquidation_auction(e: Env, user: Address, percent_liquidated: u64) -> AuctionData;
 
+    /// Creates a new user liquidation auction for the smallest percentage of the user's position
+    /// that returns their health factor to the post-liquidation target range
+    ///
+    /// Returns the percentage liquidated (15 => 15%) and the auction created
+    ///
+    /// ### Arguments
+    /// * `user` - The user getting liquidated through the auction
+    ///
+    /// ### Panics
+    /// If the user liquidation auction was unable to be created
+    fn new_auto_liquidation_auction(e: Env, user: Address) -> (u64, AuctionData);
+
+    /// Creates user liquidation auctions for a batch of users
+    ///
+    /// Users that cannot be liquidated, such as healthy users or users with an auction already in
+    /// progress, are skipped. A stale or missing price for any reserve held by a user in the batch
+    /// fails the whole batch.
+    ///
+    /// Returns a map of each liquidated user to their new auction
+    ///
+    /// ### Arguments
+    /// * `users` - A vec of (user, percent_liquidated) pairs, with at most `MAX_LIQUIDATION_BATCH`
+    ///             entries. A percent_liquidated of 0 liquidates the smallest valid percentage.
+    ///
+    /// ### Panics
+    /// If the batch is empty or exceeds `MAX_LIQUIDATION_BATCH`, or a price needed to value a
+    /// user's positions is stale or missing
+    fn new_liquidation_auctions(e: Env, users: Vec<(Address, u64)>) -> Map<Address, AuctionData>;
+
     /// Fetch an auction from the ledger. Returns a quote based on the current block.
     ///
     /// ### Arguments
@@ -314,53 +841,115 @@ impl Pool for PoolContract {
         );
     }
 
-    fn set_admin(e: Env, new_admin: Address) {
+    fn propose_admin(e: Env, new_admin: Address) {
         storage::extend_instance(&e);
         let admin = storage::get_admin(&e);
         admin.require_auth();
-        new_admin.require_auth();
 
-        storage::set_admin(&e, &new_admin);
+        storage::set_pending_admin(&e, &new_admin);
+
+        e.events()
+            .publish((Symbol::new(&e, "propose_admin"), admin), new_admin);
+    }
+
+    fn accept_admin(e: Env) {
+        storage::extend_instance(&e);
+        let admin = storage::get_admin(&e);
+
+        let new_admin = pool::execute_accept_admin(&e);
 
         e.events()
             .publish((Symbol::new(&e, "set_admin"), admin), new_admin);
     }
 
-    fn update_pool(e: Env, backstop_take_rate: u32, max_positions: u32) {
+    fn set_risk_manager(e: Env, risk_manager: Address) {
+        storage::extend_instance(&e);
+        let admin = storage::get_admin(&e);
+        admin.require_auth();
+
+        storage::set_risk_manager(&e, &risk_manager);
+
+        e.events()
+            .publish((Symbol::new(&e, "set_risk_manager"), admin), risk_manager);
+    }
+
+    fn set_emissions_manager(e: Env, emissions_manager: Address) {
         storage::extend_instance(&e);
         let admin = storage::get_admin(&e);
         admin.require_auth();
 
-        pool::execute_update_pool(&e, backstop_take_rate, max_positions);
+        storage::set_emissions_manager(&e, &emissions_manager);
 
         e.events().publish(
-            (Symbol::new(&e, "update_pool"), admin),
-            (backstop_take_rate, max_positions),
+            (Symbol::new(&e, "set_emissions_manager"), admin),
+            emissions_manager,
         );
     }
 
-    fn queue_set_reserve(e: Env, asset: Address, metadata: ReserveConfig) {
+    fn set_guardian(e: Env, guardian: Address) {
+        storage::extend_instance(&e);
+        let admin = storage::get_admin(&e);
+        admin.require_auth();
+
+        storage::set_guardian(&e, &guardian);
+
+        e.events()
+            .publish((Symbol::new(&e, "set_guardian"), admin), guardian);
+    }
+
+    fn queue_update_pool(e: Env, config: PoolConfig) {
+        storage::extend_instance(&e);
+        let admin = storage::get_admin(&e);
+        admin.require_auth();
+
+        pool::execute_queue_update_pool(&e, &config);
+
+        e.events()
+            .publish((Symbol::new(&e, "queue_update_pool"), admin), config);
+    }
+
+    fn cancel_update_pool(e: Env) {
         storage::extend_instance(&e);
         let admin = storage::get_admin(&e);
         admin.require_auth();
 
+        pool::execute_cancel_queued_update_pool(&e);
+
+        e.events()
+            .publish((Symbol::new(&e, "cancel_update_pool"), admin), ());
+    }
+
+    fn update_pool(e: Env) -> PoolConfig {
+        storage::extend_instance(&e);
+        let new_config = pool::execute_update_pool(&e);
+
+        e.events()
+            .publish((Symbol::new(&e, "update_pool"),), new_config.clone());
+        new_config
+    }
+
+    fn queue_set_reserve(e: Env, asset: Address, metadata: ReserveConfig) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
         pool::execute_queue_set_reserve(&e, &asset, &metadata);
 
         e.events().publish(
-            (Symbol::new(&e, "queue_set_reserve"), admin),
+            (Symbol::new(&e, "queue_set_reserve"), risk_manager),
             (asset, metadata),
         );
     }
 
     fn cancel_set_reserve(e: Env, asset: Address) {
         storage::extend_instance(&e);
-        let admin = storage::get_admin(&e);
-        admin.require_auth();
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
 
         pool::execute_cancel_queued_set_reserve(&e, &asset);
 
         e.events()
-            .publish((Symbol::new(&e, "cancel_set_reserve"), admin), asset);
+            .publish((Symbol::new(&e, "cancel_set_reserve"), risk_manager), asset);
     }
 
     fn set_reserve(e: Env, asset: Address) -> u32 {
@@ -371,10 +960,179 @@ impl Pool for PoolContract {
         index
     }
 
+    fn set_reserve_price_config(e: Env, asset: Address, config: ReservePriceConfig) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_set_reserve_price_config(&e, &asset, &config);
+
+        e.events().publish(
+            (Symbol::new(&e, "set_reserve_price_config"), risk_manager),
+            (asset, config),
+        );
+    }
+
+    fn set_reserve_flags(e: Env, asset: Address, flags: u32) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_set_reserve_flags(&e, &asset, flags);
+
+        e.events().publish(
+            (Symbol::new(&e, "set_reserve_flags"), risk_manager),
+            (asset, flags),
+        );
+    }
+
+    fn queue_sunset_reserve(e: Env, asset: Address, duration: u64) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_queue_sunset_reserve(&e, &asset, duration);
+
+        e.events().publish(
+            (Symbol::new(&e, "queue_sunset_reserve"), risk_manager),
+            (asset, duration),
+        );
+    }
+
+    fn cancel_sunset_reserve(e: Env, asset: Address) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_cancel_sunset_reserve(&e, &asset);
+
+        e.events().publish(
+            (Symbol::new(&e, "cancel_sunset_reserve"), risk_manager),
+            asset,
+        );
+    }
+
+    fn sunset_reserve(e: Env, asset: Address) -> u64 {
+        storage::extend_instance(&e);
+        let end_time = pool::execute_sunset_reserve(&e, &asset);
+
+        e.events()
+            .publish((Symbol::new(&e, "sunset_reserve"),), (asset, end_time));
+        end_time
+    }
+
+    fn retire_reserve(e: Env, asset: Address) -> u32 {
+        storage::extend_instance(&e);
+        let index = pool::execute_retire_reserve(&e, &asset);
+
+        e.events()
+            .publish((Symbol::new(&e, "retire_reserve"),), (asset, index));
+        index
+    }
+
+    fn set_emode_category(e: Env, id: u32, category: EModeCategory) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_set_emode_category(&e, id, &category);
+
+        e.events().publish(
+            (Symbol::new(&e, "set_emode_category"), risk_manager),
+            (id, category),
+        );
+    }
+
+    fn get_emode_category(e: Env, id: u32) -> Option<EModeCategory> {
+        storage::get_emode_category(&e, id)
+    }
+
+    fn set_emode(e: Env, user: Address, id: u32) {
+        storage::extend_instance(&e);
+        user.require_auth();
+
+        pool::execute_set_user_emode(&e, &user, id);
+
+        e.events().publish((Symbol::new(&e, "set_emode"), user), id);
+    }
+
+    fn get_emode(e: Env, user: Address) -> u32 {
+        storage::get_user_emode(&e, &user)
+    }
+
     fn get_positions(e: Env, address: Address) -> Positions {
         storage::get_user_positions(&e, &address)
     }
 
+    fn get_reserve(e: Env, asset: Address) -> ReserveView {
+        pool::load_reserve_view(&e, &asset)
+    }
+
+    fn get_reserve_list(e: Env) -> Vec<Address> {
+        storage::get_res_list(&e)
+    }
+
+    fn get_config(e: Env) -> PoolConfig {
+        storage::get_pool_config(&e)
+    }
+
+    fn get_user_position_data(e: Env, user: Address) -> UserPositionData {
+        pool::load_user_position_data(&e, &user)
+    }
+
+    fn get_user_emissions(e: Env, user: Address, reserve_token_ids: Vec<u32>) -> Vec<i128> {
+        emissions::calc_accrued_emissions(&e, &user, &reserve_token_ids)
+    }
+
+    fn approve_operator(e: Env, owner: Address, operator: Address, expiration_ledger: u32) {
+        storage::extend_instance(&e);
+        owner.require_auth();
+
+        pool::execute_approve_operator(&e, &owner, &operator, expiration_ledger);
+
+        e.events().publish(
+            (Symbol::new(&e, "approve_operator"), owner, operator),
+            expiration_ledger,
+        );
+    }
+
+    fn revoke_operator(e: Env, owner: Address, operator: Address) {
+        storage::extend_instance(&e);
+        owner.require_auth();
+
+        pool::execute_revoke_operator(&e, &owner, &operator);
+
+        e.events()
+            .publish((Symbol::new(&e, "revoke_operator"), owner, operator), ());
+    }
+
+    fn is_operator(e: Env, owner: Address, operator: Address) -> bool {
+        pool::is_operator(&e, &owner, &operator)
+    }
+
+    fn approve_borrow(
+        e: Env,
+        owner: Address,
+        delegatee: Address,
+        asset: Address,
+        amount: i128,
+        expiration_ledger: u32,
+    ) {
+        storage::extend_instance(&e);
+        owner.require_auth();
+
+        pool::execute_approve_borrow(&e, &owner, &delegatee, &asset, amount, expiration_ledger);
+
+        e.events().publish(
+            (Symbol::new(&e, "approve_borrow"), owner, delegatee),
+            (asset, amount, expiration_ledger),
+        );
+    }
+
+    fn get_borrow_allowance(e: Env, owner: Address, delegatee: Address, asset: Address) -> i128 {
+        pool::load_borrow_allowance(&e, &owner, &delegatee, &asset)
+    }
+
     fn submit(
         e: Env,
         from: Address,
@@ -385,23 +1143,61 @@ impl Pool for PoolContract {
         storage::extend_instance(&e);
         spender.require_auth();
         if from != spender {
-            from.require_auth();
+            pool::require_delegated_auth(&e, &from, &spender, &requests);
         }
 
         pool::execute_submit(&e, &from, &spender, &to, requests)
     }
 
+    fn preview_submit(e: Env, from: Address, requests: Vec<Request>) -> SubmitPreview {
+        pool::preview_submit(&e, &from, &requests)
+    }
+
+    fn flash_loan(
+        e: Env,
+        from: Address,
+        flash_loan: FlashLoan,
+        requests: Vec<Request>,
+    ) -> Positions {
+        storage::extend_instance(&e);
+        from.require_auth();
+
+        let (fee, positions) = pool::execute_flash_loan(&e, &from, &flash_loan, requests);
+
+        e.events().publish(
+            (Symbol::new(&e, "flash_loan"), flash_loan.asset, from),
+            (flash_loan.contract, flash_loan.amount, fee),
+        );
+        positions
+    }
+
     fn bad_debt(e: Env, user: Address) {
         pool::transfer_bad_debt_to_backstop(&e, &user);
     }
 
-    fn update_status(e: Env) -> u32 {
+    fn set_bad_debt_floor(e: Env, floor: i128) {
         storage::extend_instance(&e);
-        let new_status = pool::execute_update_pool_status(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_set_bad_debt_floor(&e, floor);
+
+        e.events()
+            .publish((Symbol::new(&e, "set_bad_debt_floor"), risk_manager), floor);
+    }
+
+    fn default_bad_debt(e: Env) {
+        storage::extend_instance(&e);
+        pool::burn_backstop_bad_debt(&e);
+    }
+
+    fn update_status(e: Env) -> (u32, u32) {
+        storage::extend_instance(&e);
+        let (new_status, trigger) = pool::execute_update_pool_status(&e);
 
         e.events()
             .publish((Symbol::new(&e, "set_status"),), new_status);
-        new_status
+        (new_status, trigger)
     }
 
     fn set_status(e: Env, pool_status: u32) {
@@ -413,6 +1209,15 @@ impl Pool for PoolContract {
             .publish((Symbol::new(&e, "set_status"), admin), pool_status);
     }
 
+    fn freeze(e: Env) {
+        storage::extend_instance(&e);
+        let guardian = storage::get_guardian(&e);
+        guardian.require_auth();
+        pool::execute_set_pool_status(&e, 4);
+        e.events()
+            .publish((Symbol::new(&e, "set_status"), guardian), 4u32);
+    }
+
     /********* Emission Functions **********/
 
     fn gulp_emissions(e: Env) -> i128 {
@@ -425,8 +1230,8 @@ impl Pool for PoolContract {
     }
 
     fn set_emissions_config(e: Env, res_emission_metadata: Vec<ReserveEmissionMetadata>) {
-        let admin = storage::get_admin(&e);
-        admin.require_auth();
+        let emissions_manager = storage::get_emissions_manager(&e);
+        emissions_manager.require_auth();
 
         emissions::set_pool_emissions(&e, res_emission_metadata);
     }
@@ -450,7 +1255,7 @@ impl Pool for PoolContract {
     // Note: we don't need to index the auctions. We let the zephyr programs handle these.
 
     fn new_liquidation_auction(e: Env, user: Address, percent_liquidated: u64) -> AuctionData {
-        let auction_data = auctions::create_liquidation(&e, &user, percent_liquidated);
+        let (_, auction_data) = auctions::create_liquidation(&e, &user, percent_liquidated);
 
         e.events().publish(
             (Symbol::new(&e, "new_liquidation_auction"), user),
@@ -459,6 +1264,28 @@ impl Pool for PoolContract {
         auction_data
     }
 
+    fn new_auto_liquidation_auction(e: Env, user: Address) -> (u64, AuctionData) {
+        let (percent_liquidated, auction_data) = auctions::create_liquidation(&e, &user, 0);
+
+        e.events().publish(
+            (Symbol::new(&e, "new_liquidation_auction"), user),
+            auction_data.clone(),
+        );
+        (percent_liquidated, auction_data)
+    }
+
+    fn new_liquidation_auctions(e: Env, users: Vec<(Address, u64)>) -> Map<Address, AuctionData> {
+        let auctions = auctions::create_liquidations(&e, &users);
+
+        for (user, auction_data) in auctions.iter() {
+            e.events().publish(
+                (Symbol::new(&e, "new_liquidation_auction"), user),
+                auction_data,
+            );
+        }
+        auctions
+    }
+
     fn get_auction(e: Env, auction_type: u32, user: Address) -> AuctionData {
         storage::get_auction(&e, &auction_type, &user)
     }
@@ -487,3 +1314,60 @@ impl Pool for PoolContract {
         auction_data
     }
 }
+
+/// ### Pool Retroshades
+///
+/// Configuration for the Retroshade indexing events. Only available when built with the
+/// `retroshades` feature.
+#[cfg(feature = "retroshades")]
+#[contractclient(name = "PoolRetroshadesClient")]
+pub trait PoolRetroshades {
+    /// (Admin only) Set the Reflector price feeds used by `OracleSource::Offchain` and
+    /// `OracleSource::Pubnet` reserve oracle routes
+    ///
+    /// ### Arguments
+    /// * `feeds` - The Reflector price feeds
+    ///
+    /// ### Panics
+    /// If the caller is not the admin
+    fn set_reflector_feeds(e: Env, feeds: ReflectorFeeds);
+
+    /// (Risk manager only) Set the oracle routing for a reserve. Reserves without a route are
+    /// valued at 0 by the Retroshade indexing.
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    /// * `config` - The ReserveOracleConfig for the reserve
+    ///
+    /// ### Panics
+    /// If the caller is not the risk manager or the reserve does not exist
+    fn set_reserve_oracle(e: Env, asset: Address, config: ReserveOracleConfig);
+}
+
+#[cfg(feature = "retroshades")]
+#[contractimpl]
+impl PoolRetroshades for PoolContract {
+    fn set_reflector_feeds(e: Env, feeds: ReflectorFeeds) {
+        storage::extend_instance(&e);
+        let admin = storage::get_admin(&e);
+        admin.require_auth();
+
+        storage::set_reflector_feeds(&e, &feeds);
+
+        e.events()
+            .publish((Symbol::new(&e, "set_reflector_feeds"), admin), feeds);
+    }
+
+    fn set_reserve_oracle(e: Env, asset: Address, config: ReserveOracleConfig) {
+        storage::extend_instance(&e);
+        let risk_manager = storage::get_risk_manager(&e);
+        risk_manager.require_auth();
+
+        pool::execute_set_reserve_oracle(&e, &asset, &config);
+
+        e.events().publish(
+            (Symbol::new(&e, "set_reserve_oracle"), risk_manager),
+            (asset, config),
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use soroban_sdk::{contractclient, Address, Env};
+
+/// The interface a contract must implement to receive flash loans from a pool
+#[contractclient(name = "FlashLoanReceiverClient")]
+pub trait FlashLoanReceiver {
+    /// Execute an operation with the flash loaned tokens. Before returning, the contract must
+    /// transfer `amount + fee` of `asset` back to the pool.
+    ///
+    /// ### Arguments
+    /// * `caller` - The address that requested the flash loan
+    /// * `asset` - The address of the flash loaned asset
+    /// * `amount` - The amount of tokens loaned
+    /// * `fee` - The fee owed to the pool on top of the loaned amount
+    fn exec_op(e: Env, caller: Address, asset: Address, amount: i128, fee: i128);
+}
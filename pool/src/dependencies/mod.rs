@@ -1,2 +1,5 @@
 mod backstop;
 pub use backstop::{Client as BackstopClient, PoolBackstopData};
+
+mod flash_loan_receiver;
+pub use flash_loan_receiver::{FlashLoanReceiver, FlashLoanReceiverClient};
@@ -1,10 +1,10 @@
 use crate::pool::Pool;
 use cast::i128;
-use sep_41_token::TokenClient;
 use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::{panic_with_error, unwrap::UnwrapOptimized, Address, Env, Vec};
 
 use crate::{
+    catchup::{self, Relaxed},
     errors::PoolError,
     pool::User,
     storage::{self, ReserveEmissionsData, UserEmissionData},
@@ -74,13 +74,13 @@ pub fn execute_claim(
     if to_claim > 0 {
         let backstop = storage::get_backstop(e);
         let blnd_token = storage::get_blnd_token(e);
-        // Note: no need to ensure the transfer lands here because we already know that this
-        // was a succesful call when indexing.
-        TokenClient::new(e, &blnd_token).try_transfer_from(
+        catchup::transfer_from(
+            e,
+            &blnd_token,
             &e.current_contract_address(),
             &backstop,
             to,
-            &to_claim,
+            to_claim,
         );
     }
     (
@@ -92,6 +92,67 @@ pub fn execute_claim(
     )
 }
 
+/// Calculate the emissions a user has accrued for each reserve token up to the current ledger
+/// timestamp, without updating them
+///
+/// ### Arguments
+/// * `user` - The user to calculate the emissions for
+/// * `reserve_token_ids` - The reserve tokens => (reserve index * 2 + (0 for debtToken or 1 for blendToken))
+///
+/// ### Panics
+/// If a reserve token does not exist
+pub fn calc_accrued_emissions(e: &Env, user: &Address, reserve_token_ids: &Vec<u32>) -> Vec<i128> {
+    let user_state = User::load(e, user);
+    let reserve_list = storage::get_res_list(e);
+    let mut accrued = Vec::new(e);
+    for res_token_id in reserve_token_ids.iter() {
+        let reserve_index = res_token_id / 2;
+        let res_address = match reserve_list.get(reserve_index) {
+            Some(res_address) => res_address,
+            None => panic_with_error!(e, PoolError::BadRequest),
+        };
+        let reserve_config = storage::get_res_config(e, &res_address);
+        let reserve_data = storage::get_res_data(e, &res_address);
+        let (balance, supply) = match res_token_id % 2 {
+            0 => (
+                user_state.get_liabilities(reserve_index),
+                reserve_data.d_supply,
+            ),
+            _ => (
+                user_state.get_total_supply(reserve_index),
+                reserve_data.b_supply,
+            ),
+        };
+        let supply_scalar = 10i128.pow(reserve_config.decimals);
+
+        let user_data = storage::get_user_emissions(e, user, &res_token_id);
+        let emis_data = match (
+            storage::get_res_emis_config(e, &res_token_id),
+            storage::get_res_emis_data(e, &res_token_id),
+        ) {
+            (Some(emis_config), Some(emis_data)) => {
+                calc_emission_data(e, &emis_data, supply, supply_scalar, &emis_config)
+            }
+            _ => {
+                accrued.push_back(user_data.map_or(0, |data| data.accrued));
+                continue;
+            }
+        };
+        let (user_accrued, user_index) = match user_data {
+            Some(data) => (data.accrued, data.index),
+            None => (0, 0),
+        };
+        let delta_index = (emis_data.index - user_index).max(0);
+        accrued.push_back(
+            user_accrued
+                + balance
+                    .fixed_mul_floor(delta_index, supply_scalar)
+                    .unwrap_optimized(),
+        );
+    }
+    accrued
+}
+
 /// Update the emissions information about a reserve token. Must be called before any update
 /// is made to the supply of debtTokens or blendTokens.
 ///
@@ -203,13 +264,33 @@ pub(super) fn update_emission_data_with_config(
     emis_config: &ReserveEmissionsConfig,
 ) -> ReserveEmissionsData {
     let token_emission_data = storage::get_res_emis_data(e, &res_token_id).unwrap_optimized(); // exists if config is written to
+    let new_data = calc_emission_data(e, &token_emission_data, supply, supply_scalar, emis_config);
+    if new_data.last_time != token_emission_data.last_time {
+        storage::set_res_emis_data(e, &res_token_id, &new_data);
+    }
+    new_data
+}
 
+/// Calculate the reserve token emission data at the current ledger timestamp
+///
+/// ### Arguments
+/// * `token_emission_data` - The last stored emission data for the reserve token
+/// * `supply` - The current supply of the reserve token
+/// * `supply_scalar` - The scalar of the reserve token
+/// * `emis_config` - The reserve token emission configuration
+fn calc_emission_data(
+    e: &Env,
+    token_emission_data: &ReserveEmissionsData,
+    supply: i128,
+    supply_scalar: i128,
+    emis_config: &ReserveEmissionsConfig,
+) -> ReserveEmissionsData {
     if token_emission_data.last_time >= emis_config.expiration
         || e.ledger().timestamp() == token_emission_data.last_time
         || emis_config.eps == 0
         || supply == 0
     {
-        return token_emission_data;
+        return token_emission_data.clone();
     }
 
     let ledger_timestamp = if e.ledger().timestamp() > emis_config.expiration {
@@ -218,25 +299,20 @@ pub(super) fn update_emission_data_with_config(
         e.ledger().timestamp()
     };
 
-    let additional_idx = if ledger_timestamp as i64 - token_emission_data.last_time as i64 > 0
-        && supply != 0
-        && emis_config.eps > 0
+    let additional_idx = if ledger_timestamp < token_emission_data.last_time
+        && catchup::relax(e, Relaxed::EmissionTime)
     {
-        let delta = i128(ledger_timestamp - token_emission_data.last_time);
-
-        (delta * i128(emis_config.eps))
+        0
+    } else {
+        (i128(ledger_timestamp - token_emission_data.last_time) * i128(emis_config.eps))
             .fixed_div_floor(supply, supply_scalar)
             .unwrap_optimized()
-    } else {
-        0
     };
 
-    let new_data = ReserveEmissionsData {
+    ReserveEmissionsData {
         index: additional_idx + token_emission_data.index,
         last_time: ledger_timestamp,
-    };
-    storage::set_res_emis_data(e, &res_token_id, &new_data);
-    new_data
+    }
 }
 
 fn update_user_emissions(
@@ -252,19 +328,18 @@ fn update_user_emissions(
         if user_data.index != res_emis_data.index || claim {
             let mut accrual = user_data.accrued;
 
-            // If this is in a catchup that still doesn't support parallel state writes
-            // then the accrual is 0 to notify the API of this.
-            if res_emis_data.index - user_data.index >= 0 {
+            let delta_index = res_emis_data.index - user_data.index;
+            if delta_index < 0 && catchup::relax(e, Relaxed::EmissionIndex) {
+                // the accrual is reported as 0 so the indexer knows it could not be computed
+                accrual = 0;
+            } else {
+                require_nonnegative(e, &delta_index);
                 if balance != 0 {
-                    let delta_index = res_emis_data.index - user_data.index;
-                    require_nonnegative(e, &delta_index);
                     let to_accrue = balance
-                        .fixed_mul_floor(res_emis_data.index - user_data.index, supply_scalar)
+                        .fixed_mul_floor(delta_index, supply_scalar)
                         .unwrap_optimized();
                     accrual += to_accrue;
                 }
-            } else {
-                accrual = 0
             }
             return set_user_emissions(e, user, res_token_id, res_emis_data.index, accrual, claim);
         }
@@ -419,6 +494,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "catchup"))]
     #[should_panic(expected = "attempt to subtract with overflow")]
     fn test_update_emissions_negative_time_diff() {
         let e = Env::default();
@@ -1271,6 +1347,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "catchup"))]
     #[should_panic(expected = "Error(Contract, #8)")]
     fn test_update_user_emissions_negative_index() {
         let e = Env::default();
@@ -1435,7 +1512,7 @@ mod tests {
                 new_reserve_emission_data_1.index
             );
             assert_eq!(new_user_emission_data.accrued, 0);
-            assert_eq!(result, 400_3222222 + 301_0222222);
+            assert_eq!(result.0, 400_3222222 + 301_0222222);
 
             // verify tokens are sent
             assert_eq!(blnd_token_client.balance(&merry), 400_3222222 + 301_0222222);
@@ -1560,7 +1637,7 @@ mod tests {
                 new_reserve_emission_data_1.index
             );
             assert_eq!(new_user_emission_data.accrued, 0);
-            assert_eq!(result, 400_3222222);
+            assert_eq!(result.0, 400_3222222);
 
             // verify tokens are sent
             assert_eq!(blnd_token_client.balance(&merry), 400_3222222);
@@ -1669,4 +1746,104 @@ mod tests {
             assert_eq!(blnd_token_client.balance(&backstop), 100_000_0000000)
         });
     }
+
+    #[test]
+    fn test_calc_accrued_emissions() {
+        let e = Env::default();
+        e.mock_all_auths();
+        e.budget().reset_unlimited();
+
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1501000000, // 10^6 seconds have passed
+            protocol_version: 20,
+            sequence_number: 123,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.decimals = 5;
+        reserve_data.b_supply = 100_00000;
+        reserve_data.d_supply = 50_00000;
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.decimals = 9;
+        reserve_config.index = 1;
+        reserve_data.b_supply = 100_000_000_000;
+        reserve_data.d_supply = 50_000_000_000;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        let user_positions = Positions {
+            liabilities: map![&e, (0, 2_00000)],
+            collateral: map![&e, (1, 1_000_000_000)],
+            supply: map![&e, (1, 1_000_000_000)],
+        };
+        e.as_contract(&pool, || {
+            storage::set_user_positions(&e, &samwise, &user_positions);
+
+            let reserve_emission_data_0 = ReserveEmissionsData {
+                index: 2345678,
+                last_time: 1500000000,
+            };
+            let user_emission_data_0 = UserEmissionData {
+                index: 1234567,
+                accrued: 0_1000000,
+            };
+            let res_token_index_0 = 0; // d_token for reserve 0
+            storage::set_res_emis_config(
+                &e,
+                &res_token_index_0,
+                &ReserveEmissionsConfig {
+                    expiration: 1600000000,
+                    eps: 0_0100000,
+                },
+            );
+            storage::set_res_emis_data(&e, &res_token_index_0, &reserve_emission_data_0);
+            storage::set_user_emissions(&e, &samwise, &res_token_index_0, &user_emission_data_0);
+
+            let reserve_emission_data_1 = ReserveEmissionsData {
+                index: 1345678,
+                last_time: 1500000000,
+            };
+            let user_emission_data_1 = UserEmissionData {
+                index: 1234567,
+                accrued: 1_0000000,
+            };
+            let res_token_index_1 = 1 * 2 + 1; // b_token for reserve 1
+            storage::set_res_emis_config(
+                &e,
+                &res_token_index_1,
+                &ReserveEmissionsConfig {
+                    expiration: 1600000000,
+                    eps: 0_0150000,
+                },
+            );
+            storage::set_res_emis_data(&e, &res_token_index_1, &reserve_emission_data_1);
+            storage::set_user_emissions(&e, &samwise, &res_token_index_1, &user_emission_data_1);
+
+            let reserve_token_ids: Vec<u32> = vec![&e, res_token_index_0, res_token_index_1];
+            let result = calc_accrued_emissions(&e, &samwise, &reserve_token_ids);
+            assert_eq!(result, vec![&e, 400_3222222, 301_0222222]);
+
+            // verify nothing is stored
+            let stored_reserve_emission_data =
+                storage::get_res_emis_data(&e, &res_token_index_0).unwrap_optimized();
+            let stored_user_emission_data =
+                storage::get_user_emissions(&e, &samwise, &res_token_index_1).unwrap_optimized();
+            assert_eq!(stored_reserve_emission_data.last_time, 1500000000);
+            assert_eq!(stored_reserve_emission_data.index, 2345678);
+            assert_eq!(stored_user_emission_data.index, 1234567);
+            assert_eq!(stored_user_emission_data.accrued, 1_0000000);
+        });
+    }
 }
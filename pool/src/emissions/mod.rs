@@ -2,4 +2,4 @@ mod manager;
 pub use manager::{gulp_emissions, set_pool_emissions, ReserveEmissionMetadata};
 
 mod distributor;
-pub use distributor::{execute_claim, update_emissions};
+pub use distributor::{calc_accrued_emissions, execute_claim, update_emissions};
@@ -45,4 +45,32 @@ pub enum PoolError {
     InvalidBTokenBurnAmount = 1217,
     InvalidDTokenMintAmount = 1218,
     InvalidDTokenBurnAmount = 1219,
+
+    // Oracle Aggregation Errors
+    OracleQuorumNotMet = 1220,
+    PriceDeviationTooLarge = 1221,
+
+    // Reserve Cap Errors
+    SupplyCapExceeded = 1222,
+    BorrowCapExceeded = 1223,
+
+    // Reserve Status Errors
+    ReserveActionDisabled = 1224,
+
+    // Flash Loan Errors
+    FlashLoanTooLarge = 1225,
+    FlashLoanNotRepaid = 1226,
+
+    // E-Mode Errors
+    EModeViolation = 1227,
+
+    // Isolation Mode Errors
+    IsolationViolation = 1228,
+    DebtCeilingExceeded = 1229,
+
+    // Delegation Errors
+    BorrowAllowanceExceeded = 1230,
+
+    // Bad Debt Errors
+    BackstopNotExhausted = 1231,
 }
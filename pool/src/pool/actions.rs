@@ -1,14 +1,17 @@
-use soroban_sdk::token::TokenClient;
+use soroban_sdk::Map;
 use soroban_sdk::{contracttype, panic_with_error, vec, Address, Env, Symbol, Vec};
-use soroban_sdk::{symbol_short, Map};
 
 use crate::auctions::{scale_auction, AuctionResult};
+#[cfg(feature = "retroshades")]
 use crate::reflector_oracle;
+#[cfg(feature = "retroshades")]
 use crate::retroshades::{BorrowActionInfo, CollateralActionInfo};
-use crate::{auctions, errors::PoolError, validator::require_nonnegative};
+use crate::{auctions, errors::PoolError, storage, storage::EModeCategory};
+#[cfg(feature = "retroshades")]
+use soroban_sdk::symbol_short;
 
 use super::pool::Pool;
-use super::{PositionData, User};
+use super::{check_in_emode, load_user_emode, require_isolation, PositionData, User};
 
 /// A request a user makes against the pool
 #[derive(Clone)]
@@ -41,23 +44,33 @@ impl RequestType {
     /// ### Panics
     /// If the value is not a valid RequestType
     pub fn from_u32(e: &Env, value: u32) -> Self {
+        match RequestType::try_from_u32(value) {
+            Ok(request_type) => request_type,
+            Err(error) => panic_with_error!(e, error),
+        }
+    }
+
+    /// Convert a u32 to a RequestType, or return `BadRequest` if the value is not a valid
+    /// RequestType
+    pub fn try_from_u32(value: u32) -> Result<Self, PoolError> {
         match value {
-            0 => RequestType::Supply,
-            1 => RequestType::Withdraw,
-            2 => RequestType::SupplyCollateral,
-            3 => RequestType::WithdrawCollateral,
-            4 => RequestType::Borrow,
-            5 => RequestType::Repay,
-            6 => RequestType::FillUserLiquidationAuction,
-            7 => RequestType::FillBadDebtAuction,
-            8 => RequestType::FillInterestAuction,
-            9 => RequestType::DeleteLiquidationAuction,
-            _ => panic_with_error!(e, PoolError::BadRequest),
+            0 => Ok(RequestType::Supply),
+            1 => Ok(RequestType::Withdraw),
+            2 => Ok(RequestType::SupplyCollateral),
+            3 => Ok(RequestType::WithdrawCollateral),
+            4 => Ok(RequestType::Borrow),
+            5 => Ok(RequestType::Repay),
+            6 => Ok(RequestType::FillUserLiquidationAuction),
+            7 => Ok(RequestType::FillBadDebtAuction),
+            8 => Ok(RequestType::FillInterestAuction),
+            9 => Ok(RequestType::DeleteLiquidationAuction),
+            _ => Err(PoolError::BadRequest),
         }
     }
 }
 
 /// Transfer actions to be taken by the sender and pool
+#[derive(Clone)]
 pub struct Actions {
     pub spender_transfer: Map<Address, i128>,
     pub pool_transfer: Map<Address, i128>,
@@ -89,6 +102,13 @@ impl Actions {
     }
 }
 
+/// The checks required once all requests have been processed
+#[derive(Default)]
+pub struct RequestChecks {
+    pub check_health: bool,
+    pub check_isolation: bool,
+}
+
 /// Build a set of pool actions and the new positions from the supplied requests. Validates that the requests
 /// are valid based on the status and supported reserves in the pool.
 ///
@@ -113,19 +133,87 @@ pub fn build_actions_from_request(
 ) -> (Actions, User, bool) {
     let mut actions = Actions::new(e);
     let mut from_state = User::load(e, from);
-    let prev_positions_count = from_state.positions.effective_count();
-    let mut check_health = false;
+    let emode = load_user_emode(e, from);
+    let prev_positions_count = pool.count_positions(&from_state.positions);
+    let mut checks = RequestChecks::default();
     for request in requests.iter() {
-        // verify the request is allowed
-        require_nonnegative(e, &request.amount);
-        pool.require_action_allowed(e, request.request_type);
-        match RequestType::from_u32(e, request.request_type) {
-            RequestType::Supply => {
-                let mut reserve = pool.load_reserve(e, &request.address, true);
-                let b_tokens_minted = reserve.to_b_token_down(request.amount);
-                from_state.add_supply(e, &mut reserve, b_tokens_minted);
-                actions.add_for_spender_transfer(&reserve.asset, request.amount);
-                pool.cache_reserve(reserve);
+        if let Err(error) = apply_request(
+            e,
+            pool,
+            &mut from_state,
+            &emode,
+            &mut actions,
+            &mut checks,
+            &request,
+        ) {
+            panic_with_error!(e, error);
+        }
+    }
+
+    // Verify max positions haven't been exceeded
+    pool.require_under_max(e, &from_state.positions, prev_positions_count);
+
+    // Verify isolated collateral is not mixed and its debt ceiling is respected
+    require_isolation(e, from, pool, &from_state.positions, checks.check_isolation);
+
+    (actions, from_state, checks.check_health)
+}
+
+/// Apply a single request to the user's positions, the pool's cached reserves and the actions,
+/// or return the reason the request is invalid. Requests applied against a preview pool do not
+/// publish events, and fills do not store auctions, other users' positions or transfer tokens.
+///
+/// The health factor and isolation checks, which need every request to be processed first, are
+/// recorded in `checks`.
+///
+/// ### Arguments
+/// * pool - The pool
+/// * from_state - The state of the user sending the request
+/// * emode - The e-mode category of the user, if any
+/// * actions - The transfers to be made once all requests are processed
+/// * checks - The checks to be made once all requests are processed
+/// * request - The request to apply
+///
+/// ### Panics
+/// If a fill cannot be applied to the positions of the user being filled
+// some request state is only read by the retroshade events
+#[cfg_attr(
+    not(feature = "retroshades"),
+    allow(unused_variables, unused_assignments)
+)]
+pub fn apply_request(
+    e: &Env,
+    pool: &mut Pool,
+    from_state: &mut User,
+    emode: &Option<EModeCategory>,
+    actions: &mut Actions,
+    checks: &mut RequestChecks,
+    request: &Request,
+) -> Result<(), PoolError> {
+    // verify the request is allowed
+    if request.amount.is_negative() {
+        return Err(PoolError::NegativeAmountError);
+    }
+    pool.check_action_allowed(request.request_type)?;
+    let request_type = RequestType::try_from_u32(request.request_type)?;
+    if request.request_type <= RequestType::Repay as u32 && !storage::has_res(e, &request.address) {
+        return Err(PoolError::BadRequest);
+    }
+    let from = from_state.address.clone();
+    let preview = pool.is_preview();
+    match request_type {
+        RequestType::Supply => {
+            let mut reserve = pool.load_reserve(e, &request.address, true);
+            reserve.check_action_allowed(request.request_type)?;
+            let b_tokens_minted = reserve.to_b_token_down(request.amount);
+            if b_tokens_minted == 0 {
+                return Err(PoolError::InvalidBTokenMintAmount);
+            }
+            from_state.add_supply(e, &mut reserve, b_tokens_minted);
+            reserve.check_supply_under_cap()?;
+            actions.add_for_spender_transfer(&reserve.asset, request.amount);
+            pool.cache_reserve(reserve);
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "supply"),
@@ -135,18 +223,23 @@ pub fn build_actions_from_request(
                     (request.amount, b_tokens_minted),
                 );
             }
-            RequestType::Withdraw => {
-                let mut reserve = pool.load_reserve(e, &request.address, true);
-                let cur_b_tokens = from_state.get_supply(reserve.index);
-                let mut to_burn = reserve.to_b_token_up(request.amount);
-                let mut tokens_out = request.amount;
-                if to_burn > cur_b_tokens {
-                    to_burn = cur_b_tokens;
-                    tokens_out = reserve.to_asset_from_b_token(cur_b_tokens);
-                }
-                from_state.remove_supply(e, &mut reserve, to_burn);
-                actions.add_for_pool_transfer(&reserve.asset, tokens_out);
-                pool.cache_reserve(reserve);
+        }
+        RequestType::Withdraw => {
+            let mut reserve = pool.load_reserve(e, &request.address, true);
+            let cur_b_tokens = from_state.get_supply(reserve.index);
+            let mut to_burn = reserve.to_b_token_up(request.amount);
+            let mut tokens_out = request.amount;
+            if to_burn > cur_b_tokens {
+                to_burn = cur_b_tokens;
+                tokens_out = reserve.to_asset_from_b_token(cur_b_tokens);
+            }
+            if to_burn == 0 {
+                return Err(PoolError::InvalidBTokenBurnAmount);
+            }
+            from_state.remove_supply(e, &mut reserve, to_burn);
+            actions.add_for_pool_transfer(&reserve.asset, tokens_out);
+            pool.cache_reserve(reserve);
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "withdraw"),
@@ -156,84 +249,88 @@ pub fn build_actions_from_request(
                     (tokens_out, to_burn),
                 );
             }
-            RequestType::SupplyCollateral => {
-                let mut reserve = pool.load_reserve(e, &request.address, true);
-                let b_tokens_minted = reserve.to_b_token_down(request.amount);
-                let updated_emissions = from_state.add_collateral(e, &mut reserve, b_tokens_minted);
-                actions.add_for_spender_transfer(&reserve.asset, request.amount);
-                pool.cache_reserve(reserve.clone());
-
-                // Retroshades logic
-                {
-                    let reserve_address = request.address.clone();
-                    let user_address = from.clone();
-                    let amount_supplied = request.amount;
-                    let b_tokens_minted = b_tokens_minted;
-
-                    let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
-                    let user_collateral_supply = from_state.get_collateral(reserve.index);
-
-                    let user_updated_emissions = updated_emissions;
-                    let reserve_supply = reserve.total_supply();
-                    let reserve_liabilities = reserve.total_liabilities();
-
-                    let usdc_amount_supplied = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        amount_supplied,
-                    );
-                    let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_supply,
-                    );
-                    let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_liabilities,
-                    );
-                    let usdc_user_reserve_total_supply =
-                        reflector_oracle::get_token_amount_in_usdc_value(
-                            &e,
-                            &pool.config.oracle,
-                            &TokenClient::new(&e, &reserve_address),
-                            user_reserve_total_supply,
-                        );
-                    let usdc_user_collateral_supply =
-                        reflector_oracle::get_token_amount_in_usdc_value(
-                            &e,
-                            &pool.config.oracle,
-                            &TokenClient::new(&e, &reserve_address),
-                            user_collateral_supply,
-                        );
-
-                    CollateralActionInfo {
-                        pool: e.current_contract_address(),
-                        reserve_address,
-                        user_address,
-                        action_type: symbol_short!("supply"),
-                        amount: amount_supplied,
-                        b_tokens: b_tokens_minted,
+        }
+        RequestType::SupplyCollateral => {
+            let mut reserve = pool.load_reserve(e, &request.address, true);
+            reserve.check_action_allowed(request.request_type)?;
+            check_in_emode(emode, &reserve.asset)?;
+            let b_tokens_minted = reserve.to_b_token_down(request.amount);
+            if b_tokens_minted == 0 {
+                return Err(PoolError::InvalidBTokenMintAmount);
+            }
+            let updated_emissions = from_state.add_collateral(e, &mut reserve, b_tokens_minted);
+            checks.check_isolation = true;
+            reserve.check_supply_under_cap()?;
+            actions.add_for_spender_transfer(&reserve.asset, request.amount);
+            pool.cache_reserve(reserve.clone());
+
+            // Retroshades logic
+            #[cfg(feature = "retroshades")]
+            if !preview {
+                let reserve_address = request.address.clone();
+                let user_address = from.clone();
+                let amount_supplied = request.amount;
+                let b_tokens_minted = b_tokens_minted;
+
+                let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
+                let user_collateral_supply = from_state.get_collateral(reserve.index);
+
+                let user_updated_emissions = updated_emissions;
+                let reserve_supply = reserve.total_supply();
+                let reserve_liabilities = reserve.total_liabilities();
+
+                let usdc_amount_supplied = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    amount_supplied,
+                );
+                let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_supply,
+                );
+                let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_liabilities,
+                );
+                let usdc_user_reserve_total_supply =
+                    reflector_oracle::get_token_amount_in_usdc_value(
+                        e,
+                        &reserve_address,
                         user_reserve_total_supply,
-                        user_collateral_supply,
-                        user_updated_emissions,
-                        reserve_supply,
-                        reserve_liabilities,
-                        usdc_amount: usdc_amount_supplied,
-                        usdc_reserve_supply,
-                        usdc_reserve_liabilities,
-                        usdc_user_collateral_supply,
-                        usdc_user_reserve_total_supply,
-
-                        ledger: e.ledger().sequence(),
-                        timestamp: e.ledger().timestamp(),
-                    }
-                    .emit(&e);
+                    );
+                let usdc_user_collateral_supply = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    user_collateral_supply,
+                );
+
+                CollateralActionInfo {
+                    pool: e.current_contract_address(),
+                    reserve_address,
+                    user_address,
+                    action_type: symbol_short!("supply"),
+                    amount: amount_supplied,
+                    b_tokens: b_tokens_minted,
+                    user_reserve_total_supply,
+                    user_collateral_supply,
+                    user_updated_emissions,
+                    reserve_supply,
+                    reserve_liabilities,
+                    usdc_amount: usdc_amount_supplied,
+                    usdc_reserve_supply,
+                    usdc_reserve_liabilities,
+                    usdc_user_collateral_supply,
+                    usdc_user_reserve_total_supply,
+
+                    ledger: e.ledger().sequence(),
+                    timestamp: e.ledger().timestamp(),
                 }
+                .emit(&e);
+            }
 
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "supply_collateral"),
@@ -243,91 +340,91 @@ pub fn build_actions_from_request(
                     (request.amount, b_tokens_minted),
                 );
             }
-            RequestType::WithdrawCollateral => {
-                let mut reserve = pool.load_reserve(e, &request.address, true);
-                let cur_b_tokens = from_state.get_collateral(reserve.index);
-                let mut to_burn = reserve.to_b_token_up(request.amount);
-                let mut tokens_out = request.amount;
-                if to_burn > cur_b_tokens {
-                    to_burn = cur_b_tokens;
-                    tokens_out = reserve.to_asset_from_b_token(cur_b_tokens);
-                }
-                let updated_emissions = from_state.remove_collateral(e, &mut reserve, to_burn);
-                actions.add_for_pool_transfer(&reserve.asset, tokens_out);
-                check_health = true;
-                pool.cache_reserve(reserve.clone());
-
-                // Retroshades logic
-                {
-                    let reserve_address = request.address.clone();
-                    let user_address = from.clone();
-                    let amount_withdrawn = tokens_out;
-                    let b_tokens_burned = to_burn;
-
-                    let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
-                    let user_collateral_supply = from_state.get_collateral(reserve.index);
-
-                    let user_updated_emissions = updated_emissions;
-                    let reserve_supply = reserve.total_supply();
-                    let reserve_liabilities = reserve.total_liabilities();
-
-                    let usdc_amount_withdrawn = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        amount_withdrawn,
-                    );
-                    let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_supply,
-                    );
-                    let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_liabilities,
-                    );
-                    let usdc_user_reserve_total_supply =
-                        reflector_oracle::get_token_amount_in_usdc_value(
-                            &e,
-                            &pool.config.oracle,
-                            &TokenClient::new(&e, &reserve_address),
-                            user_reserve_total_supply,
-                        );
-                    let usdc_user_collateral_supply =
-                        reflector_oracle::get_token_amount_in_usdc_value(
-                            &e,
-                            &pool.config.oracle,
-                            &TokenClient::new(&e, &reserve_address),
-                            user_collateral_supply,
-                        );
-
-                    CollateralActionInfo {
-                        pool: e.current_contract_address(),
-                        reserve_address,
-                        user_address,
-                        action_type: symbol_short!("withdraw"),
-                        amount: amount_withdrawn,
-                        b_tokens: b_tokens_burned,
+        }
+        RequestType::WithdrawCollateral => {
+            let mut reserve = pool.load_reserve(e, &request.address, true);
+            let cur_b_tokens = from_state.get_collateral(reserve.index);
+            let mut to_burn = reserve.to_b_token_up(request.amount);
+            let mut tokens_out = request.amount;
+            if to_burn > cur_b_tokens {
+                to_burn = cur_b_tokens;
+                tokens_out = reserve.to_asset_from_b_token(cur_b_tokens);
+            }
+            if to_burn == 0 {
+                return Err(PoolError::InvalidBTokenBurnAmount);
+            }
+            let updated_emissions = from_state.remove_collateral(e, &mut reserve, to_burn);
+            actions.add_for_pool_transfer(&reserve.asset, tokens_out);
+            checks.check_health = true;
+            pool.cache_reserve(reserve.clone());
+
+            // Retroshades logic
+            #[cfg(feature = "retroshades")]
+            if !preview {
+                let reserve_address = request.address.clone();
+                let user_address = from.clone();
+                let amount_withdrawn = tokens_out;
+                let b_tokens_burned = to_burn;
+
+                let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
+                let user_collateral_supply = from_state.get_collateral(reserve.index);
+
+                let user_updated_emissions = updated_emissions;
+                let reserve_supply = reserve.total_supply();
+                let reserve_liabilities = reserve.total_liabilities();
+
+                let usdc_amount_withdrawn = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    amount_withdrawn,
+                );
+                let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_supply,
+                );
+                let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_liabilities,
+                );
+                let usdc_user_reserve_total_supply =
+                    reflector_oracle::get_token_amount_in_usdc_value(
+                        e,
+                        &reserve_address,
                         user_reserve_total_supply,
-                        user_collateral_supply,
-                        user_updated_emissions,
-                        reserve_supply,
-                        reserve_liabilities,
-                        usdc_amount: usdc_amount_withdrawn,
-                        usdc_reserve_supply,
-                        usdc_reserve_liabilities,
-                        usdc_user_collateral_supply,
-                        usdc_user_reserve_total_supply,
-
-                        ledger: e.ledger().sequence(),
-                        timestamp: e.ledger().timestamp(),
-                    }
-                    .emit(&e);
+                    );
+                let usdc_user_collateral_supply = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    user_collateral_supply,
+                );
+
+                CollateralActionInfo {
+                    pool: e.current_contract_address(),
+                    reserve_address,
+                    user_address,
+                    action_type: symbol_short!("withdraw"),
+                    amount: amount_withdrawn,
+                    b_tokens: b_tokens_burned,
+                    user_reserve_total_supply,
+                    user_collateral_supply,
+                    user_updated_emissions,
+                    reserve_supply,
+                    reserve_liabilities,
+                    usdc_amount: usdc_amount_withdrawn,
+                    usdc_reserve_supply,
+                    usdc_reserve_liabilities,
+                    usdc_user_collateral_supply,
+                    usdc_user_reserve_total_supply,
+
+                    ledger: e.ledger().sequence(),
+                    timestamp: e.ledger().timestamp(),
                 }
+                .emit(&e);
+            }
 
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "withdraw_collateral"),
@@ -337,85 +434,89 @@ pub fn build_actions_from_request(
                     (tokens_out, to_burn),
                 );
             }
+        }
 
-            RequestType::Borrow => {
-                let mut reserve = pool.load_reserve(e, &request.address, true);
-                let d_tokens_minted = reserve.to_d_token_up(request.amount);
-                let updated_emissions =
-                    from_state.add_liabilities(e, &mut reserve, d_tokens_minted);
-                reserve.require_utilization_below_max(e);
-                actions.add_for_pool_transfer(&reserve.asset, request.amount);
-                check_health = true;
-                pool.cache_reserve(reserve.clone());
-
-                // retroshades logic
-                {
-                    let reserve_address = request.address.clone();
-                    let user_address = from.clone();
-                    let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
-                    let user_liabilities = from_state.get_liabilities(reserve.index);
-
-                    let user_updated_emissions = updated_emissions;
-                    let reserve_supply = reserve.total_supply();
-                    let reserve_liabilities = reserve.total_liabilities();
-
-                    let usdc_amount_withdrawn = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        request.amount,
-                    );
-                    let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_supply,
-                    );
-                    let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_liabilities,
-                    );
-                    let usdc_user_reserve_total_supply =
-                        reflector_oracle::get_token_amount_in_usdc_value(
-                            &e,
-                            &pool.config.oracle,
-                            &TokenClient::new(&e, &reserve_address),
-                            user_reserve_total_supply,
-                        );
-                    let usdc_user_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        user_liabilities,
+        RequestType::Borrow => {
+            let mut reserve = pool.load_reserve(e, &request.address, true);
+            reserve.check_action_allowed(request.request_type)?;
+            check_in_emode(emode, &reserve.asset)?;
+            let d_tokens_minted = reserve.to_d_token_up(request.amount);
+            if d_tokens_minted == 0 {
+                return Err(PoolError::InvalidDTokenMintAmount);
+            }
+            let updated_emissions = from_state.add_liabilities(e, &mut reserve, d_tokens_minted);
+            reserve.check_utilization_below_max()?;
+            reserve.check_borrow_under_cap()?;
+            checks.check_isolation = true;
+            actions.add_for_pool_transfer(&reserve.asset, request.amount);
+            checks.check_health = true;
+            pool.cache_reserve(reserve.clone());
+
+            // retroshades logic
+            #[cfg(feature = "retroshades")]
+            if !preview {
+                let reserve_address = request.address.clone();
+                let user_address = from.clone();
+                let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
+                let user_liabilities = from_state.get_liabilities(reserve.index);
+
+                let user_updated_emissions = updated_emissions;
+                let reserve_supply = reserve.total_supply();
+                let reserve_liabilities = reserve.total_liabilities();
+
+                let usdc_amount_withdrawn = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    request.amount,
+                );
+                let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_supply,
+                );
+                let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_liabilities,
+                );
+                let usdc_user_reserve_total_supply =
+                    reflector_oracle::get_token_amount_in_usdc_value(
+                        e,
+                        &reserve_address,
+                        user_reserve_total_supply,
                     );
+                let usdc_user_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    user_liabilities,
+                );
 
-                    BorrowActionInfo {
-                        pool: e.current_contract_address(),
-                        reserve_address,
-                        user_address,
-                        action_type: symbol_short!("borrow"),
-                        amount: request.amount,
-                        d_tokens: d_tokens_minted,
-                        user_reserve_total_supply,
-                        user_liabilities,
-                        user_updated_emissions,
-                        reserve_supply,
-                        reserve_liabilities,
-                        usdc_amount: usdc_amount_withdrawn,
-                        usdc_reserve_supply,
-                        usdc_reserve_liabilities,
-                        usdc_user_liabilities,
-                        usdc_user_reserve_total_supply,
-                        utilization_rate: reserve.utilization(),
-
-                        ledger: e.ledger().sequence(),
-                        timestamp: e.ledger().timestamp(),
-                    }
-                    .emit(&e);
+                BorrowActionInfo {
+                    pool: e.current_contract_address(),
+                    reserve_address,
+                    user_address,
+                    action_type: symbol_short!("borrow"),
+                    amount: request.amount,
+                    d_tokens: d_tokens_minted,
+                    user_reserve_total_supply,
+                    user_liabilities,
+                    user_updated_emissions,
+                    reserve_supply,
+                    reserve_liabilities,
+                    usdc_amount: usdc_amount_withdrawn,
+                    usdc_reserve_supply,
+                    usdc_reserve_liabilities,
+                    usdc_user_liabilities,
+                    usdc_user_reserve_total_supply,
+                    utilization_rate: reserve.utilization(),
+
+                    ledger: e.ledger().sequence(),
+                    timestamp: e.ledger().timestamp(),
                 }
+                .emit(&e);
+            }
 
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "borrow"),
@@ -425,27 +526,33 @@ pub fn build_actions_from_request(
                     (request.amount, d_tokens_minted),
                 );
             }
-            RequestType::Repay => {
-                let mut reserve = pool.load_reserve(e, &request.address, true);
-                let cur_d_tokens = from_state.get_liabilities(reserve.index);
-                let d_tokens_burnt = reserve.to_d_token_down(request.amount);
-                actions.add_for_spender_transfer(&reserve.asset, request.amount);
-
-                let updated_emissions;
-                let amount_to_refund;
-                let d_burnt_tokens;
-                let amount = request.amount;
-
-                if d_tokens_burnt > cur_d_tokens {
-                    amount_to_refund = request.amount - reserve.to_asset_from_d_token(cur_d_tokens);
-                    d_burnt_tokens = cur_d_tokens;
-
-                    require_nonnegative(e, &amount_to_refund);
+        }
+        RequestType::Repay => {
+            let mut reserve = pool.load_reserve(e, &request.address, true);
+            let cur_d_tokens = from_state.get_liabilities(reserve.index);
+            let d_tokens_burnt = reserve.to_d_token_down(request.amount);
+            actions.add_for_spender_transfer(&reserve.asset, request.amount);
+
+            let updated_emissions;
+            let amount_to_refund;
+            let d_burnt_tokens;
+            let amount = request.amount;
+
+            if d_tokens_burnt > cur_d_tokens {
+                amount_to_refund = request.amount - reserve.to_asset_from_d_token(cur_d_tokens);
+                d_burnt_tokens = cur_d_tokens;
+
+                if amount_to_refund.is_negative() {
+                    return Err(PoolError::NegativeAmountError);
+                }
+                if cur_d_tokens == 0 {
+                    return Err(PoolError::InvalidDTokenBurnAmount);
+                }
 
-                    updated_emissions =
-                        from_state.remove_liabilities(e, &mut reserve, cur_d_tokens);
+                updated_emissions = from_state.remove_liabilities(e, &mut reserve, cur_d_tokens);
 
-                    actions.add_for_pool_transfer(&reserve.asset, amount_to_refund);
+                actions.add_for_pool_transfer(&reserve.asset, amount_to_refund);
+                if !preview {
                     e.events().publish(
                         (
                             Symbol::new(e, "repay"),
@@ -454,11 +561,15 @@ pub fn build_actions_from_request(
                         ),
                         (request.amount - amount_to_refund, cur_d_tokens),
                     );
-                } else {
-                    d_burnt_tokens = d_tokens_burnt;
-                    updated_emissions =
-                        from_state.remove_liabilities(e, &mut reserve, d_tokens_burnt);
+                }
+            } else {
+                d_burnt_tokens = d_tokens_burnt;
+                if d_tokens_burnt == 0 {
+                    return Err(PoolError::InvalidDTokenBurnAmount);
+                }
+                updated_emissions = from_state.remove_liabilities(e, &mut reserve, d_tokens_burnt);
 
+                if !preview {
                     e.events().publish(
                         (
                             Symbol::new(e, "repay"),
@@ -468,88 +579,84 @@ pub fn build_actions_from_request(
                         (request.amount, d_tokens_burnt),
                     );
                 }
+            }
 
-                // retroshades logic
-                {
-                    let reserve_address = request.address.clone();
-                    let user_address = from.clone();
-                    let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
-                    let user_liabilities = from_state.get_liabilities(reserve.index);
-
-                    let user_updated_emissions = updated_emissions;
-                    let reserve_supply = reserve.total_supply();
-                    let reserve_liabilities = reserve.total_liabilities();
-
-                    let usdc_amount = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        amount,
-                    );
-                    let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_supply,
-                    );
-                    let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        reserve_liabilities,
-                    );
-                    let usdc_user_reserve_total_supply =
-                        reflector_oracle::get_token_amount_in_usdc_value(
-                            &e,
-                            &pool.config.oracle,
-                            &TokenClient::new(&e, &reserve_address),
-                            user_reserve_total_supply,
-                        );
-                    let usdc_user_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
-                        &e,
-                        &pool.config.oracle,
-                        &TokenClient::new(&e, &reserve_address),
-                        user_liabilities,
+            // retroshades logic
+            #[cfg(feature = "retroshades")]
+            if !preview {
+                let reserve_address = request.address.clone();
+                let user_address = from.clone();
+                let user_reserve_total_supply = from_state.get_total_supply(reserve.index);
+                let user_liabilities = from_state.get_liabilities(reserve.index);
+
+                let user_updated_emissions = updated_emissions;
+                let reserve_supply = reserve.total_supply();
+                let reserve_liabilities = reserve.total_liabilities();
+
+                let usdc_amount =
+                    reflector_oracle::get_token_amount_in_usdc_value(e, &reserve_address, amount);
+                let usdc_reserve_supply = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_supply,
+                );
+                let usdc_reserve_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    reserve_liabilities,
+                );
+                let usdc_user_reserve_total_supply =
+                    reflector_oracle::get_token_amount_in_usdc_value(
+                        e,
+                        &reserve_address,
+                        user_reserve_total_supply,
                     );
+                let usdc_user_liabilities = reflector_oracle::get_token_amount_in_usdc_value(
+                    e,
+                    &reserve_address,
+                    user_liabilities,
+                );
 
-                    BorrowActionInfo {
-                        pool: e.current_contract_address(),
-                        reserve_address,
-                        user_address,
-                        action_type: symbol_short!("repay"),
-                        amount,
-                        d_tokens: d_burnt_tokens,
-                        user_reserve_total_supply,
-                        user_liabilities,
-                        user_updated_emissions,
-                        reserve_supply,
-                        reserve_liabilities,
-                        usdc_amount,
-                        usdc_reserve_supply,
-                        usdc_reserve_liabilities,
-                        usdc_user_liabilities,
-                        usdc_user_reserve_total_supply,
-                        utilization_rate: reserve.utilization(),
-
-                        ledger: e.ledger().sequence(),
-                        timestamp: e.ledger().timestamp(),
-                    }
-                    .emit(&e);
+                BorrowActionInfo {
+                    pool: e.current_contract_address(),
+                    reserve_address,
+                    user_address,
+                    action_type: symbol_short!("repay"),
+                    amount,
+                    d_tokens: d_burnt_tokens,
+                    user_reserve_total_supply,
+                    user_liabilities,
+                    user_updated_emissions,
+                    reserve_supply,
+                    reserve_liabilities,
+                    usdc_amount,
+                    usdc_reserve_supply,
+                    usdc_reserve_liabilities,
+                    usdc_user_liabilities,
+                    usdc_user_reserve_total_supply,
+                    utilization_rate: reserve.utilization(),
+
+                    ledger: e.ledger().sequence(),
+                    timestamp: e.ledger().timestamp(),
                 }
-
-                pool.cache_reserve(reserve);
+                .emit(&e);
             }
-            RequestType::FillUserLiquidationAuction => {
-                let result = auctions::fill(
-                    e,
-                    pool,
-                    0,
-                    &request.address,
-                    &mut from_state,
-                    request.amount as u64,
-                );
-                check_health = true;
 
+            pool.cache_reserve(reserve);
+        }
+        RequestType::FillUserLiquidationAuction => {
+            let result = auctions::fill(
+                e,
+                pool,
+                0,
+                &request.address,
+                from_state,
+                request.amount as u64,
+            )?;
+            checks.check_health = true;
+            checks.check_isolation = true;
+
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "fill_auction"),
@@ -559,18 +666,21 @@ pub fn build_actions_from_request(
                     (from.clone(), request.amount),
                 );
             }
-            RequestType::FillBadDebtAuction => {
-                // Note: will fail if input address is not the backstop since there cannot be a bad debt auction for a different address in storage
-                let result = auctions::fill(
-                    e,
-                    pool,
-                    1,
-                    &request.address,
-                    &mut from_state,
-                    request.amount as u64,
-                );
-                check_health = true;
-
+        }
+        RequestType::FillBadDebtAuction => {
+            // Note: will fail if input address is not the backstop since there cannot be a bad debt auction for a different address in storage
+            let result = auctions::fill(
+                e,
+                pool,
+                1,
+                &request.address,
+                from_state,
+                request.amount as u64,
+            )?;
+            checks.check_health = true;
+            checks.check_isolation = true;
+
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "fill_auction"),
@@ -580,18 +690,21 @@ pub fn build_actions_from_request(
                     (from.clone(), request.amount),
                 );
             }
+        }
 
-            RequestType::FillInterestAuction => {
-                // Note: will fail if input address is not the backstop since there cannot be an interest auction for a different address in storage
-                let result = auctions::fill(
-                    e,
-                    pool,
-                    2,
-                    &request.address,
-                    &mut from_state,
-                    request.amount as u64,
-                );
-
+        RequestType::FillInterestAuction => {
+            // Note: will fail if input address is not the backstop since there cannot be an interest auction for a different address in storage
+            let result = auctions::fill(
+                e,
+                pool,
+                2,
+                &request.address,
+                from_state,
+                request.amount as u64,
+            )?;
+            checks.check_isolation = true;
+
+            if !preview {
                 e.events().publish(
                     (
                         Symbol::new(e, "fill_auction"),
@@ -601,36 +714,35 @@ pub fn build_actions_from_request(
                     (from.clone(), request.amount),
                 );
             }
-            RequestType::DeleteLiquidationAuction => {
-                // Note: request object is ignored besides type
-                auctions::delete_liquidation(e, &from);
-                check_health = true;
+        }
+        RequestType::DeleteLiquidationAuction => {
+            // Note: request object is ignored besides type
+            auctions::delete_liquidation(e, &from, preview)?;
+            checks.check_health = true;
+            if !preview {
                 e.events().publish(
                     (Symbol::new(&e, "delete_liquidation_auction"), from.clone()),
                     (),
                 );
             }
-            _ => (),
         }
     }
 
-    // Verify max positions haven't been exceeded
-    pool.require_under_max(e, &from_state.positions, prev_positions_count);
-
-    (actions, from_state, check_health)
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
 
     use crate::{
-        constants::SCALAR_7,
-        storage::{self, PoolConfig},
+        constants::{RESERVE_BORROW_DISABLED, SCALAR_7},
+        storage::{self, EModeCategory, PoolConfig, UserIsolatedDebt},
         testutils::{self, create_comet_lp_pool, create_pool},
         AuctionData, AuctionType, Positions,
     };
 
     use super::*;
+    use sep_40_oracle::testutils::Asset;
     use soroban_sdk::{
         map,
         testutils::{Address as _, Ledger, LedgerInfo},
@@ -670,6 +782,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -709,6 +824,57 @@ mod tests {
         });
     }
 
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1222)")]
+    fn test_build_actions_from_request_supply_over_cap() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, reserve_data) = testutils::default_reserve_meta();
+        reserve_config.supply_cap = 105_0000000;
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::SupplyCollateral as u32,
+                    address: underlying.clone(),
+                    amount: 10_0000000,
+                },
+            ];
+            build_actions_from_request(&e, &mut pool, &samwise, requests);
+        });
+    }
+
     /***** withdraw *****/
 
     #[test]
@@ -739,6 +905,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
 
         let user_positions = Positions {
@@ -813,6 +982,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions {
             liabilities: map![&e],
@@ -884,6 +1056,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -956,6 +1131,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions {
             liabilities: map![&e],
@@ -1029,6 +1207,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions {
             liabilities: map![&e],
@@ -1099,6 +1280,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -1134,10 +1318,9 @@ mod tests {
         });
     }
 
-    /***** repay *****/
-
     #[test]
-    fn test_build_actions_from_request_repay() {
+    #[should_panic(expected = "Error(Contract, #1223)")]
+    fn test_build_actions_from_request_borrow_over_cap() {
         let e = Env::default();
         e.mock_all_auths();
 
@@ -1146,7 +1329,8 @@ mod tests {
         let pool = testutils::create_pool(&e);
 
         let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
-        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        let (mut reserve_config, reserve_data) = testutils::default_reserve_meta();
+        reserve_config.borrow_cap = 80_0000000;
         testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
 
         e.ledger().set(LedgerInfo {
@@ -1161,57 +1345,33 @@ mod tests {
         });
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
-            bstop_rate: 0_2000000,
+            bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
-        };
-        let user_positions = Positions {
-            liabilities: map![&e, (0, 20_0000000)],
-            collateral: map![&e],
-            supply: map![&e],
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
-            storage::set_user_positions(&e, &samwise, &user_positions);
 
             let mut pool = Pool::load(&e);
 
             let requests = vec![
                 &e,
                 Request {
-                    request_type: RequestType::Repay as u32,
+                    request_type: RequestType::Borrow as u32,
                     address: underlying.clone(),
-                    amount: 10_1234567,
+                    amount: 10_0000000,
                 },
             ];
-            let (actions, user, health_check) =
-                build_actions_from_request(&e, &mut pool, &samwise, requests);
-
-            assert_eq!(health_check, false);
-
-            let spender_transfer = actions.spender_transfer;
-            let pool_transfer = actions.pool_transfer;
-            assert_eq!(spender_transfer.len(), 1);
-            assert_eq!(
-                spender_transfer.get_unchecked(underlying.clone()),
-                10_1234567
-            );
-            assert_eq!(pool_transfer.len(), 0);
-
-            let positions = user.positions.clone();
-            assert_eq!(positions.liabilities.len(), 1);
-            assert_eq!(positions.collateral.len(), 0);
-            assert_eq!(positions.supply.len(), 0);
-            let d_tokens_repaid = 10_1234451;
-            assert_eq!(user.get_liabilities(0), 20_0000000 - d_tokens_repaid);
-
-            let reserve = pool.load_reserve(&e, &underlying, false);
-            assert_eq!(reserve.d_supply, reserve_data.d_supply - d_tokens_repaid);
+            build_actions_from_request(&e, &mut pool, &samwise, requests);
         });
     }
 
     #[test]
-    fn test_build_actions_from_request_repay_over_balance() {
+    #[should_panic(expected = "Error(Contract, #1224)")]
+    fn test_build_actions_from_request_borrow_disabled() {
         let e = Env::default();
         e.mock_all_auths();
 
@@ -1220,7 +1380,8 @@ mod tests {
         let pool = testutils::create_pool(&e);
 
         let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
-        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.flags = RESERVE_BORROW_DISABLED;
         testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
 
         e.ledger().set(LedgerInfo {
@@ -1235,56 +1396,33 @@ mod tests {
         });
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
-            bstop_rate: 0_2000000,
+            bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
-        };
-        let user_positions = Positions {
-            liabilities: map![&e, (0, 20_0000000)],
-            collateral: map![&e],
-            supply: map![&e],
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
-            storage::set_user_positions(&e, &samwise, &user_positions);
 
             let mut pool = Pool::load(&e);
 
             let requests = vec![
                 &e,
                 Request {
-                    request_type: RequestType::Repay as u32,
+                    request_type: RequestType::Borrow as u32,
                     address: underlying.clone(),
-                    amount: 21_0000000,
+                    amount: 10_0000000,
                 },
             ];
-            let (actions, user, health_check) =
-                build_actions_from_request(&e, &mut pool, &samwise, requests);
-
-            assert_eq!(health_check, false);
-
-            let spender_transfer = actions.spender_transfer;
-            let pool_transfer = actions.pool_transfer;
-            assert_eq!(spender_transfer.len(), 1);
-            assert_eq!(
-                spender_transfer.get_unchecked(underlying.clone()),
-                21_0000000
-            );
-            assert_eq!(pool_transfer.len(), 1);
-            assert_eq!(pool_transfer.get_unchecked(underlying.clone()), 0_9999771);
-
-            let positions = user.positions.clone();
-            assert_eq!(positions.liabilities.len(), 0);
-            assert_eq!(positions.collateral.len(), 0);
-            assert_eq!(positions.supply.len(), 0);
-
-            let reserve = pool.load_reserve(&e, &underlying, false);
-            assert_eq!(reserve.d_supply, reserve_data.d_supply - 20_0000000);
+            build_actions_from_request(&e, &mut pool, &samwise, requests);
         });
     }
 
     #[test]
-    fn test_aggregating_actions() {
+    #[should_panic(expected = "Error(Contract, #1227)")]
+    fn test_build_actions_from_request_borrow_outside_emode() {
         let e = Env::default();
         e.mock_all_auths();
 
@@ -1293,15 +1431,13 @@ mod tests {
         let pool = testutils::create_pool(&e);
 
         let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
-        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
-        reserve_data.last_time = 600;
-        testutils::create_reserve(
-            &e,
-            &pool,
-            &underlying.clone(),
-            &reserve_config,
-            &reserve_data,
-        );
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, reserve_data) = testutils::default_reserve_meta();
+        reserve_config.index = 1;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
 
         e.ledger().set(LedgerInfo {
             timestamp: 600,
@@ -1315,11 +1451,327 @@ mod tests {
         });
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
-            bstop_rate: 0_2000000,
+            bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_emode_category(
+                &e,
+                1,
+                &EModeCategory {
+                    c_factor: 0_9500000,
+                    l_factor: 0_9500000,
+                    assets: vec![&e, underlying_1.clone()],
+                },
+            );
+            storage::set_user_emode(&e, &samwise, 1);
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::Borrow as u32,
+                    address: underlying.clone(),
+                    amount: 10_0000000,
+                },
+            ];
+            build_actions_from_request(&e, &mut pool, &samwise, requests);
+        });
+    }
+
+    #[test]
+    fn test_build_actions_from_request_borrow_isolated() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        reserve_config.isolated = true;
+        reserve_config.debt_ceiling = 100_0000000;
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        reserve_config.index = 1;
+        reserve_config.isolated_borrow = true;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 1_0000000, 1_5000000]);
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::SupplyCollateral as u32,
+                    address: underlying_0.clone(),
+                    amount: 50_0000000,
+                },
+                Request {
+                    request_type: RequestType::Borrow as u32,
+                    address: underlying_1.clone(),
+                    amount: 10_0000000,
+                },
+                Request {
+                    request_type: RequestType::Repay as u32,
+                    address: underlying_1.clone(),
+                    amount: 4_0000000,
+                },
+            ];
+            let (_, user, health_check) =
+                build_actions_from_request(&e, &mut pool, &samwise, requests);
+
+            assert!(health_check);
+            assert_eq!(user.positions.collateral.len(), 1);
+            assert_eq!(user.positions.liabilities.len(), 1);
+            // 6 tokens of debt priced at 1.5
+            assert_eq!(storage::get_isolated_debt(&e, &underlying_0), 9_0000000);
+            let user_debt = storage::get_user_isolated_debt(&e, &samwise).unwrap();
+            assert_eq!(user_debt.asset, underlying_0);
+            assert_eq!(user_debt.debt, 9_0000000);
+        });
+    }
+
+    /***** repay *****/
+
+    #[test]
+    fn test_build_actions_from_request_repay() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let user_positions = Positions {
+            liabilities: map![&e, (0, 20_0000000)],
+            collateral: map![&e],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &samwise, &user_positions);
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::Repay as u32,
+                    address: underlying.clone(),
+                    amount: 10_1234567,
+                },
+            ];
+            let (actions, user, health_check) =
+                build_actions_from_request(&e, &mut pool, &samwise, requests);
+
+            assert_eq!(health_check, false);
+
+            let spender_transfer = actions.spender_transfer;
+            let pool_transfer = actions.pool_transfer;
+            assert_eq!(spender_transfer.len(), 1);
+            assert_eq!(
+                spender_transfer.get_unchecked(underlying.clone()),
+                10_1234567
+            );
+            assert_eq!(pool_transfer.len(), 0);
+
+            let positions = user.positions.clone();
+            assert_eq!(positions.liabilities.len(), 1);
+            assert_eq!(positions.collateral.len(), 0);
+            assert_eq!(positions.supply.len(), 0);
+            let d_tokens_repaid = 10_1234451;
+            assert_eq!(user.get_liabilities(0), 20_0000000 - d_tokens_repaid);
+
+            let reserve = pool.load_reserve(&e, &underlying, false);
+            assert_eq!(reserve.d_supply, reserve_data.d_supply - d_tokens_repaid);
+        });
+    }
+
+    #[test]
+    fn test_build_actions_from_request_repay_over_balance() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let user_positions = Positions {
+            liabilities: map![&e, (0, 20_0000000)],
+            collateral: map![&e],
+            supply: map![&e],
         };
-        let user_positions = Positions::env_default(&e);
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &samwise, &user_positions);
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::Repay as u32,
+                    address: underlying.clone(),
+                    amount: 21_0000000,
+                },
+            ];
+            let (actions, user, health_check) =
+                build_actions_from_request(&e, &mut pool, &samwise, requests);
+
+            assert_eq!(health_check, false);
+
+            let spender_transfer = actions.spender_transfer;
+            let pool_transfer = actions.pool_transfer;
+            assert_eq!(spender_transfer.len(), 1);
+            assert_eq!(
+                spender_transfer.get_unchecked(underlying.clone()),
+                21_0000000
+            );
+            assert_eq!(pool_transfer.len(), 1);
+            assert_eq!(pool_transfer.get_unchecked(underlying.clone()), 0_9999771);
+
+            let positions = user.positions.clone();
+            assert_eq!(positions.liabilities.len(), 0);
+            assert_eq!(positions.collateral.len(), 0);
+            assert_eq!(positions.supply.len(), 0);
+
+            let reserve = pool.load_reserve(&e, &underlying, false);
+            assert_eq!(reserve.d_supply, reserve_data.d_supply - 20_0000000);
+        });
+    }
+
+    #[test]
+    fn test_aggregating_actions() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        testutils::create_reserve(
+            &e,
+            &pool,
+            &underlying.clone(),
+            &reserve_config,
+            &reserve_data,
+        );
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let user_positions = Positions::env_default(&e);
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
             storage::set_user_positions(&e, &samwise, &user_positions);
@@ -1463,12 +1915,16 @@ mod tests {
                 (underlying_1.clone(), 1_5395739)
             ],
             block: 176,
+            curve: Default::default(),
         };
         let pool_config = PoolConfig {
             oracle: oracle_address,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![
@@ -1511,6 +1967,7 @@ mod tests {
                     (underlying_1.clone(), 7697870)
                 ],
                 block: 176,
+                curve: Default::default(),
             };
             let new_auction =
                 storage::get_auction(&e, &(AuctionType::UserLiquidation as u32), &samwise);
@@ -1522,6 +1979,303 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_fill_user_liquidation_tracks_isolated_debt() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 176 + 200,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_100_000_000;
+        reserve_config_0.c_factor = 0_8500000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        reserve_config_0.isolated = true;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_data_1.b_rate = 1_200_000_000;
+        reserve_config_1.c_factor = 0_7500000;
+        reserve_config_1.l_factor = 0_7500000;
+        reserve_data_1.last_time = 12345;
+        reserve_config_1.index = 1;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_2, mut reserve_data_2) = testutils::default_reserve_meta();
+        reserve_data_2.last_time = 12345;
+        reserve_config_2.c_factor = 0_0000000;
+        reserve_config_2.l_factor = 0_7000000;
+        reserve_config_2.index = 2;
+        reserve_config_2.isolated_borrow = true;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_2,
+            &reserve_config_2,
+            &reserve_data_2,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+                Asset::Stellar(underlying_2.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 1_0000000, 1_0000000, 2_0000000]);
+
+        let auction_data = AuctionData {
+            bid: map![&e, (underlying_2.clone(), 1_2375000)],
+            lot: map![&e, (underlying_0.clone(), 30_5595329)],
+            block: 176,
+            curve: Default::default(),
+        };
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let positions: Positions = Positions {
+            collateral: map![&e, (reserve_config_0.index, 90_9100000)],
+            liabilities: map![&e, (reserve_config_2.index, 02_7500000),],
+            supply: map![&e],
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_auction(
+                &e,
+                &(AuctionType::UserLiquidation as u32),
+                &samwise,
+                &auction_data,
+            );
+
+            storage::set_user_isolated_debt(
+                &e,
+                &samwise,
+                &Some(UserIsolatedDebt {
+                    asset: underlying_0.clone(),
+                    debt: 2_7500000,
+                }),
+            );
+            storage::set_isolated_debt(&e, &underlying_0, 2_7500000);
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::FillUserLiquidationAuction as u32,
+                    address: samwise.clone(),
+                    amount: 50,
+                },
+            ];
+            build_actions_from_request(&e, &mut pool, &frodo, requests);
+
+            // half of the liquidated user's debt moves to the filler, and both are valued at
+            // the current price of 2
+            let samwise_debt = storage::get_user_isolated_debt(&e, &samwise).unwrap();
+            assert_eq!(samwise_debt.debt, 4_2625000);
+            let frodo_debt = storage::get_user_isolated_debt(&e, &frodo).unwrap();
+            assert_eq!(frodo_debt.asset, underlying_0);
+            assert_eq!(frodo_debt.debt, 1_2375000);
+            assert_eq!(storage::get_isolated_debt(&e, &underlying_0), 5_5000000);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1228)")]
+    fn test_fill_user_liquidation_checks_filler_isolation() {
+        let e = Env::default();
+
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 12345,
+            protocol_version: 20,
+            sequence_number: 176 + 200,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        let pool_address = create_pool(&e);
+
+        let (oracle_address, oracle_client) = testutils::create_mock_oracle(&e);
+
+        // creating reserves for a pool exhausts the budget
+        e.budget().reset_unlimited();
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_0, mut reserve_data_0) = testutils::default_reserve_meta();
+        reserve_data_0.last_time = 12345;
+        reserve_data_0.b_rate = 1_100_000_000;
+        reserve_config_0.c_factor = 0_8500000;
+        reserve_config_0.l_factor = 0_9000000;
+        reserve_config_0.index = 0;
+        reserve_config_0.isolated = true;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_0,
+            &reserve_config_0,
+            &reserve_data_0,
+        );
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_1, mut reserve_data_1) = testutils::default_reserve_meta();
+        reserve_data_1.b_rate = 1_200_000_000;
+        reserve_config_1.c_factor = 0_7500000;
+        reserve_config_1.l_factor = 0_7500000;
+        reserve_data_1.last_time = 12345;
+        reserve_config_1.index = 1;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_1,
+            &reserve_config_1,
+            &reserve_data_1,
+        );
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config_2, reserve_data_2) = testutils::default_reserve_meta();
+        reserve_config_2.c_factor = 0_0000000;
+        reserve_config_2.l_factor = 0_7000000;
+        reserve_config_2.index = 2;
+        testutils::create_reserve(
+            &e,
+            &pool_address,
+            &underlying_2,
+            &reserve_config_2,
+            &reserve_data_2,
+        );
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+                Asset::Stellar(underlying_2.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 1_0000000, 1_0000000, 2_0000000]);
+
+        let auction_data = AuctionData {
+            bid: map![&e, (underlying_2.clone(), 1_2375000)],
+            lot: map![
+                &e,
+                (underlying_0.clone(), 30_5595329),
+                (underlying_1.clone(), 1_5395739)
+            ],
+            block: 176,
+            curve: Default::default(),
+        };
+        let pool_config = PoolConfig {
+            oracle: oracle_address,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let positions: Positions = Positions {
+            collateral: map![
+                &e,
+                (reserve_config_0.index, 90_9100000),
+                (reserve_config_1.index, 04_5800000),
+            ],
+            liabilities: map![&e, (reserve_config_2.index, 02_7500000),],
+            supply: map![&e],
+        };
+        e.as_contract(&pool_address, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_auction(
+                &e,
+                &(AuctionType::UserLiquidation as u32),
+                &samwise,
+                &auction_data,
+            );
+
+            // the filler's isolated collateral can't be mixed with the lot's other collateral
+            storage::set_user_positions(
+                &e,
+                &frodo,
+                &Positions {
+                    collateral: map![&e, (reserve_config_0.index, 10_0000000)],
+                    liabilities: map![&e],
+                    supply: map![&e],
+                },
+            );
+
+            let mut pool = Pool::load(&e);
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::FillUserLiquidationAuction as u32,
+                    address: samwise.clone(),
+                    amount: 50,
+                },
+            ];
+            build_actions_from_request(&e, &mut pool, &frodo, requests);
+        });
+    }
+
     #[test]
     fn test_fill_bad_debt_auction() {
         let e = Env::default();
@@ -1593,11 +2347,15 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let auction_data = AuctionData {
             bid: map![&e, (underlying_0, 10_0000000), (underlying_1, 2_5000000)],
             lot: map![&e, (backstop_token_id, 95_2000000)],
             block: 51,
+            curve: Default::default(),
         };
         let positions: Positions = Positions {
             collateral: map![&e],
@@ -1739,6 +2497,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let auction_data = AuctionData {
             bid: map![&e, (backstop_token_id.clone(), 100_0000000)],
@@ -1748,6 +2509,7 @@ mod tests {
                 (underlying_1.clone(), 25_0000000)
             ],
             block: 51,
+            curve: Default::default(),
         };
 
         e.as_contract(&pool_address, || {
@@ -1826,6 +2588,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let auction_data = AuctionData {
             bid: map![&e, (underlying_0.clone(), 952_0000000)],
@@ -1835,6 +2600,7 @@ mod tests {
                 (underlying_1.clone(), 25_0000000)
             ],
             block: 51,
+            curve: Default::default(),
         };
 
         e.as_contract(&pool_address, || {
@@ -1905,6 +2671,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
 
         let user_positions = Positions {
@@ -1933,6 +2702,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "catchup"))]
     #[should_panic(expected = "Error(Contract, #1208)")]
     fn test_actions_requires_positions_under_max() {
         let e = Env::default();
@@ -1962,6 +2732,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 1,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
 
         let user_positions = Positions {
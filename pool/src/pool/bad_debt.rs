@@ -1,11 +1,18 @@
+#[cfg(feature = "retroshades")]
+use soroban_sdk::symbol_short;
 use soroban_sdk::{panic_with_error, Address, Env, Symbol};
 
+#[cfg(feature = "retroshades")]
+use crate::retroshades::BadDebtInfo;
+
 use crate::{
+    auctions::AuctionType,
+    dependencies::BackstopClient,
     errors::PoolError,
     storage::{self},
 };
 
-use super::{user::User, Pool};
+use super::{isolation::update_isolated_debt, user::User, Pool};
 
 /// Transfer bad debt from a user to the backstop. Validates that the user does hold bad debt
 /// and transfers all held d_tokens to the backstop.
@@ -33,11 +40,15 @@ pub fn transfer_bad_debt_to_backstop(e: &Env, user: &Address) {
     let backstop_state = User::load(e, &backstop_address);
     let mut new_user_state = user_state.clone();
     let mut new_backstop_state = backstop_state.clone();
+    #[cfg(feature = "retroshades")]
+    let mut bad_debt_info = BadDebtInfo::new(e, user, symbol_short!("transfer"));
     for (reserve_index, liability_balance) in user_state.positions.liabilities.iter() {
         let asset = reserve_list.get_unchecked(reserve_index);
         let mut reserve = pool.load_reserve(e, &asset, true);
         new_backstop_state.add_liabilities(e, &mut reserve, liability_balance);
         new_user_state.remove_liabilities(e, &mut reserve, liability_balance);
+        #[cfg(feature = "retroshades")]
+        bad_debt_info.push(e, &reserve, liability_balance);
         pool.cache_reserve(reserve);
 
         e.events().publish(
@@ -46,19 +57,106 @@ pub fn transfer_bad_debt_to_backstop(e: &Env, user: &Address) {
         );
     }
 
+    // the user no longer holds any liabilities, so none of their debt is backed by isolated collateral
+    update_isolated_debt(e, user, &mut pool, &new_user_state.positions);
+
     pool.store_cached_reserves(e);
     new_backstop_state.store(e);
     new_user_state.store(e);
+
+    #[cfg(feature = "retroshades")]
+    bad_debt_info.emit(e);
+}
+
+/// Set the backstop token balance below which the backstop's bad debt can be defaulted. A floor
+/// of 0 disables defaulting.
+///
+/// ### Arguments
+/// * `floor` - The backstop token balance
+///
+/// ### Panics
+/// If the floor is negative
+pub fn execute_set_bad_debt_floor(e: &Env, floor: i128) {
+    if floor < 0 {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    storage::set_bad_debt_floor(e, &floor);
+}
+
+/// Default on the backstop's bad debt once the backstop can no longer cover it. All of the
+/// backstop's liabilities are burnt, and the loss is socialized to the suppliers of each reserve
+/// by reducing the reserve's b_rate.
+///
+/// ### Panics
+/// If the backstop holds at least the bad debt floor in tokens, the backstop does not have any
+/// liabilities, or a bad debt auction is in progress
+pub fn burn_backstop_bad_debt(e: &Env) {
+    let backstop_address = storage::get_backstop(e);
+    if storage::has_auction(e, &(AuctionType::BadDebtAuction as u32), &backstop_address) {
+        panic_with_error!(e, PoolError::AuctionInProgress);
+    }
+
+    // the backstop must no longer be able to cover the bad debt with an auction
+    let backstop_client = BackstopClient::new(e, &backstop_address);
+    let pool_backstop_data = backstop_client.pool_data(&e.current_contract_address());
+    if pool_backstop_data.tokens >= storage::get_bad_debt_floor(e) {
+        panic_with_error!(e, PoolError::BackstopNotExhausted);
+    }
+
+    let backstop_state = User::load(e, &backstop_address);
+    if backstop_state.positions.liabilities.is_empty() {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+
+    let mut pool = Pool::load(e);
+    let reserve_list = storage::get_res_list(e);
+    let mut new_backstop_state = backstop_state.clone();
+    #[cfg(feature = "retroshades")]
+    let mut bad_debt_info = BadDebtInfo::new(e, &backstop_address, symbol_short!("default"));
+    for (reserve_index, liability_balance) in backstop_state.positions.liabilities.iter() {
+        let asset = reserve_list.get_unchecked(reserve_index);
+        let mut reserve = pool.load_reserve(e, &asset, true);
+        #[cfg(feature = "retroshades")]
+        bad_debt_info.push(e, &reserve, liability_balance);
+        let socialized_amount =
+            new_backstop_state.default_liabilities(e, &mut reserve, liability_balance);
+        pool.cache_reserve(reserve);
+
+        e.events().publish(
+            (Symbol::new(e, "defaulted_debt"), asset),
+            (liability_balance, socialized_amount),
+        );
+    }
+
+    // the backstop no longer holds any liabilities, so none of its debt is backed by isolated collateral
+    update_isolated_debt(
+        e,
+        &backstop_address,
+        &mut pool,
+        &new_backstop_state.positions,
+    );
+
+    pool.store_cached_reserves(e);
+    new_backstop_state.store(e);
+
+    #[cfg(feature = "retroshades")]
+    bad_debt_info.emit(e);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{pool::Positions, storage::PoolConfig, testutils};
+    use crate::{
+        auctions::AuctionData,
+        pool::Positions,
+        storage::{PoolConfig, UserIsolatedDebt},
+        testutils,
+    };
 
     use super::*;
     use soroban_sdk::{
         map,
         testutils::{Address as _, Ledger, LedgerInfo},
+        vec,
     };
 
     /***** transfer_bad_debt_to_backstop ******/
@@ -99,6 +197,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions {
             liabilities: map![&e, (0, 24_0000000), (1, 25_0000000)],
@@ -128,6 +229,71 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_transfer_bad_debt_clears_isolated_debt() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 123,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let pool = testutils::create_pool(&e);
+        let backstop = Address::generate(&e);
+
+        let samwise = Address::generate(&e);
+        let bombadil = Address::generate(&e);
+        let isolated_asset = Address::generate(&e);
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let user_positions = Positions {
+            liabilities: map![&e, (0, 24_0000000)],
+            collateral: map![&e],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_backstop(&e, &backstop);
+            storage::set_user_positions(&e, &samwise, &user_positions);
+            storage::set_isolated_debt(&e, &isolated_asset, 60_0000000);
+            storage::set_user_isolated_debt(
+                &e,
+                &samwise,
+                &Some(UserIsolatedDebt {
+                    asset: isolated_asset.clone(),
+                    debt: 49_0000000,
+                }),
+            );
+
+            e.budget().reset_unlimited();
+            transfer_bad_debt_to_backstop(&e, &samwise);
+
+            assert!(storage::get_user_isolated_debt(&e, &samwise).is_none());
+            assert!(storage::get_user_isolated_debt(&e, &backstop).is_none());
+            assert_eq!(storage::get_isolated_debt(&e, &isolated_asset), 11_0000000);
+        });
+    }
+
     #[test]
     #[should_panic(expected = "Error(Contract, #1200)")]
     fn test_transfer_bad_debt_with_collateral_panics() {
@@ -166,6 +332,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions {
             liabilities: map![&e, (0, 24_0000000), (1, 25_0000000)],
@@ -219,6 +388,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions::env_default(&e);
         e.as_contract(&pool, || {
@@ -268,6 +440,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         let user_positions = Positions {
             liabilities: map![&e, (0, 24_0000000), (1, 25_0000000)],
@@ -283,4 +458,280 @@ mod tests {
             transfer_bad_debt_to_backstop(&e, &backstop);
         });
     }
+
+    /***** burn_backstop_bad_debt ******/
+
+    #[test]
+    fn test_burn_backstop_bad_debt() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.budget().reset_unlimited(); // setup exhausts budget
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 123,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (blnd, _) = testutils::create_blnd_token(&e, &pool, &bombadil);
+        let (usdc, _) = testutils::create_token_contract(&e, &bombadil);
+        let (lp_token, _) = testutils::create_comet_lp_pool(&e, &bombadil, &blnd, &usdc);
+        let (backstop, backstop_client) = testutils::create_backstop(&e);
+        testutils::setup_backstop(&e, &pool, &backstop, &lp_token, &usdc, &blnd);
+        backstop_client.update_tkn_val();
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.index = 1;
+        reserve_data.last_time = 600;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let backstop_positions = Positions {
+            liabilities: map![&e, (0, 24_0000000), (1, 25_0000000)],
+            collateral: map![&e],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &backstop, &backstop_positions);
+            storage::set_bad_debt_floor(&e, &1);
+
+            burn_backstop_bad_debt(&e);
+
+            let new_backstop_positions = storage::get_user_positions(&e, &backstop);
+            assert_eq!(new_backstop_positions.liabilities.len(), 0);
+
+            let reserve_data_0 = storage::get_res_data(&e, &underlying_0);
+            assert_eq!(reserve_data_0.d_supply, 51_0000000);
+            assert_eq!(reserve_data_0.b_supply, 100_0000000);
+            assert_eq!(reserve_data_0.b_rate, 0_760_000_000);
+            let reserve_data_1 = storage::get_res_data(&e, &underlying_1);
+            assert_eq!(reserve_data_1.d_supply, 50_0000000);
+            assert_eq!(reserve_data_1.b_supply, 100_0000000);
+            assert_eq!(reserve_data_1.b_rate, 0_750_000_000);
+        });
+    }
+
+    #[test]
+    fn test_burn_backstop_bad_debt_clears_isolated_debt() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.budget().reset_unlimited(); // setup exhausts budget
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 123,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+        let isolated_asset = Address::generate(&e);
+
+        let (blnd, _) = testutils::create_blnd_token(&e, &pool, &bombadil);
+        let (usdc, _) = testutils::create_token_contract(&e, &bombadil);
+        let (lp_token, _) = testutils::create_comet_lp_pool(&e, &bombadil, &blnd, &usdc);
+        let (backstop, backstop_client) = testutils::create_backstop(&e);
+        testutils::setup_backstop(&e, &pool, &backstop, &lp_token, &usdc, &blnd);
+        backstop_client.update_tkn_val();
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.index = 1;
+        reserve_data.last_time = 600;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let backstop_positions = Positions {
+            liabilities: map![&e, (0, 24_0000000), (1, 25_0000000)],
+            collateral: map![&e],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &backstop, &backstop_positions);
+            storage::set_bad_debt_floor(&e, &1);
+            storage::set_isolated_debt(&e, &isolated_asset, 49_0000000);
+            storage::set_user_isolated_debt(
+                &e,
+                &backstop,
+                &Some(UserIsolatedDebt {
+                    asset: isolated_asset.clone(),
+                    debt: 49_0000000,
+                }),
+            );
+
+            burn_backstop_bad_debt(&e);
+
+            assert!(storage::get_user_isolated_debt(&e, &backstop).is_none());
+            assert_eq!(storage::get_isolated_debt(&e, &isolated_asset), 0);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1231)")]
+    fn test_burn_backstop_bad_debt_over_floor_panics() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.budget().reset_unlimited(); // setup exhausts budget
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 123,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+
+        let (blnd, blnd_client) = testutils::create_blnd_token(&e, &pool, &bombadil);
+        let (usdc, usdc_client) = testutils::create_token_contract(&e, &bombadil);
+        let (lp_token, lp_token_client) =
+            testutils::create_comet_lp_pool(&e, &bombadil, &blnd, &usdc);
+        let (backstop, backstop_client) = testutils::create_backstop(&e);
+        testutils::setup_backstop(&e, &pool, &backstop, &lp_token, &usdc, &blnd);
+        blnd_client.mint(&samwise, &1_001_0000000);
+        blnd_client.approve(&samwise, &lp_token, &i128::MAX, &99999);
+        usdc_client.mint(&samwise, &26_0000000);
+        usdc_client.approve(&samwise, &lp_token, &i128::MAX, &99999);
+        lp_token_client.join_pool(&100_0000000, &vec![&e, 1_001_0000000, 26_0000000], &samwise);
+        backstop_client.deposit(&samwise, &pool, &100_0000000);
+        backstop_client.update_tkn_val();
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let backstop_positions = Positions {
+            liabilities: map![&e, (0, 24_0000000)],
+            collateral: map![&e],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &backstop, &backstop_positions);
+            // a backstop holding exactly the floor is not exhausted
+            storage::set_bad_debt_floor(&e, &100_0000000);
+
+            burn_backstop_bad_debt(&e);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1212)")]
+    fn test_burn_backstop_bad_debt_auction_in_progress_panics() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+        let backstop = Address::generate(&e);
+
+        let auction_data = AuctionData {
+            bid: map![&e],
+            lot: map![&e],
+            block: 50,
+            curve: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_backstop(&e, &backstop);
+            storage::set_auction(
+                &e,
+                &(AuctionType::BadDebtAuction as u32),
+                &backstop,
+                &auction_data,
+            );
+
+            burn_backstop_bad_debt(&e);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_burn_backstop_bad_debt_without_liabilities_panics() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.budget().reset_unlimited(); // setup exhausts budget
+
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (blnd, _) = testutils::create_blnd_token(&e, &pool, &bombadil);
+        let (usdc, _) = testutils::create_token_contract(&e, &bombadil);
+        let (lp_token, _) = testutils::create_comet_lp_pool(&e, &bombadil, &blnd, &usdc);
+        let (backstop, backstop_client) = testutils::create_backstop(&e);
+        testutils::setup_backstop(&e, &pool, &backstop, &lp_token, &usdc, &blnd);
+        backstop_client.update_tkn_val();
+
+        e.as_contract(&pool, || {
+            storage::set_bad_debt_floor(&e, &1);
+            burn_backstop_bad_debt(&e);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_bad_debt_floor_negative_panics() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        e.as_contract(&pool, || {
+            execute_set_bad_debt_floor(&e, 1_0000000);
+            assert_eq!(storage::get_bad_debt_floor(&e), 1_0000000);
+
+            execute_set_bad_debt_floor(&e, -1);
+        });
+    }
 }
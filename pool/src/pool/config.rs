@@ -1,10 +1,18 @@
+#[cfg(feature = "retroshades")]
+use crate::storage::ReserveOracleConfig;
 use crate::{
-    constants::{SCALAR_7, SCALAR_9, SECONDS_PER_WEEK},
+    constants::{
+        DEFAULT_MAX_PRICE_AGE, MAX_BACKSTOP_PC, MAX_RESERVE_ORACLES, MAX_TWAP_RECORDS,
+        RATE_MODEL_FIXED, RATE_MODEL_REACTIVE, RATE_MODEL_STATIC, RATE_MODEL_TWO_SLOPE,
+        RESERVE_FLAGS, RESERVE_SUNSET, SCALAR_7, SCALAR_9, SECONDS_PER_WEEK,
+    },
     errors::PoolError,
     storage::{
-        self, has_queued_reserve_set, PoolConfig, QueuedReserveInit, ReserveConfig, ReserveData,
+        self, has_queued_reserve_set, AuctionConfig, AuctionCurve, PoolConfig, QueuedPoolUpdate,
+        QueuedReserveInit, ReserveConfig, ReserveData, ReservePriceConfig, StatusConfig,
     },
 };
+use sep_40_oracle::PriceFeedClient;
 use soroban_sdk::{panic_with_error, Address, Env, String};
 
 use super::pool::Pool;
@@ -47,6 +55,9 @@ pub fn execute_initialize(
             bstop_rate: *bstop_rate,
             status: 6,
             max_positions: *max_positions,
+            max_price_age: DEFAULT_MAX_PRICE_AGE,
+            auction_config: AuctionConfig::default(),
+            status_config: StatusConfig::default(),
         },
     );
     storage::set_blnd_token(e, blnd_id);
@@ -54,16 +65,100 @@ pub fn execute_initialize(
     storage::set_is_init(e);
 }
 
-/// Update the pool
-pub fn execute_update_pool(e: &Env, backstop_take_rate: u32, max_positions: u32) {
+/// Execute queueing an update to the pool's config. The pool's status is not updated, and is
+/// managed through `set_status` and `update_status`.
+///
+/// ### Panics
+/// If an update is already queued, the config is invalid, or a new oracle does not report prices
+/// with the same decimals as the current oracle
+pub fn execute_queue_update_pool(e: &Env, new_config: &PoolConfig) {
+    if storage::get_queued_pool_update(e).is_some() {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
     // ensure backstop is [0,1)
-    if backstop_take_rate >= SCALAR_7 as u32 {
+    if new_config.bstop_rate >= SCALAR_7 as u32 {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    // ensure prices can be used at all
+    if new_config.max_price_age == 0 {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    if new_config.max_positions < 2 {
         panic_with_error!(e, PoolError::BadRequest);
     }
-    let mut pool_config = storage::get_pool_config(e);
-    pool_config.bstop_rate = backstop_take_rate;
-    pool_config.max_positions = max_positions;
-    storage::set_pool_config(e, &pool_config);
+    let auction_config = &new_config.auction_config;
+    if !is_valid_auction_curve(&auction_config.liquidation)
+        || !is_valid_auction_curve(&auction_config.bad_debt)
+        || !is_valid_auction_curve(&auction_config.interest)
+    {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    if !is_valid_status_config(&new_config.status_config) {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+
+    let mut pool = Pool::load(e);
+    // reserve price configs require oracles to share the decimals of the pool's oracle
+    if new_config.oracle != pool.config.oracle
+        && PriceFeedClient::new(e, &new_config.oracle).decimals() != pool.load_price_decimals(e)
+    {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+
+    let mut unlock_time = e.ledger().timestamp();
+    // require a timelock if pool status is not setup
+    if pool.config.status != 6 {
+        unlock_time += SECONDS_PER_WEEK;
+    }
+    storage::set_queued_pool_update(
+        e,
+        &QueuedPoolUpdate {
+            new_config: new_config.clone(),
+            unlock_time,
+        },
+    );
+}
+
+/// Execute cancelling a queued update to the pool's config
+pub fn execute_cancel_queued_update_pool(e: &Env) {
+    storage::del_queued_pool_update(e);
+}
+
+/// Execute a queued update to the pool's config. Returns the new config.
+///
+/// ### Panics
+/// If no update is queued or the update is still timelocked
+pub fn execute_update_pool(e: &Env) -> PoolConfig {
+    let queued_update = match storage::get_queued_pool_update(e) {
+        Some(queued_update) => queued_update,
+        None => panic_with_error!(e, PoolError::BadRequest),
+    };
+    if queued_update.unlock_time > e.ledger().timestamp() {
+        panic_with_error!(e, PoolError::InitNotUnlocked);
+    }
+
+    storage::del_queued_pool_update(e);
+
+    let mut new_config = queued_update.new_config;
+    new_config.status = storage::get_pool_config(e).status;
+    storage::set_pool_config(e, &new_config);
+    new_config
+}
+
+/// Execute accepting the proposed admin of the pool. Returns the new admin.
+///
+/// ### Panics
+/// If no admin has been proposed or the proposed admin has not authorized the call
+pub fn execute_accept_admin(e: &Env) -> Address {
+    let new_admin = match storage::get_pending_admin(e) {
+        Some(pending_admin) => pending_admin,
+        None => panic_with_error!(e, PoolError::BadRequest),
+    };
+    new_admin.require_auth();
+
+    storage::set_admin(e, &new_admin);
+    storage::del_pending_admin(e);
+    new_admin
 }
 
 /// Execute a queueing a reserve initialization for the pool
@@ -107,6 +202,102 @@ pub fn execute_set_reserve(e: &Env, asset: &Address) -> u32 {
     initialize_reserve(e, asset, &queued_init.new_config)
 }
 
+/// Execute setting the oracle routing for a reserve
+///
+/// Panics if the reserve does not exist
+#[cfg(feature = "retroshades")]
+pub fn execute_set_reserve_oracle(e: &Env, asset: &Address, config: &ReserveOracleConfig) {
+    if !storage::has_res(e, asset) {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    storage::set_res_oracle(e, asset, config);
+}
+
+/// Execute setting the price config for a reserve
+///
+/// Panics if the reserve does not exist or the config is invalid
+pub fn execute_set_reserve_price_config(e: &Env, asset: &Address, config: &ReservePriceConfig) {
+    if !storage::has_res(e, asset) {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    require_valid_price_config(e, config);
+    storage::set_res_price_config(e, asset, config);
+}
+
+/// Execute setting the status flags for a reserve. Takes effect immediately.
+///
+/// Panics if the reserve does not exist or the flags are invalid
+pub fn execute_set_reserve_flags(e: &Env, asset: &Address, flags: u32) {
+    if !storage::has_res(e, asset) || flags & !RESERVE_FLAGS != 0 {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    // accrue the reserve before storing it
+    let mut pool = Pool::load(e);
+    let mut reserve = pool.load_reserve(e, asset, false);
+    // the sunset flag is managed by the sunset process
+    reserve.flags = flags | (reserve.flags & RESERVE_SUNSET);
+    reserve.store(e);
+}
+
+/// Check that the auction curve moves in whole steps and the bid eventually decays to 0%
+fn is_valid_auction_curve(curve: &AuctionCurve) -> bool {
+    curve.step > 0
+        && curve.decay > 0
+        && curve.ramp % curve.step == 0
+        && curve.decay % curve.step == 0
+        // a start premium requires a ramp to decrease it over
+        && (curve.start_premium.is_none() || curve.ramp > 0)
+}
+
+/// Check that the status thresholds are within bounds and that each status triggers in order
+///
+/// ### Arguments
+/// * `config` - The status thresholds to validate
+fn is_valid_status_config(config: &StatusConfig) -> bool {
+    let scalar_7 = SCALAR_7 as u32;
+    config.on_ice_q4w > 0
+        && config.admin_frozen_q4w <= scalar_7
+        // the admin set status can only hold the pool at a stricter limit
+        && config.on_ice_q4w <= config.admin_on_ice_q4w
+        && config.frozen_q4w <= config.admin_frozen_q4w
+        // the pool must go on-ice before it is frozen
+        && config.on_ice_q4w < config.frozen_q4w
+        && config.admin_on_ice_q4w < config.admin_frozen_q4w
+        && config.backstop_pc > 0
+        && config.backstop_pc <= MAX_BACKSTOP_PC
+}
+
+/// Require that the reserve's oracles, quorum, deviation and TWAP settings are valid, and that
+/// each oracle reports prices with the same decimals as the pool's oracle
+///
+/// ### Arguments
+/// * `config` - The reserve price config to validate
+///
+/// ### Panics
+/// If the price config is invalid
+fn require_valid_price_config(e: &Env, config: &ReservePriceConfig) {
+    let num_oracles = config.oracles.len();
+    if num_oracles > MAX_RESERVE_ORACLES
+        || config.min_quorum == 0
+        || config.min_quorum > num_oracles.max(1)
+        || i128::from(config.max_deviation) > SCALAR_7
+        || config.twap_records > MAX_TWAP_RECORDS
+        || ((config.twap_borrow || config.twap_liquidation) && config.twap_records == 0)
+    {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+
+    // prices from every oracle must share the decimals of the pool's oracle
+    if num_oracles > 0 {
+        let pool_decimals = Pool::load(e).load_price_decimals(e);
+        for oracle in config.oracles.iter() {
+            if PriceFeedClient::new(e, &oracle).decimals() != pool_decimals {
+                panic_with_error!(e, PoolError::BadRequest);
+            }
+        }
+    }
+}
+
 /// sets reserve data for the pool
 fn initialize_reserve(e: &Env, asset: &Address, config: &ReserveConfig) -> u32 {
     let index: u32;
@@ -128,6 +319,8 @@ fn initialize_reserve(e: &Env, asset: &Address, config: &ReserveConfig) -> u32 {
             || reserve_config.r_two != config.r_two
             || reserve_config.r_three != config.r_three
             || reserve_config.util != config.util
+            || reserve_config.rate_model != config.rate_model
+            || reserve_config.kink != config.kink
         {
             reserve.ir_mod = SCALAR_9;
         }
@@ -142,6 +335,7 @@ fn initialize_reserve(e: &Env, asset: &Address, config: &ReserveConfig) -> u32 {
             b_supply: 0,
             last_time: e.ledger().timestamp(),
             backstop_credit: 0,
+            flags: 0,
         };
         storage::set_res_data(e, asset, &init_data);
     }
@@ -153,11 +347,19 @@ fn initialize_reserve(e: &Env, asset: &Address, config: &ReserveConfig) -> u32 {
         l_factor: config.l_factor,
         util: config.util,
         max_util: config.max_util,
+        supply_cap: config.supply_cap,
+        borrow_cap: config.borrow_cap,
+        flash_fee: config.flash_fee,
+        isolated: config.isolated,
+        debt_ceiling: config.debt_ceiling,
+        isolated_borrow: config.isolated_borrow,
         r_base: config.r_base,
         r_one: config.r_one,
         r_two: config.r_two,
         r_three: config.r_three,
         reactivity: config.reactivity,
+        rate_model: config.rate_model,
+        kink: config.kink,
     };
     storage::set_res_config(e, asset, &reserve_config);
 
@@ -174,20 +376,65 @@ fn require_valid_reserve_metadata(e: &Env, metadata: &ReserveConfig) {
         || (metadata.max_util > SCALAR_7_U32 || metadata.max_util <= metadata.util)
         || metadata.r_base >= 1_0000000
         || metadata.r_base < 0_0001000
-        || (metadata.r_one > metadata.r_two || metadata.r_two > metadata.r_three)
-        || (metadata.reactivity > 0_0001000)
+        || !is_valid_rate_model(metadata)
+        || metadata.supply_cap < 0
+        || metadata.borrow_cap < 0
+        || metadata.flash_fee > SCALAR_7_U32
+        || metadata.debt_ceiling < 0
     {
         panic_with_error!(e, PoolError::InvalidReserveMetadata);
     }
 }
 
+/// Check that the interest rate parameters are valid for the reserve's rate model. Parameters
+/// the rate model does not use must be zero.
+#[allow(clippy::zero_prefixed_literal)]
+fn is_valid_rate_model(metadata: &ReserveConfig) -> bool {
+    match metadata.rate_model {
+        RATE_MODEL_REACTIVE => {
+            metadata.r_one <= metadata.r_two
+                && metadata.r_two <= metadata.r_three
+                && metadata.reactivity <= 0_0001000
+                && metadata.kink == 0
+        }
+        RATE_MODEL_FIXED => {
+            metadata.r_one == 0
+                && metadata.r_two == 0
+                && metadata.r_three == 0
+                && metadata.reactivity == 0
+                && metadata.kink == 0
+        }
+        RATE_MODEL_TWO_SLOPE => {
+            metadata.r_one <= metadata.r_two
+                && metadata.r_three == 0
+                && metadata.reactivity == 0
+                && metadata.kink > 0
+                && metadata.kink < SCALAR_7 as u32
+        }
+        RATE_MODEL_STATIC => {
+            metadata.r_one <= metadata.r_two
+                && metadata.r_two <= metadata.r_three
+                && metadata.reactivity == 0
+                && metadata.kink == 0
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::storage::QueuedReserveInit;
+    #[cfg(feature = "retroshades")]
+    use crate::storage::{OracleAsset, OracleSource};
     use crate::testutils;
+    use crate::{
+        constants::RESERVE_FROZEN,
+        storage::{QueuedReserveInit, ReserveSunset},
+    };
 
     use super::*;
+    use sep_40_oracle::testutils::Asset;
     use soroban_sdk::testutils::{Address as _, Ledger, LedgerInfo};
+    use soroban_sdk::{unwrap::UnwrapOptimized, vec, Symbol};
 
     #[test]
     fn test_execute_initialize() {
@@ -219,6 +466,7 @@ mod tests {
             assert_eq!(pool_config.oracle, oracle);
             assert_eq!(pool_config.bstop_rate, bstop_rate);
             assert_eq!(pool_config.status, 6);
+            assert_eq!(pool_config.max_price_age, 24 * 60 * 60);
             assert_eq!(storage::get_backstop(&e), backstop_address);
             assert_eq!(storage::get_blnd_token(&e), blnd_id);
         });
@@ -321,6 +569,427 @@ mod tests {
 
     #[test]
     fn test_execute_update_pool() {
+        let e = Env::default();
+        e.ledger().set(LedgerInfo {
+            timestamp: 123456 * 5,
+            protocol_version: 20,
+            sequence_number: 123456,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let new_pool_config = PoolConfig {
+            oracle: pool_config.oracle.clone(),
+            bstop_rate: 0_2000000,
+            status: 4,
+            max_positions: 4,
+            max_price_age: 3600,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &new_pool_config);
+            let queued_update = storage::get_queued_pool_update(&e).unwrap_optimized();
+            assert_eq!(queued_update.new_config.bstop_rate, 0_2000000);
+            assert_eq!(
+                queued_update.unlock_time,
+                e.ledger().timestamp() + SECONDS_PER_WEEK
+            );
+            // config is not updated until the update is executed
+            assert_eq!(storage::get_pool_config(&e).bstop_rate, 0_1000000);
+        });
+
+        e.ledger().with_mut(|li| li.timestamp += SECONDS_PER_WEEK);
+        e.as_contract(&pool, || {
+            let result = execute_update_pool(&e);
+            let new_pool_config = storage::get_pool_config(&e);
+            assert_eq!(new_pool_config.bstop_rate, 0_2000000);
+            assert_eq!(new_pool_config.oracle, pool_config.oracle);
+            assert_eq!(new_pool_config.status, pool_config.status);
+            assert_eq!(new_pool_config.max_positions, 4u32);
+            assert_eq!(new_pool_config.max_price_age, 3600);
+            assert_eq!(result.status, pool_config.status);
+            assert!(storage::get_queued_pool_update(&e).is_none());
+        });
+    }
+
+    #[test]
+    fn test_execute_update_pool_status_6() {
+        let e = Env::default();
+        e.mock_all_auths();
+        let pool = testutils::create_pool(&e);
+        let (oracle_0, oracle_client_0) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_client_1) = testutils::create_mock_oracle(&e);
+        for oracle_client in [oracle_client_0, oracle_client_1] {
+            oracle_client.set_data(
+                &Address::generate(&e),
+                &Asset::Stellar(Address::generate(&e)),
+                &vec![&e, Asset::Stellar(Address::generate(&e))],
+                &7,
+                &300,
+            );
+        }
+
+        let pool_config = PoolConfig {
+            oracle: oracle_0,
+            bstop_rate: 0_1000000,
+            status: 6,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let new_pool_config = PoolConfig {
+            oracle: oracle_1.clone(),
+            bstop_rate: 0_1000000,
+            status: 6,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &new_pool_config);
+            let queued_update = storage::get_queued_pool_update(&e).unwrap_optimized();
+            assert_eq!(queued_update.unlock_time, e.ledger().timestamp());
+
+            execute_update_pool(&e);
+            assert_eq!(storage::get_pool_config(&e).oracle, oracle_1);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1203)")]
+    fn test_execute_update_pool_requires_unlock() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &pool_config);
+            execute_update_pool(&e);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_update_pool_requires_queued_update() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 6,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &pool_config);
+            execute_cancel_queued_update_pool(&e);
+            execute_update_pool(&e);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_update_pool_duplicate() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &pool_config);
+            execute_queue_update_pool(&e, &pool_config);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_update_pool_validates() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let mut new_pool_config = pool_config.clone();
+        new_pool_config.bstop_rate = 1_0000000;
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &new_pool_config);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_update_pool_validates_max_price_age() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let mut new_pool_config = pool_config.clone();
+        new_pool_config.max_price_age = 0;
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &new_pool_config);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_update_pool_validates_max_positions() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let mut new_pool_config = pool_config.clone();
+        new_pool_config.max_positions = 1;
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &new_pool_config);
+        });
+    }
+
+    #[test]
+    fn test_queue_update_pool_validates_auction_config() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let valid_curve = AuctionCurve {
+            ramp: 100,
+            decay: 50,
+            step: 10,
+            start_premium: Some(0_1000000),
+        };
+        let invalid_curves = [
+            // no step
+            AuctionCurve {
+                step: 0,
+                ..valid_curve.clone()
+            },
+            // bid never decays
+            AuctionCurve {
+                decay: 0,
+                ..valid_curve.clone()
+            },
+            // ramp is not a multiple of the step
+            AuctionCurve {
+                ramp: 105,
+                ..valid_curve.clone()
+            },
+            // decay is not a multiple of the step
+            AuctionCurve {
+                decay: 55,
+                ..valid_curve.clone()
+            },
+            // start premium without a ramp
+            AuctionCurve {
+                ramp: 0,
+                ..valid_curve.clone()
+            },
+        ];
+        for curve in invalid_curves.iter() {
+            assert!(!is_valid_auction_curve(curve));
+        }
+        assert!(is_valid_auction_curve(&valid_curve));
+        assert!(is_valid_auction_curve(&AuctionCurve::default()));
+
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let mut new_pool_config = pool_config.clone();
+            new_pool_config.auction_config.liquidation = valid_curve.clone();
+            execute_queue_update_pool(&e, &new_pool_config);
+            let queued_update = storage::get_queued_pool_update(&e).unwrap_optimized();
+            assert_eq!(queued_update.new_config.auction_config.liquidation.step, 10);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_update_pool_validates_auction_curve() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let mut new_pool_config = pool_config.clone();
+        new_pool_config.auction_config.bad_debt.step = 0;
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_update_pool(&e, &new_pool_config);
+        });
+    }
+
+    #[test]
+    fn test_queue_update_pool_validates_status_config() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let valid_config = StatusConfig {
+            on_ice_q4w: 0_2000000,
+            frozen_q4w: 0_4000000,
+            admin_on_ice_q4w: 0_3000000,
+            admin_frozen_q4w: 1_0000000,
+            backstop_pc: 100_000,
+        };
+        let invalid_configs = [
+            // no on-ice limit
+            StatusConfig {
+                on_ice_q4w: 0,
+                ..valid_config.clone()
+            },
+            // admin frozen limit over 100%
+            StatusConfig {
+                admin_frozen_q4w: 1_0000001,
+                ..valid_config.clone()
+            },
+            // on-ice limit is not under the frozen limit
+            StatusConfig {
+                on_ice_q4w: 0_4000000,
+                admin_on_ice_q4w: 0_4000000,
+                ..valid_config.clone()
+            },
+            // admin on-ice limit is not under the admin frozen limit
+            StatusConfig {
+                admin_on_ice_q4w: 1_0000000,
+                ..valid_config.clone()
+            },
+            // on-ice limit is over the admin on-ice limit
+            StatusConfig {
+                on_ice_q4w: 0_3500000,
+                ..valid_config.clone()
+            },
+            // frozen limit is over the admin frozen limit
+            StatusConfig {
+                admin_frozen_q4w: 0_3500000,
+                ..valid_config.clone()
+            },
+            // no minimum backstop deposit
+            StatusConfig {
+                backstop_pc: 0,
+                ..valid_config.clone()
+            },
+            // minimum backstop deposit too large
+            StatusConfig {
+                backstop_pc: MAX_BACKSTOP_PC + 1,
+                ..valid_config.clone()
+            },
+        ];
+        for config in invalid_configs.iter() {
+            assert!(!is_valid_status_config(config));
+        }
+        assert!(is_valid_status_config(&valid_config));
+        assert!(is_valid_status_config(&StatusConfig::default()));
+
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let mut new_pool_config = pool_config.clone();
+            new_pool_config.status_config = valid_config.clone();
+            execute_queue_update_pool(&e, &new_pool_config);
+            let queued_update = storage::get_queued_pool_update(&e).unwrap_optimized();
+            assert_eq!(queued_update.new_config.status_config.on_ice_q4w, 0_2000000);
+            assert_eq!(queued_update.new_config.status_config.backstop_pc, 100_000);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_update_pool_validates_status_thresholds() {
         let e = Env::default();
         let pool = testutils::create_pool(&e);
 
@@ -329,36 +998,57 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
+        let mut new_pool_config = pool_config.clone();
+        new_pool_config.status_config.frozen_q4w = 0_2000000;
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
 
-            // happy path
-            execute_update_pool(&e, 0_2000000, 4u32);
-            let new_pool_config = storage::get_pool_config(&e);
-            assert_eq!(new_pool_config.bstop_rate, 0_2000000);
-            assert_eq!(new_pool_config.oracle, pool_config.oracle);
-            assert_eq!(new_pool_config.status, pool_config.status);
-            assert_eq!(new_pool_config.max_positions, 4u32)
+            execute_queue_update_pool(&e, &new_pool_config);
         });
     }
 
     #[test]
     #[should_panic(expected = "Error(Contract, #1200)")]
-    fn test_execute_update_pool_validates() {
+    fn test_queue_update_pool_validates_oracle_decimals() {
         let e = Env::default();
+        e.mock_all_auths();
         let pool = testutils::create_pool(&e);
+        let (oracle_0, oracle_client_0) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_client_1) = testutils::create_mock_oracle(&e);
+        oracle_client_0.set_data(
+            &Address::generate(&e),
+            &Asset::Stellar(Address::generate(&e)),
+            &vec![&e, Asset::Stellar(Address::generate(&e))],
+            &7,
+            &300,
+        );
+        oracle_client_1.set_data(
+            &Address::generate(&e),
+            &Asset::Stellar(Address::generate(&e)),
+            &vec![&e, Asset::Stellar(Address::generate(&e))],
+            &14,
+            &300,
+        );
 
         let pool_config = PoolConfig {
-            oracle: Address::generate(&e),
+            oracle: oracle_0,
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
+        let mut new_pool_config = pool_config.clone();
+        new_pool_config.oracle = oracle_1;
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
 
-            execute_update_pool(&e, 1_0000000, 4u32);
+            execute_queue_update_pool(&e, &new_pool_config);
         });
     }
 
@@ -382,12 +1072,23 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
             bstop_rate: 0_1000000,
             status: 6,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -429,12 +1130,23 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 1_000_000_0000000,
+            borrow_cap: 500_000_0000000,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -445,6 +1157,8 @@ mod tests {
             assert_eq!(queued_init.new_config.l_factor, metadata.l_factor);
             assert_eq!(queued_init.new_config.util, metadata.util);
             assert_eq!(queued_init.new_config.max_util, metadata.max_util);
+            assert_eq!(queued_init.new_config.supply_cap, metadata.supply_cap);
+            assert_eq!(queued_init.new_config.borrow_cap, metadata.borrow_cap);
             assert_eq!(queued_init.new_config.r_base, metadata.r_base);
             assert_eq!(queued_init.new_config.r_one, metadata.r_one);
             assert_eq!(queued_init.new_config.r_two, metadata.r_two);
@@ -479,12 +1193,23 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
             bstop_rate: 0_1000000,
             status: 6,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -518,12 +1243,23 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         let pool_config = PoolConfig {
             oracle: Address::generate(&e),
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -551,6 +1287,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         e.as_contract(&pool, || {
             storage::set_queued_reserve_set(
@@ -588,6 +1332,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 1_000_000_0000000,
+            borrow_cap: 500_000_0000000,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         e.as_contract(&pool, || {
             storage::set_queued_reserve_set(
@@ -605,6 +1357,8 @@ mod tests {
             assert_eq!(res_config_0.l_factor, metadata.l_factor);
             assert_eq!(res_config_0.util, metadata.util);
             assert_eq!(res_config_0.max_util, metadata.max_util);
+            assert_eq!(res_config_0.supply_cap, metadata.supply_cap);
+            assert_eq!(res_config_0.borrow_cap, metadata.borrow_cap);
             assert_eq!(res_config_0.r_one, metadata.r_one);
             assert_eq!(res_config_0.r_two, metadata.r_two);
             assert_eq!(res_config_0.r_three, metadata.r_three);
@@ -634,6 +1388,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         e.as_contract(&pool, || {
             storage::set_queued_reserve_set(
@@ -694,6 +1456,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -771,6 +1536,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -840,6 +1608,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 105,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
 
         let pool_config = PoolConfig {
@@ -847,6 +1623,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -863,6 +1642,300 @@ mod tests {
         });
     }
 
+    #[cfg(feature = "retroshades")]
+    #[test]
+    fn test_execute_set_reserve_oracle() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let oracle_config = ReserveOracleConfig {
+            source: OracleSource::Offchain,
+            asset: OracleAsset::Other(Symbol::new(&e, "BTC")),
+        };
+        e.as_contract(&pool, || {
+            assert!(storage::get_res_oracle(&e, &underlying).is_none());
+
+            execute_set_reserve_oracle(&e, &underlying, &oracle_config);
+
+            let stored = storage::get_res_oracle(&e, &underlying).unwrap_optimized();
+            assert!(matches!(stored.source, OracleSource::Offchain));
+            match stored.asset {
+                OracleAsset::Other(symbol) => assert_eq!(symbol, Symbol::new(&e, "BTC")),
+                _ => assert!(false),
+            }
+        });
+    }
+
+    #[cfg(feature = "retroshades")]
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_reserve_oracle_requires_reserve() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+        let underlying = Address::generate(&e);
+
+        let oracle_config = ReserveOracleConfig {
+            source: OracleSource::Custom(Address::generate(&e)),
+            asset: OracleAsset::Stellar(underlying.clone()),
+        };
+        e.as_contract(&pool, || {
+            execute_set_reserve_oracle(&e, &underlying, &oracle_config);
+        });
+    }
+
+    #[test]
+    fn test_execute_set_reserve_price_config() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let (oracle_0, oracle_client_0) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_client_1) = testutils::create_mock_oracle(&e);
+        for oracle_client in [&oracle_client_0, &oracle_client_1] {
+            oracle_client.set_data(
+                &bombadil,
+                &Asset::Other(Symbol::new(&e, "USD")),
+                &vec![&e, Asset::Stellar(underlying.clone())],
+                &7,
+                &300,
+            );
+        }
+        let pool_config = PoolConfig {
+            oracle: oracle_0.clone(),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let price_config = ReservePriceConfig {
+            max_age: 60 * 60,
+            oracles: vec![&e, oracle_0.clone(), oracle_1.clone()],
+            min_quorum: 2,
+            max_deviation: 0_0100000,
+            twap_records: 4,
+            twap_borrow: true,
+            twap_liquidation: true,
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_set_reserve_price_config(&e, &underlying, &price_config);
+
+            let stored = storage::get_res_price_config(&e, &underlying).unwrap_optimized();
+            assert_eq!(stored.max_age, 60 * 60);
+            assert_eq!(stored.oracles, vec![&e, oracle_0, oracle_1]);
+            assert_eq!(stored.min_quorum, 2);
+            assert_eq!(stored.max_deviation, 0_0100000);
+            assert_eq!(stored.twap_records, 4);
+            assert!(stored.twap_borrow);
+            assert!(stored.twap_liquidation);
+        });
+    }
+
+    #[test]
+    fn test_execute_set_reserve_flags() {
+        let e = Env::default();
+        e.mock_all_auths();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_set_reserve_flags(&e, &underlying, RESERVE_FROZEN);
+
+            let res_data = storage::get_res_data(&e, &underlying);
+            assert_eq!(res_data.flags, RESERVE_FROZEN);
+            assert_eq!(res_data.b_supply, reserve_data.b_supply);
+            assert_eq!(res_data.d_supply, reserve_data.d_supply);
+        });
+    }
+
+    #[test]
+    fn test_execute_set_reserve_flags_keeps_sunset() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.flags = RESERVE_SUNSET;
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_sunset(
+                &e,
+                &underlying,
+                &ReserveSunset {
+                    unlock_time: 0,
+                    duration: 1000,
+                    c_factor: reserve_config.c_factor,
+                    start_time: 1,
+                },
+            );
+
+            execute_set_reserve_flags(&e, &underlying, RESERVE_FROZEN);
+
+            let res_data = storage::get_res_data(&e, &underlying);
+            assert_eq!(res_data.flags, RESERVE_FROZEN | RESERVE_SUNSET);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_reserve_flags_validates_flags() {
+        let e = Env::default();
+        e.mock_all_auths();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        e.as_contract(&pool, || {
+            execute_set_reserve_flags(&e, &underlying, 1 << 4);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_reserve_price_config_validates_quorum() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let price_config = ReservePriceConfig {
+            max_age: 0,
+            oracles: vec![&e],
+            min_quorum: 2,
+            max_deviation: 0,
+            twap_records: 0,
+            twap_borrow: false,
+            twap_liquidation: false,
+        };
+        e.as_contract(&pool, || {
+            execute_set_reserve_price_config(&e, &underlying, &price_config);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_reserve_price_config_validates_twap_flags() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let price_config = ReservePriceConfig {
+            max_age: 0,
+            oracles: vec![&e],
+            min_quorum: 1,
+            max_deviation: 0,
+            twap_records: 0,
+            twap_borrow: false,
+            twap_liquidation: true,
+        };
+        e.as_contract(&pool, || {
+            execute_set_reserve_price_config(&e, &underlying, &price_config);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_reserve_price_config_validates_decimals() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        let pool = testutils::create_pool(&e);
+        let bombadil = Address::generate(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let (oracle_0, oracle_client_0) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_client_1) = testutils::create_mock_oracle(&e);
+        oracle_client_0.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, Asset::Stellar(underlying.clone())],
+            &7,
+            &300,
+        );
+        oracle_client_1.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, Asset::Stellar(underlying.clone())],
+            &14,
+            &300,
+        );
+        let pool_config = PoolConfig {
+            oracle: oracle_0.clone(),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let price_config = ReservePriceConfig {
+            max_age: 0,
+            oracles: vec![&e, oracle_0, oracle_1],
+            min_quorum: 1,
+            max_deviation: 0,
+            twap_records: 0,
+            twap_borrow: false,
+            twap_liquidation: false,
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_set_reserve_price_config(&e, &underlying, &price_config);
+        });
+    }
+
     #[test]
     fn test_initialize_reserve_sets_index() {
         let e = Env::default();
@@ -884,6 +1957,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         e.as_contract(&pool, || {
             initialize_reserve(&e, &asset_id_0, &metadata);
@@ -922,6 +2003,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
         // no panic
@@ -945,6 +2034,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -966,6 +2063,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -987,6 +2092,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -1008,6 +2121,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -1029,6 +2150,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -1050,6 +2179,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -1071,6 +2208,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -1092,6 +2237,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -1113,6 +2266,178 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0001001,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+        };
+        require_valid_reserve_metadata(&e, &metadata);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1202)")]
+    fn test_validate_reserve_metadata_validates_caps() {
+        let e = Env::default();
+
+        let metadata = ReserveConfig {
+            index: 0,
+            decimals: 18,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_5000000,
+            max_util: 0_9500000,
+            r_base: 0_0100000,
+            r_one: 0_0500000,
+            r_two: 0_5000000,
+            r_three: 1_5000000,
+            reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: -1,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+        };
+        require_valid_reserve_metadata(&e, &metadata);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1202)")]
+    fn test_validate_reserve_metadata_validates_flash_fee() {
+        let e = Env::default();
+
+        let metadata = ReserveConfig {
+            index: 0,
+            decimals: 18,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_5000000,
+            max_util: 0_9500000,
+            r_base: 0_0100000,
+            r_one: 0_0500000,
+            r_two: 0_5000000,
+            r_three: 1_5000000,
+            reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 1_0000001,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+        };
+        require_valid_reserve_metadata(&e, &metadata);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1202)")]
+    fn test_validate_reserve_metadata_validates_debt_ceiling() {
+        let e = Env::default();
+
+        let metadata = ReserveConfig {
+            index: 0,
+            decimals: 18,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_5000000,
+            max_util: 0_9500000,
+            r_base: 0_0100000,
+            r_one: 0_0500000,
+            r_two: 0_5000000,
+            r_three: 1_5000000,
+            reactivity: 100,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: true,
+            debt_ceiling: -1,
+            isolated_borrow: false,
+        };
+        require_valid_reserve_metadata(&e, &metadata);
+    }
+
+    #[test]
+    fn test_validate_reserve_metadata_validates_rate_model_params() {
+        let mut metadata = ReserveConfig {
+            index: 0,
+            decimals: 18,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_5000000,
+            max_util: 0_9500000,
+            r_base: 0_0400000,
+            r_one: 0,
+            r_two: 0,
+            r_three: 0,
+            reactivity: 0,
+            rate_model: RATE_MODEL_FIXED,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+        };
+        assert!(is_valid_rate_model(&metadata));
+        metadata.r_one = 0_0500000;
+        assert!(!is_valid_rate_model(&metadata));
+
+        metadata.rate_model = RATE_MODEL_TWO_SLOPE;
+        metadata.r_two = 0_6000000;
+        metadata.kink = 0_9000000;
+        assert!(is_valid_rate_model(&metadata));
+        metadata.kink = 1_0000000;
+        assert!(!is_valid_rate_model(&metadata));
+        metadata.kink = 0;
+        assert!(!is_valid_rate_model(&metadata));
+
+        metadata.rate_model = RATE_MODEL_STATIC;
+        metadata.r_three = 1_0000000;
+        assert!(is_valid_rate_model(&metadata));
+        metadata.reactivity = 100;
+        assert!(!is_valid_rate_model(&metadata));
+
+        metadata.rate_model = RATE_MODEL_REACTIVE;
+        assert!(is_valid_rate_model(&metadata));
+        metadata.kink = 0_9000000;
+        assert!(!is_valid_rate_model(&metadata));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1202)")]
+    fn test_validate_reserve_metadata_validates_rate_model() {
+        let e = Env::default();
+
+        let metadata = ReserveConfig {
+            index: 0,
+            decimals: 18,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_5000000,
+            max_util: 0_9500000,
+            r_base: 0_0100000,
+            r_one: 0_0500000,
+            r_two: 0_5000000,
+            r_three: 1_5000000,
+            reactivity: 0,
+            rate_model: 4,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
         };
         require_valid_reserve_metadata(&e, &metadata);
     }
@@ -0,0 +1,306 @@
+use soroban_sdk::{panic_with_error, unwrap::UnwrapOptimized, Address, Env, Symbol, Vec};
+
+use crate::{
+    errors::PoolError,
+    storage::{self, BorrowAllowance},
+    validator::require_nonnegative,
+};
+
+use super::{Request, RequestType};
+
+/// Require that an expiration ledger is in the future and within the maximum TTL of an entry
+fn require_valid_expiration(e: &Env, expiration_ledger: u32) {
+    let sequence = e.ledger().sequence();
+    if expiration_ledger < sequence || expiration_ledger - sequence > e.storage().max_ttl() {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+}
+
+/// Check if an operator is approved to manage an owner's positions
+///
+/// ### Arguments
+/// * `owner` - The address whose positions are managed
+/// * `operator` - The address managing the positions
+pub fn is_operator(e: &Env, owner: &Address, operator: &Address) -> bool {
+    match storage::get_operator(e, owner, operator) {
+        Some(expiration_ledger) => expiration_ledger >= e.ledger().sequence(),
+        None => false,
+    }
+}
+
+/// Fetch the amount of a reserve a delegatee can currently borrow against an owner's collateral
+///
+/// ### Arguments
+/// * `owner` - The address whose collateral backs the borrow
+/// * `delegatee` - The address approved to borrow
+/// * `asset` - The underlying asset of the reserve
+pub fn load_borrow_allowance(
+    e: &Env,
+    owner: &Address,
+    delegatee: &Address,
+    asset: &Address,
+) -> i128 {
+    match storage::get_borrow_allowance(e, owner, delegatee, asset) {
+        Some(allowance) if allowance.expiration_ledger >= e.ledger().sequence() => allowance.amount,
+        _ => 0,
+    }
+}
+
+/// Execute approving an operator to manage an owner's positions until the expiration ledger
+///
+/// Panics if the owner is the operator or the expiration ledger is invalid
+pub fn execute_approve_operator(
+    e: &Env,
+    owner: &Address,
+    operator: &Address,
+    expiration_ledger: u32,
+) {
+    if owner == operator || operator == &e.current_contract_address() {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    require_valid_expiration(e, expiration_ledger);
+    storage::set_operator(e, owner, operator, expiration_ledger);
+}
+
+/// Execute revoking an operator's approval to manage an owner's positions
+pub fn execute_revoke_operator(e: &Env, owner: &Address, operator: &Address) {
+    storage::del_operator(e, owner, operator);
+}
+
+/// Execute approving a delegatee to borrow an amount of a reserve against an owner's collateral.
+/// An amount of 0 revokes the allowance.
+///
+/// Panics if the reserve does not exist, the amount is negative, or the expiration ledger is invalid
+pub fn execute_approve_borrow(
+    e: &Env,
+    owner: &Address,
+    delegatee: &Address,
+    asset: &Address,
+    amount: i128,
+    expiration_ledger: u32,
+) {
+    require_nonnegative(e, &amount);
+    if owner == delegatee || !storage::has_res(e, asset) {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    if amount == 0 {
+        storage::del_borrow_allowance(e, owner, delegatee, asset);
+        return;
+    }
+    require_valid_expiration(e, expiration_ledger);
+    storage::set_borrow_allowance(
+        e,
+        owner,
+        delegatee,
+        asset,
+        &BorrowAllowance {
+            amount,
+            expiration_ledger,
+        },
+    );
+}
+
+/// Require that a spender is authorized to submit requests that modify `from`'s positions.
+///
+/// An approved operator can submit any requests for `from`. A spender with borrow allowances can
+/// submit only `Borrow` requests for `from`, and spends its allowance for each. Otherwise, `from`
+/// must authorize the submission.
+///
+/// ### Arguments
+/// * `from` - The address whose positions are being modified
+/// * `spender` - The address submitting the requests, that has already been authorized
+/// * `requests` - The requests being submitted
+///
+/// ### Panics
+/// If a borrow exceeds the spender's allowance, or `from` has not authorized the submission
+pub fn require_delegated_auth(e: &Env, from: &Address, spender: &Address, requests: &Vec<Request>) {
+    if is_operator(e, from, spender) {
+        return;
+    }
+
+    let is_delegated_borrow = !requests.is_empty()
+        && requests.iter().all(|request| {
+            request.request_type == RequestType::Borrow as u32
+                && storage::get_borrow_allowance(e, from, spender, &request.address).is_some()
+        });
+    if !is_delegated_borrow {
+        from.require_auth();
+        return;
+    }
+
+    for request in requests.iter() {
+        let mut allowance =
+            storage::get_borrow_allowance(e, from, spender, &request.address).unwrap_optimized();
+        if allowance.expiration_ledger < e.ledger().sequence() || allowance.amount < request.amount
+        {
+            panic_with_error!(e, PoolError::BorrowAllowanceExceeded);
+        }
+        allowance.amount -= request.amount;
+        storage::set_borrow_allowance(e, from, spender, &request.address, &allowance);
+
+        e.events().publish(
+            (
+                Symbol::new(e, "delegated_borrow"),
+                request.address.clone(),
+                from.clone(),
+            ),
+            (spender.clone(), request.amount, allowance.amount),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils;
+    use soroban_sdk::{
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec,
+    };
+
+    fn setup_delegation(e: &Env) -> (Address, Address) {
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let bombadil = Address::generate(e);
+        let pool = testutils::create_pool(e);
+
+        let (underlying, _) = testutils::create_token_contract(e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(e, &pool, &underlying, &reserve_config, &reserve_data);
+        (pool, underlying)
+    }
+
+    fn borrow(asset: &Address, amount: i128) -> Request {
+        Request {
+            request_type: RequestType::Borrow as u32,
+            address: asset.clone(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_approve_operator() {
+        let e = Env::default();
+        let (pool, _) = setup_delegation(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        e.as_contract(&pool, || {
+            assert!(!is_operator(&e, &samwise, &frodo));
+
+            execute_approve_operator(&e, &samwise, &frodo, 1300);
+            assert!(is_operator(&e, &samwise, &frodo));
+            assert!(!is_operator(&e, &frodo, &samwise));
+
+            // no `from` auth is required for an operator
+            require_delegated_auth(&e, &samwise, &frodo, &vec![&e]);
+
+            execute_revoke_operator(&e, &samwise, &frodo);
+            assert!(!is_operator(&e, &samwise, &frodo));
+        });
+
+        e.ledger().with_mut(|li| li.sequence_number = 1301);
+        e.as_contract(&pool, || {
+            execute_approve_operator(&e, &samwise, &frodo, 1301);
+            assert!(is_operator(&e, &samwise, &frodo));
+        });
+        e.ledger().with_mut(|li| li.sequence_number = 1302);
+        e.as_contract(&pool, || {
+            assert!(!is_operator(&e, &samwise, &frodo));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_approve_operator_expired() {
+        let e = Env::default();
+        let (pool, _) = setup_delegation(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        e.as_contract(&pool, || {
+            execute_approve_operator(&e, &samwise, &frodo, 1233);
+        });
+    }
+
+    #[test]
+    fn test_approve_borrow() {
+        let e = Env::default();
+        let (pool, underlying) = setup_delegation(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        e.as_contract(&pool, || {
+            execute_approve_borrow(&e, &samwise, &frodo, &underlying, 10_0000000, 1300);
+            assert_eq!(
+                load_borrow_allowance(&e, &samwise, &frodo, &underlying),
+                10_0000000
+            );
+
+            // no `from` auth is required to spend an allowance
+            let requests = vec![
+                &e,
+                borrow(&underlying, 4_0000000),
+                borrow(&underlying, 1_0000000),
+            ];
+            require_delegated_auth(&e, &samwise, &frodo, &requests);
+            assert_eq!(
+                load_borrow_allowance(&e, &samwise, &frodo, &underlying),
+                5_0000000
+            );
+
+            execute_approve_borrow(&e, &samwise, &frodo, &underlying, 0, 0);
+            assert_eq!(load_borrow_allowance(&e, &samwise, &frodo, &underlying), 0);
+            assert!(storage::get_borrow_allowance(&e, &samwise, &frodo, &underlying).is_none());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1230)")]
+    fn test_require_delegated_auth_exceeds_allowance() {
+        let e = Env::default();
+        let (pool, underlying) = setup_delegation(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        e.as_contract(&pool, || {
+            execute_approve_borrow(&e, &samwise, &frodo, &underlying, 10_0000000, 1300);
+
+            let requests = vec![&e, borrow(&underlying, 10_0000001)];
+            require_delegated_auth(&e, &samwise, &frodo, &requests);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Auth, InvalidAction)")]
+    fn test_require_delegated_auth_requires_from_auth() {
+        let e = Env::default();
+        let (pool, underlying) = setup_delegation(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+
+        e.as_contract(&pool, || {
+            execute_approve_borrow(&e, &samwise, &frodo, &underlying, 10_0000000, 1300);
+
+            // an allowance does not cover other requests
+            let requests = vec![
+                &e,
+                borrow(&underlying, 1_0000000),
+                Request {
+                    request_type: RequestType::WithdrawCollateral as u32,
+                    address: underlying.clone(),
+                    amount: 1_0000000,
+                },
+            ];
+            require_delegated_auth(&e, &samwise, &frodo, &requests);
+        });
+    }
+}
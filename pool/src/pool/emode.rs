@@ -0,0 +1,330 @@
+use soroban_sdk::{panic_with_error, Address, Env};
+
+use crate::{
+    catchup::{self, Relaxed},
+    constants::SCALAR_7,
+    errors::PoolError,
+    storage::{self, EModeCategory},
+};
+
+use super::{
+    pool::{Pool, PriceMode},
+    PositionData, Positions, User,
+};
+
+impl EModeCategory {
+    /// Check if the category contains a reserve
+    ///
+    /// ### Arguments
+    /// * `asset` - The underlying asset of the reserve
+    pub fn contains(&self, asset: &Address) -> bool {
+        self.assets.contains(asset)
+    }
+
+    /// Check if all of the collateral and liabilities of a set of positions are
+    /// in the category
+    ///
+    /// ### Arguments
+    /// * `positions` - The positions to check
+    pub fn contains_positions(&self, e: &Env, positions: &Positions) -> bool {
+        let reserve_list = storage::get_res_list(e);
+        positions
+            .collateral
+            .keys()
+            .iter()
+            .chain(positions.liabilities.keys().iter())
+            .all(|index| self.contains(&reserve_list.get_unchecked(index)))
+    }
+}
+
+/// Load the efficiency mode category a user has opted into, if any
+///
+/// ### Arguments
+/// * `user` - The address of the user
+pub fn load_user_emode(e: &Env, user: &Address) -> Option<EModeCategory> {
+    match storage::get_user_emode(e, user) {
+        0 => None,
+        id => storage::get_emode_category(e, id),
+    }
+}
+
+/// Check that a reserve is in the user's efficiency mode category, if they have opted into one
+///
+/// ### Arguments
+/// * `emode` - The category the user has opted into
+/// * `asset` - The underlying asset of the reserve
+pub fn check_in_emode(emode: &Option<EModeCategory>, asset: &Address) -> Result<(), PoolError> {
+    if let Some(category) = emode {
+        if !category.contains(asset) {
+            return Err(PoolError::EModeViolation);
+        }
+    }
+    Ok(())
+}
+
+/// Execute setting an efficiency mode category. Takes effect immediately for all
+/// users that have opted into the category.
+///
+/// Panics if the id is 0, the factors are invalid or lower than a reserve's own factors,
+/// or an asset is not a reserve
+pub fn execute_set_emode_category(e: &Env, id: u32, category: &EModeCategory) {
+    if id == 0
+        || category.assets.is_empty()
+        || i128::from(category.c_factor) > SCALAR_7
+        || i128::from(category.l_factor) > SCALAR_7
+    {
+        panic_with_error!(e, PoolError::InvalidReserveMetadata);
+    }
+    for (i, asset) in category.assets.iter().enumerate() {
+        if !storage::has_res(e, &asset) || category.assets.first_index_of(&asset) != Some(i as u32)
+        {
+            panic_with_error!(e, PoolError::BadRequest);
+        }
+        let reserve_config = storage::get_res_config(e, &asset);
+        if category.c_factor < reserve_config.c_factor
+            || category.l_factor < reserve_config.l_factor
+        {
+            panic_with_error!(e, PoolError::InvalidReserveMetadata);
+        }
+    }
+    storage::set_emode_category(e, id, category);
+}
+
+/// Execute a user opting into an efficiency mode category, or out of one with an id of 0
+///
+/// Panics if the category does not exist, the user holds collateral or liabilities outside
+/// of the category, or the user's health factor would be too low
+pub fn execute_set_user_emode(e: &Env, user: &Address, id: u32) {
+    let emode = match id {
+        0 => None,
+        _ => match storage::get_emode_category(e, id) {
+            Some(category) => Some(category),
+            None => panic_with_error!(e, PoolError::BadRequest),
+        },
+    };
+    let user_state = User::load(e, user);
+    if let Some(category) = &emode {
+        if !category.contains_positions(e, &user_state.positions) {
+            panic_with_error!(e, PoolError::EModeViolation);
+        }
+    }
+
+    // opting out lowers the user's factors, so their health must be rechecked. In catch-up mode
+    // the check is relaxed before the prices are loaded, as they are not in a replayed footprint.
+    let mut pool = Pool::load(e);
+    if user_state.has_liabilities()
+        && !catchup::relax(e, Relaxed::HealthCheck)
+        && PositionData::calculate_from_positions(
+            e,
+            &mut pool,
+            &user_state.positions,
+            &emode,
+            PriceMode::Borrow,
+        )
+        .is_hf_under(1_0000100)
+    {
+        panic_with_error!(e, PoolError::InvalidHf);
+    }
+
+    storage::set_user_emode(e, user, id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{storage::PoolConfig, testutils};
+    use sep_40_oracle::testutils::Asset;
+    use soroban_sdk::{
+        map,
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec, Symbol, Vec,
+    };
+
+    /// Create two reserves with a c_factor and l_factor of 0.75 priced at 1
+    fn setup_emode(e: &Env) -> (Address, Vec<Address>) {
+        e.budget().reset_unlimited();
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 0,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(e);
+        let pool = testutils::create_pool(e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(e);
+
+        let (underlying_0, _) = testutils::create_token_contract(e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(e, &bombadil);
+        let (mut reserve_config, reserve_data) = testutils::default_reserve_meta();
+        reserve_config.index = 1;
+        testutils::create_reserve(e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(e, "USD")),
+            &vec![
+                e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![e, 1_0000000, 1_0000000]);
+
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(e, &pool_config);
+        });
+        (pool, vec![e, underlying_0, underlying_1])
+    }
+
+    #[test]
+    fn test_execute_set_emode_category() {
+        let e = Env::default();
+        let (pool, assets) = setup_emode(&e);
+
+        let category = EModeCategory {
+            c_factor: 0_9500000,
+            l_factor: 0_9700000,
+            assets: assets.clone(),
+        };
+        e.as_contract(&pool, || {
+            execute_set_emode_category(&e, 1, &category);
+
+            let stored = storage::get_emode_category(&e, 1).unwrap();
+            assert_eq!(stored.c_factor, 0_9500000);
+            assert_eq!(stored.l_factor, 0_9700000);
+            assert_eq!(stored.assets, assets);
+            assert!(storage::get_emode_category(&e, 2).is_none());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1202)")]
+    fn test_execute_set_emode_category_below_reserve_factors() {
+        let e = Env::default();
+        let (pool, assets) = setup_emode(&e);
+
+        let category = EModeCategory {
+            c_factor: 0_7000000,
+            l_factor: 0_9700000,
+            assets,
+        };
+        e.as_contract(&pool, || {
+            execute_set_emode_category(&e, 1, &category);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_execute_set_emode_category_duplicate_asset() {
+        let e = Env::default();
+        let (pool, assets) = setup_emode(&e);
+
+        let category = EModeCategory {
+            c_factor: 0_9500000,
+            l_factor: 0_9500000,
+            assets: vec![&e, assets.get_unchecked(0), assets.get_unchecked(0)],
+        };
+        e.as_contract(&pool, || {
+            execute_set_emode_category(&e, 1, &category);
+        });
+    }
+
+    #[test]
+    fn test_execute_set_user_emode() {
+        let e = Env::default();
+        let (pool, assets) = setup_emode(&e);
+        let samwise = Address::generate(&e);
+
+        let category = EModeCategory {
+            c_factor: 0_9500000,
+            l_factor: 0_9500000,
+            assets,
+        };
+        let positions = Positions {
+            liabilities: map![&e, (1, 60_0000000)],
+            collateral: map![&e, (0, 70_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_emode_category(&e, 1, &category);
+            storage::set_user_positions(&e, &samwise, &positions);
+
+            // only healthy with the category's factors
+            execute_set_user_emode(&e, &samwise, 1);
+            assert_eq!(storage::get_user_emode(&e, &samwise), 1);
+            assert_eq!(load_user_emode(&e, &samwise).unwrap().c_factor, 0_9500000);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1227)")]
+    fn test_execute_set_user_emode_position_outside_category() {
+        let e = Env::default();
+        let (pool, assets) = setup_emode(&e);
+        let samwise = Address::generate(&e);
+
+        let category = EModeCategory {
+            c_factor: 0_9500000,
+            l_factor: 0_9500000,
+            assets: vec![&e, assets.get_unchecked(0)],
+        };
+        let positions = Positions {
+            liabilities: map![&e, (1, 10_0000000)],
+            collateral: map![&e, (0, 70_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_emode_category(&e, 1, &category);
+            storage::set_user_positions(&e, &samwise, &positions);
+
+            execute_set_user_emode(&e, &samwise, 1);
+        });
+    }
+
+    #[test]
+    #[cfg(not(feature = "catchup"))]
+    #[should_panic(expected = "Error(Contract, #1205)")]
+    fn test_execute_set_user_emode_opt_out_unhealthy() {
+        let e = Env::default();
+        let (pool, assets) = setup_emode(&e);
+        let samwise = Address::generate(&e);
+
+        let category = EModeCategory {
+            c_factor: 0_9500000,
+            l_factor: 0_9500000,
+            assets,
+        };
+        let positions = Positions {
+            liabilities: map![&e, (1, 60_0000000)],
+            collateral: map![&e, (0, 70_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_emode_category(&e, 1, &category);
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_user_emode(&e, &samwise, 1);
+
+            execute_set_user_emode(&e, &samwise, 0);
+        });
+    }
+}
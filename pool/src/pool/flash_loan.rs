@@ -0,0 +1,295 @@
+use sep_41_token::TokenClient;
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{
+    contracttype, map, panic_with_error, unwrap::UnwrapOptimized, Address, Env, Vec,
+};
+
+use crate::{
+    catchup::{self, Relaxed},
+    constants::SCALAR_7,
+    dependencies::FlashLoanReceiverClient,
+    errors::PoolError,
+};
+
+use super::{
+    actions::{Request, RequestType},
+    pool::Pool,
+    submit::execute_submit_with_credit,
+    Positions,
+};
+
+/// A flash loan of a reserve's available liquidity
+#[derive(Clone)]
+#[contracttype]
+pub struct FlashLoan {
+    pub contract: Address, // the contract receiving the loan that implements `exec_op`
+    pub asset: Address,    // the underlying asset to loan
+    pub amount: i128,      // the amount of underlying tokens to loan
+}
+
+/// Execute a flash loan for a user against the pool.
+///
+/// The requests are processed for `from` as they would be in a submit, with the loaned tokens
+/// paying for the tokens the requests send to the pool before `from` does. The rest of the loan
+/// and any tokens the requests withdraw are sent to the receiver contract, and then the receiver
+/// contract's `exec_op` is invoked. By the time `exec_op` returns, the pool must have been repaid
+/// the loaned amount plus the reserve's flash loan fee.
+///
+/// This lets a liquidator with no inventory fill a liquidation by repaying the bid with the
+/// loan and withdrawing the lot to the receiver, which can swap it to repay the loan.
+///
+/// Returns the fee paid and the new positions for `from`
+///
+/// ### Arguments
+/// * from - The address of the user whose positions are being modified
+/// * flash_loan - The flash loan to execute
+/// * requests - A vec of requests to be processed between the loan and its repayment
+///
+/// ### Panics
+/// If the loan is larger than the available liquidity, the requests are unable to be fully
+/// executed, or the loan and fee are not repaid
+pub fn execute_flash_loan(
+    e: &Env,
+    from: &Address,
+    flash_loan: &FlashLoan,
+    requests: Vec<Request>,
+) -> (i128, Positions) {
+    let pool_address = e.current_contract_address();
+    if from == &pool_address || flash_loan.contract == pool_address || flash_loan.amount <= 0 {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+
+    let mut pool = Pool::load(e);
+    pool.require_action_allowed(e, RequestType::Borrow as u32);
+    let reserve = pool.load_reserve(e, &flash_loan.asset, false);
+    reserve.require_action_allowed(e, RequestType::Borrow as u32);
+    if flash_loan.amount > reserve.total_supply() - reserve.total_liabilities() {
+        panic_with_error!(e, PoolError::FlashLoanTooLarge);
+    }
+    let fee = flash_loan
+        .amount
+        .fixed_mul_ceil(i128::from(reserve.flash_fee), SCALAR_7)
+        .unwrap_optimized();
+
+    // the loan pays for the requests first, and the rest is sent to the receiver
+    let mut credit = map![e, (flash_loan.asset.clone(), flash_loan.amount)];
+    let positions =
+        execute_submit_with_credit(e, from, from, &flash_loan.contract, requests, &mut credit);
+    let unused_loan = credit.get(flash_loan.asset.clone()).unwrap_or(0);
+    if unused_loan > 0 {
+        catchup::transfer(
+            e,
+            &flash_loan.asset,
+            &pool_address,
+            &flash_loan.contract,
+            unused_loan,
+        );
+    }
+
+    let token_client = TokenClient::new(e, &flash_loan.asset);
+    let pre_repay_balance = token_client.balance(&pool_address);
+    FlashLoanReceiverClient::new(e, &flash_loan.contract).exec_op(
+        from,
+        &flash_loan.asset,
+        &flash_loan.amount,
+        &fee,
+    );
+    if token_client.balance(&pool_address) < pre_repay_balance + flash_loan.amount + fee
+        && !catchup::relax(e, Relaxed::FlashLoanRepayment)
+    {
+        panic_with_error!(e, PoolError::FlashLoanNotRepaid);
+    }
+
+    if fee > 0 {
+        let mut pool = Pool::load(e);
+        let mut reserve = pool.load_reserve(e, &flash_loan.asset, true);
+        reserve.credit_fee(pool.config.bstop_rate, fee);
+        pool.cache_reserve(reserve);
+        pool.store_cached_reserves(e);
+    }
+
+    (fee, positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        storage::{self, PoolConfig},
+        testutils,
+    };
+
+    use super::*;
+    use soroban_sdk::{
+        contract, contractimpl,
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec,
+    };
+
+    /// Flash loan receiver that repays the loan, and the fee if `pay_fee` is set
+    #[contract]
+    pub struct MockReceiver;
+
+    #[contractimpl]
+    impl MockReceiver {
+        pub fn exec_op(e: Env, _caller: Address, asset: Address, amount: i128, fee: i128) {
+            let pool = e
+                .storage()
+                .instance()
+                .get::<_, Address>(&soroban_sdk::symbol_short!("pool"))
+                .unwrap();
+            let repay_fee = e
+                .storage()
+                .instance()
+                .get::<_, bool>(&soroban_sdk::symbol_short!("pay_fee"))
+                .unwrap();
+            let to_repay = if repay_fee { amount + fee } else { amount };
+            TokenClient::new(&e, &asset).transfer(&e.current_contract_address(), &pool, &to_repay);
+        }
+
+        pub fn setup(e: Env, pool: Address, pay_fee: bool) {
+            e.storage()
+                .instance()
+                .set(&soroban_sdk::symbol_short!("pool"), &pool);
+            e.storage()
+                .instance()
+                .set(&soroban_sdk::symbol_short!("pay_fee"), &pay_fee);
+        }
+    }
+
+    fn setup_flash_loan(e: &Env, pay_fee: bool) -> (Address, Address, Address, FlashLoan) {
+        e.budget().reset_unlimited();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(e);
+        let samwise = Address::generate(e);
+        let pool = testutils::create_pool(e);
+
+        let (underlying, underlying_client) = testutils::create_token_contract(e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.flash_fee = 0_0010000;
+        reserve_data.last_time = 600;
+        testutils::create_reserve(e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let receiver = e.register_contract(None, MockReceiver);
+        MockReceiverClient::new(e, &receiver).setup(&pool, &pay_fee);
+        // the receiver holds enough to cover the fee
+        underlying_client.mint(&receiver, &1_0000000);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(e, &pool_config);
+        });
+
+        let flash_loan = FlashLoan {
+            contract: receiver.clone(),
+            asset: underlying.clone(),
+            amount: 20_0000000,
+        };
+        (pool, samwise, receiver, flash_loan)
+    }
+
+    #[test]
+    fn test_execute_flash_loan() {
+        let e = Env::default();
+        let (pool, samwise, receiver, flash_loan) = setup_flash_loan(&e, true);
+        let token_client = TokenClient::new(&e, &flash_loan.asset);
+
+        e.as_contract(&pool, || {
+            let reserve_data = storage::get_res_data(&e, &flash_loan.asset);
+            let pool_balance = token_client.balance(&pool);
+
+            let (fee, positions) = execute_flash_loan(&e, &samwise, &flash_loan, vec![&e]);
+
+            assert_eq!(fee, 0_0200000);
+            assert_eq!(positions.liabilities.len(), 0);
+            assert_eq!(token_client.balance(&pool), pool_balance + 0_0200000);
+            assert_eq!(token_client.balance(&receiver), 0_9800000);
+
+            // 20% of the fee is credited to the backstop and the rest to suppliers
+            let new_reserve_data = storage::get_res_data(&e, &flash_loan.asset);
+            assert_eq!(
+                new_reserve_data.backstop_credit,
+                reserve_data.backstop_credit + 0_0040000
+            );
+            assert!(new_reserve_data.b_rate > reserve_data.b_rate);
+            assert_eq!(new_reserve_data.d_rate, reserve_data.d_rate);
+        });
+    }
+
+    #[test]
+    fn test_execute_flash_loan_funds_requests() {
+        let e = Env::default();
+        let (pool, samwise, receiver, flash_loan) = setup_flash_loan(&e, true);
+        let token_client = TokenClient::new(&e, &flash_loan.asset);
+
+        e.as_contract(&pool, || {
+            let pool_balance = token_client.balance(&pool);
+
+            // samwise holds no tokens, so the supply is paid for with the loan and the
+            // withdrawal is sent to the receiver
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::Supply as u32,
+                    address: flash_loan.asset.clone(),
+                    amount: 5_0000000,
+                },
+                Request {
+                    request_type: RequestType::Withdraw as u32,
+                    address: flash_loan.asset.clone(),
+                    amount: 5_0000000,
+                },
+            ];
+            let (fee, positions) = execute_flash_loan(&e, &samwise, &flash_loan, requests);
+
+            assert_eq!(fee, 0_0200000);
+            assert_eq!(positions.supply.len(), 0);
+            assert_eq!(token_client.balance(&samwise), 0);
+            assert_eq!(token_client.balance(&pool), pool_balance + 0_0200000);
+            assert_eq!(token_client.balance(&receiver), 0_9800000);
+        });
+    }
+
+    #[test]
+    #[cfg(not(feature = "catchup"))]
+    #[should_panic(expected = "Error(Contract, #1226)")]
+    fn test_execute_flash_loan_requires_fee() {
+        let e = Env::default();
+        let (pool, samwise, _, flash_loan) = setup_flash_loan(&e, false);
+
+        e.as_contract(&pool, || {
+            execute_flash_loan(&e, &samwise, &flash_loan, vec![&e]);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1225)")]
+    fn test_execute_flash_loan_over_liquidity() {
+        let e = Env::default();
+        let (pool, samwise, _, mut flash_loan) = setup_flash_loan(&e, true);
+        // the default reserve has 100 supplied and 75 borrowed
+        flash_loan.amount = 25_0000001;
+
+        e.as_contract(&pool, || {
+            execute_flash_loan(&e, &samwise, &flash_loan, vec![&e]);
+        });
+    }
+}
@@ -1,9 +1,16 @@
+use cast::i128;
 use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::{unwrap::UnwrapOptimized, Env};
 
-use crate::{constants::SCALAR_7, storage};
+use crate::{
+    constants::{RESERVE_SUNSET, SCALAR_7},
+    storage::{self, EModeCategory},
+};
 
-use super::{pool::Pool, Positions};
+use super::{
+    pool::{Pool, PriceMode},
+    Positions,
+};
 
 pub struct PositionData {
     /// The effective collateral balance denominated in the base asset
@@ -24,10 +31,23 @@ impl PositionData {
     /// ### Arguments
     /// * pool - The pool
     /// * positions - The positions to calculate the health factor for
-    pub fn calculate_from_positions(e: &Env, pool: &mut Pool, positions: &Positions) -> Self {
+    /// * emode - The efficiency mode category the positions' owner has opted into. Its factors are
+    ///           only applied if all of the collateral and liabilities are in the category.
+    /// * mode - The health check the positions are valued for. Reserves that opt into TWAP pricing
+    ///          for the mode are valued against both the spot price and the TWAP.
+    pub fn calculate_from_positions(
+        e: &Env,
+        pool: &mut Pool,
+        positions: &Positions,
+        emode: &Option<EModeCategory>,
+        mode: PriceMode,
+    ) -> Self {
         let oracle_scalar = 10i128.pow(pool.load_price_decimals(e));
 
         let reserve_list = storage::get_res_list(e);
+        let emode = emode
+            .as_ref()
+            .filter(|category| category.contains_positions(e, positions));
 
         let mut collateral_base = 0;
         let mut liability_base = 0;
@@ -40,11 +60,18 @@ impl PositionData {
                 continue;
             }
             let reserve = pool.load_reserve(e, &reserve_list.get_unchecked(i), false);
-            let asset_to_base = 1;
 
             if b_token_balance > 0 {
+                let asset_to_base = pool.load_collateral_price(e, &reserve.asset, mode);
                 // append users effective collateral to collateral_base
-                let asset_collateral = reserve.to_effective_asset_from_b_token(b_token_balance);
+                // a sunset reserve's collateral factor ramps down regardless of the category
+                let asset_collateral = match emode {
+                    Some(category) if reserve.flags & RESERVE_SUNSET == 0 => reserve
+                        .to_asset_from_b_token(b_token_balance)
+                        .fixed_mul_floor(i128(category.c_factor), SCALAR_7)
+                        .unwrap_optimized(),
+                    _ => reserve.to_effective_asset_from_b_token(b_token_balance),
+                };
                 collateral_base += asset_to_base
                     .fixed_mul_floor(asset_collateral, reserve.scalar)
                     .unwrap_optimized();
@@ -57,17 +84,15 @@ impl PositionData {
             }
 
             if d_token_balance > 0 {
+                let asset_to_base = pool.load_liability_price(e, &reserve.asset, mode);
                 // append users effective liability to liability_base
-                let asset_liability = reserve.to_effective_asset_from_d_token(d_token_balance);
-                e.events().publish(
-                    (
-                        "asset_liability",
-                        asset_to_base,
-                        asset_liability,
-                        reserve.scalar,
-                    ),
-                    (),
-                );
+                let asset_liability = match emode {
+                    Some(category) => reserve
+                        .to_asset_from_d_token(d_token_balance)
+                        .fixed_div_ceil(i128(category.l_factor), SCALAR_7)
+                        .unwrap_optimized(),
+                    None => reserve.to_effective_asset_from_d_token(d_token_balance),
+                };
                 liability_base += asset_to_base
                     .fixed_mul_ceil(asset_liability, reserve.scalar)
                     .unwrap_optimized();
@@ -204,6 +229,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 5,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
 
         let positions = Positions {
@@ -214,7 +242,13 @@ mod tests {
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
             let mut pool = Pool::load(&e);
-            let position_data = PositionData::calculate_from_positions(&e, &mut pool, &positions);
+            let position_data = PositionData::calculate_from_positions(
+                &e,
+                &mut pool,
+                &positions,
+                &None,
+                PriceMode::Borrow,
+            );
             assert_eq!(position_data.collateral_base, 262_7985925);
             assert_eq!(position_data.liability_base, 185_2368828);
             assert_eq!(position_data.collateral_raw, 350_3984567);
@@ -223,6 +257,97 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_calculate_from_positions_emode() {
+        let e = Env::default();
+        e.budget().reset_unlimited();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, reserve_data) = testutils::default_reserve_meta();
+        reserve_config.index = 1;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 1_0000000, 1_0000000]);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 0,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 5,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+
+        let positions = Positions {
+            liabilities: map![&e, (1, 50_0000000)],
+            collateral: map![&e, (0, 100_0000000)],
+            supply: map![&e],
+        };
+        let mut category = EModeCategory {
+            c_factor: 0_9500000,
+            l_factor: 0_9500000,
+            assets: vec![&e, underlying_0.clone(), underlying_1.clone()],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            let mut pool = Pool::load(&e);
+            let position_data = PositionData::calculate_from_positions(
+                &e,
+                &mut pool,
+                &positions,
+                &Some(category.clone()),
+                PriceMode::Borrow,
+            );
+            assert_eq!(position_data.collateral_base, 95_0000000);
+            assert_eq!(position_data.liability_base, 52_6315790);
+            assert_eq!(position_data.collateral_raw, 100_0000000);
+            assert_eq!(position_data.liability_raw, 50_0000000);
+
+            // the category factors are not applied if a position is outside of the category
+            category.assets = vec![&e, underlying_0.clone()];
+            let position_data = PositionData::calculate_from_positions(
+                &e,
+                &mut pool,
+                &positions,
+                &Some(category),
+                PriceMode::Borrow,
+            );
+            assert_eq!(position_data.collateral_base, 75_0000000);
+            assert_eq!(position_data.liability_base, 66_6666667);
+        });
+    }
+
     #[test]
     fn test_as_health_factor_rounds_floor() {
         let position_data = PositionData {
@@ -3,13 +3,20 @@ use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::{unwrap::UnwrapOptimized, Env};
 
 use crate::{
-    constants::{SCALAR_7, SCALAR_9, SECONDS_PER_YEAR},
+    catchup::{self, Relaxed},
+    constants::{
+        RATE_MODEL_FIXED, RATE_MODEL_STATIC, RATE_MODEL_TWO_SLOPE, SCALAR_7, SCALAR_9,
+        SECONDS_PER_YEAR,
+    },
     storage::ReserveConfig,
 };
 
 /// Calculates the loan accrual ratio for the Reserve based on the current utilization and
 /// rate modifier for the reserve.
 ///
+/// Only the reactive rate model uses and updates the rate modifier. The other rate models
+/// reset it to 1.
+///
 /// ### Arguments
 /// * `config` - The Reserve config to calculate an accrual for
 /// * `cur_util` - The current utilization rate of the reserve (7 decimals)
@@ -18,7 +25,6 @@ use crate::{
 ///
 /// ### Returns
 /// * (i128, i128) - (accrual amount scaled to 9 decimal places, new interest rate modifier scaled to 9 decimal places)
-#[allow(clippy::zero_prefixed_literal)]
 pub fn calc_accrual(
     e: &Env,
     config: &ReserveConfig,
@@ -26,7 +32,44 @@ pub fn calc_accrual(
     ir_mod: i128,
     last_time: u64,
 ) -> (i128, i128) {
-    let cur_ir: i128;
+    let last_time = if last_time > e.ledger().timestamp() && catchup::relax(e, Relaxed::AccrualTime)
+    {
+        e.ledger().timestamp().saturating_sub(500)
+    } else {
+        last_time
+    };
+
+    // scale delta blocks to 9 decimals
+    let delta_time_scaled = i128(e.ledger().timestamp() - last_time) * SCALAR_9;
+
+    let (cur_ir, new_ir_mod) = match config.rate_model {
+        RATE_MODEL_FIXED => (i128(config.r_base), SCALAR_9),
+        RATE_MODEL_TWO_SLOPE => (calc_two_slope_rate(config, cur_util), SCALAR_9),
+        RATE_MODEL_STATIC => (calc_three_kink_rate(config, cur_util, SCALAR_9), SCALAR_9),
+        _ => (
+            calc_three_kink_rate(config, cur_util, ir_mod),
+            calc_ir_mod(config, cur_util, ir_mod, delta_time_scaled),
+        ),
+    };
+
+    // calc accrual amount over blocks
+    let time_weight = delta_time_scaled / SECONDS_PER_YEAR;
+    (
+        SCALAR_9
+            + time_weight
+                .fixed_mul_ceil(cur_ir * 100, SCALAR_9)
+                .unwrap_optimized(),
+        new_ir_mod,
+    )
+}
+
+/// Calculates the interest rate on the three kink curve, with kinks at the target utilization
+/// and 95% utilization, scaled by the rate modifier
+///
+/// ### Returns
+/// * i128 - the interest rate scaled to 7 decimal places
+#[allow(clippy::zero_prefixed_literal)]
+fn calc_three_kink_rate(config: &ReserveConfig, cur_util: i128, ir_mod: i128) -> i128 {
     let target_util: i128 = i128(config.util);
     if cur_util <= target_util {
         let util_scalar = cur_util
@@ -37,9 +80,9 @@ pub fn calc_accrual(
             .unwrap_optimized()
             + i128(config.r_base);
 
-        cur_ir = base_rate
+        base_rate
             .fixed_mul_ceil(ir_mod, SCALAR_9)
-            .unwrap_optimized();
+            .unwrap_optimized()
     } else if cur_util <= 0_9500000 {
         let util_scalar = (cur_util - target_util)
             .fixed_div_ceil(0_9500000 - target_util, SCALAR_7)
@@ -50,9 +93,9 @@ pub fn calc_accrual(
             + i128(config.r_one)
             + i128(config.r_base);
 
-        cur_ir = base_rate
+        base_rate
             .fixed_mul_ceil(ir_mod, SCALAR_9)
-            .unwrap_optimized();
+            .unwrap_optimized()
     } else {
         let util_scalar = (cur_util - 0_9500000)
             .fixed_div_ceil(0_0500000, SCALAR_7)
@@ -64,22 +107,47 @@ pub fn calc_accrual(
         let intersection = ir_mod
             .fixed_mul_ceil(i128(config.r_two + config.r_one + config.r_base), SCALAR_9)
             .unwrap_optimized();
-        cur_ir = extra_rate + intersection;
+        extra_rate + intersection
     }
+}
 
-    // update rate_modifier
-    // scale delta blocks and util dif to 9 decimals
-
-    let last_time = if last_time > e.ledger().timestamp() {
-        (e.ledger().timestamp() - 500)
+/// Calculates the interest rate on the two slope curve, where `r_one` is reached at the kink and
+/// `r_two` is added between the kink and 100% utilization
+///
+/// ### Returns
+/// * i128 - the interest rate scaled to 7 decimal places
+fn calc_two_slope_rate(config: &ReserveConfig, cur_util: i128) -> i128 {
+    let kink: i128 = i128(config.kink);
+    if cur_util <= kink {
+        let util_scalar = cur_util.fixed_div_ceil(kink, SCALAR_7).unwrap_optimized();
+        util_scalar
+            .fixed_mul_ceil(i128(config.r_one), SCALAR_7)
+            .unwrap_optimized()
+            + i128(config.r_base)
     } else {
-        last_time
-    };
-
-    let delta_time_scaled = i128(e.ledger().timestamp() - last_time) * SCALAR_9;
+        let util_scalar = (cur_util - kink)
+            .fixed_div_ceil(SCALAR_7 - kink, SCALAR_7)
+            .unwrap_optimized();
+        util_scalar
+            .fixed_mul_ceil(i128(config.r_two), SCALAR_7)
+            .unwrap_optimized()
+            + i128(config.r_one)
+            + i128(config.r_base)
+    }
+}
 
-    let util_dif_scaled = (cur_util - target_util) * 100;
-    let new_ir_mod: i128;
+/// Calculates the new rate modifier, which moves towards the utilization error over time
+///
+/// ### Returns
+/// * i128 - the new interest rate modifier scaled to 9 decimal places
+fn calc_ir_mod(
+    config: &ReserveConfig,
+    cur_util: i128,
+    ir_mod: i128,
+    delta_time_scaled: i128,
+) -> i128 {
+    // scale util dif to 9 decimals
+    let util_dif_scaled = (cur_util - i128(config.util)) * 100;
     if util_dif_scaled >= 0 {
         // rate modifier increasing
         let util_error = delta_time_scaled
@@ -91,9 +159,9 @@ pub fn calc_accrual(
         let next_ir_mod = ir_mod + rate_dif;
         let ir_mod_max = 10 * SCALAR_9;
         if next_ir_mod > ir_mod_max {
-            new_ir_mod = ir_mod_max;
+            ir_mod_max
         } else {
-            new_ir_mod = next_ir_mod;
+            next_ir_mod
         }
     } else {
         // rate modifier decreasing
@@ -106,21 +174,11 @@ pub fn calc_accrual(
         let next_ir_mod = ir_mod + rate_dif;
         let ir_mod_min = SCALAR_9 / 10;
         if next_ir_mod < ir_mod_min {
-            new_ir_mod = ir_mod_min;
+            ir_mod_min
         } else {
-            new_ir_mod = next_ir_mod;
+            next_ir_mod
         }
     }
-
-    // calc accrual amount over blocks
-    let time_weight = delta_time_scaled / SECONDS_PER_YEAR;
-    (
-        SCALAR_9
-            + time_weight
-                .fixed_mul_ceil(cur_ir * 100, SCALAR_9)
-                .unwrap_optimized(),
-        new_ir_mod,
-    )
 }
 
 #[cfg(test)]
@@ -143,6 +201,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 1_000_000_000;
@@ -179,6 +245,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 1_000_000_000;
@@ -215,6 +289,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 1_000_000_000;
@@ -251,6 +333,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 9_997_000_000;
@@ -286,6 +376,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 0_150_000_000;
@@ -321,6 +419,14 @@ mod tests {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 0_100_000_000;
@@ -357,6 +463,14 @@ mod tests {
             r_two: 0,
             r_three: 0,
             reactivity: 0_0000020,
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         };
         let ir_mod: i128 = 1_000_000_000;
@@ -386,4 +500,145 @@ mod tests {
         assert_eq!(accrual_3, 1_000_003_964);
         assert_eq!(ir_mod_3, 1_000_206_565);
     }
+
+    #[test]
+    fn test_calc_accrual_fixed_model() {
+        let e = Env::default();
+
+        let reserve_config = ReserveConfig {
+            decimals: 7,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_7500000,
+            max_util: 0_9500000,
+            r_base: 0_2500000,
+            r_one: 0,
+            r_two: 0,
+            r_three: 0,
+            reactivity: 0,
+            rate_model: RATE_MODEL_FIXED,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+            index: 0,
+        };
+        let ir_mod: i128 = 1_500_000_000;
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 500,
+            protocol_version: 20,
+            sequence_number: 100,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let (accrual_0, ir_mod_0) = calc_accrual(&e, &reserve_config, 0, ir_mod, 0);
+        let (accrual_1, ir_mod_1) = calc_accrual(&e, &reserve_config, 0_9565656, ir_mod, 0);
+
+        assert_eq!(accrual_0, 1_000_003_964);
+        assert_eq!(ir_mod_0, 1_000_000_000);
+        assert_eq!(accrual_1, 1_000_003_964);
+        assert_eq!(ir_mod_1, 1_000_000_000);
+    }
+
+    #[test]
+    fn test_calc_accrual_two_slope_model() {
+        let e = Env::default();
+
+        let reserve_config = ReserveConfig {
+            decimals: 7,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_7500000,
+            max_util: 0_9500000,
+            r_base: 0_0100000,
+            r_one: 0_0500000,
+            r_two: 0_5000000,
+            r_three: 0,
+            reactivity: 0,
+            rate_model: RATE_MODEL_TWO_SLOPE,
+            kink: 0_8000000,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+            index: 0,
+        };
+        let ir_mod: i128 = 1_500_000_000;
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 500,
+            protocol_version: 20,
+            sequence_number: 100,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let (accrual_0, ir_mod_0) = calc_accrual(&e, &reserve_config, 0_4000000, ir_mod, 0);
+        let (accrual_1, ir_mod_1) = calc_accrual(&e, &reserve_config, 0_9000000, ir_mod, 0);
+
+        // 3.5% under the kink
+        assert_eq!(accrual_0, 1_000_000_555);
+        assert_eq!(ir_mod_0, 1_000_000_000);
+        // 31% over the kink
+        assert_eq!(accrual_1, 1_000_004_915);
+        assert_eq!(ir_mod_1, 1_000_000_000);
+    }
+
+    #[test]
+    fn test_calc_accrual_static_model() {
+        let e = Env::default();
+
+        let reserve_config = ReserveConfig {
+            decimals: 7,
+            c_factor: 0_7500000,
+            l_factor: 0_7500000,
+            util: 0_7500000,
+            max_util: 0_9500000,
+            r_base: 0_0100000,
+            r_one: 0_0500000,
+            r_two: 0_5000000,
+            r_three: 1_5000000,
+            reactivity: 0,
+            rate_model: RATE_MODEL_STATIC,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
+            index: 0,
+        };
+        let ir_mod: i128 = 1_500_000_000;
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 500,
+            protocol_version: 20,
+            sequence_number: 100,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let (accrual, ir_mod) = calc_accrual(&e, &reserve_config, 0_6565656, ir_mod, 0);
+
+        // matches the reactive model with a rate modifier of 1
+        assert_eq!(accrual, 1_000_000_853);
+        assert_eq!(ir_mod, 1_000_000_000);
+    }
 }
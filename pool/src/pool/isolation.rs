@@ -0,0 +1,523 @@
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{map, panic_with_error, unwrap::UnwrapOptimized, Address, Env, Map};
+
+use crate::{
+    errors::PoolError,
+    storage::{self, UserIsolatedDebt},
+};
+
+use super::{pool::Pool, Positions, Reserve};
+
+/// The debt backed by isolated collateral after a user's positions change
+pub struct IsolatedDebtUpdate {
+    /// The debt backed by the user's isolated collateral, if any
+    pub user_debt: Option<UserIsolatedDebt>,
+    /// The new total debt backed by each isolated reserve the user's debt moved from or to
+    pub total_debt: Map<Address, i128>,
+}
+
+/// Require that a user's positions respect isolation mode, and track the debt backed by their
+/// isolated collateral.
+///
+/// A user with isolated collateral cannot hold any other collateral, and can only borrow from
+/// reserves that allow borrowing against isolated collateral. The debt backed by each isolated
+/// reserve is tracked against the reserve's debt ceiling.
+///
+/// ### Arguments
+/// * user - The address of the user
+/// * pool - The pool
+/// * positions - The user's positions after the requests have been processed
+/// * check_rules - If the requests added collateral or liabilities to the positions
+///
+/// ### Panics
+/// If the positions mix isolated collateral with other collateral, borrow from a reserve
+/// that does not allow it, or exceed the isolated reserve's debt ceiling
+pub fn require_isolation(
+    e: &Env,
+    user: &Address,
+    pool: &mut Pool,
+    positions: &Positions,
+    check_rules: bool,
+) {
+    match check_isolation(e, user, pool, positions, check_rules) {
+        Ok(Some(update)) => store_isolated_debt(e, user, &update),
+        Ok(None) => (),
+        Err(error) => panic_with_error!(e, error),
+    }
+}
+
+/// Update the debt backed by a user's isolated collateral after their liabilities were moved or
+/// removed without their involvement, like by a liquidation or a bad debt transfer. Isolation
+/// rules are not checked.
+///
+/// ### Arguments
+/// * user - The address of the user
+/// * pool - The pool
+/// * positions - The user's positions after the update
+///
+/// ### Panics
+/// If the tracked isolated debt is inconsistent
+pub fn update_isolated_debt(e: &Env, user: &Address, pool: &mut Pool, positions: &Positions) {
+    require_isolation(e, user, pool, positions, false);
+}
+
+/// Check that a user's positions respect isolation mode, without storing the debt backed by
+/// their isolated collateral.
+///
+/// The debt backed by a user's isolated collateral is the value of all of their liabilities in
+/// the oracle's base asset, priced like health checks, as of their last update. Returns the debt
+/// tracked for the user and isolated reserves once updated, or None if nothing is tracked for
+/// the user.
+///
+/// ### Arguments
+/// * user - The address of the user
+/// * pool - The pool
+/// * positions - The user's positions after the requests have been processed
+/// * check_rules - If the requests added collateral or liabilities to the positions
+pub fn check_isolation(
+    e: &Env,
+    user: &Address,
+    pool: &mut Pool,
+    positions: &Positions,
+    check_rules: bool,
+) -> Result<Option<IsolatedDebtUpdate>, PoolError> {
+    let reserve_list = storage::get_res_list(e);
+    let mut isolated: Option<Reserve> = None;
+    for index in positions.collateral.keys().iter() {
+        let reserve = pool.load_reserve(e, &reserve_list.get_unchecked(index), false);
+        if reserve.isolated {
+            isolated = Some(reserve.clone());
+        }
+        pool.cache_reserve(reserve);
+    }
+    let prev_user_debt = storage::get_user_isolated_debt(e, user);
+    if isolated.is_none() && prev_user_debt.is_none() {
+        return Ok(None);
+    }
+
+    let mut debt = 0;
+    if isolated.is_some() {
+        if check_rules && positions.collateral.len() > 1 {
+            return Err(PoolError::IsolationViolation);
+        }
+        for (index, d_tokens) in positions.liabilities.iter() {
+            let reserve = pool.load_reserve(e, &reserve_list.get_unchecked(index), false);
+            if check_rules && !reserve.isolated_borrow {
+                return Err(PoolError::IsolationViolation);
+            }
+            let asset_to_base = pool.load_price(e, &reserve.asset);
+            debt += asset_to_base
+                .fixed_mul_ceil(reserve.to_asset_from_d_token(d_tokens), reserve.scalar)
+                .unwrap_optimized();
+            pool.cache_reserve(reserve);
+        }
+    }
+    if debt == 0 && prev_user_debt.is_none() {
+        return Ok(None);
+    }
+
+    // move the user's debt off of the isolated reserve it was tracked against
+    // the total debt always includes the user's previous debt, so it can't go below zero
+    // unless the isolated debt accounting is broken
+    let mut total_debt: Map<Address, i128> = map![e];
+    if let Some(prev_user_debt) = &prev_user_debt {
+        let total = storage::get_isolated_debt(e, &prev_user_debt.asset) - prev_user_debt.debt;
+        if total < 0 {
+            return Err(PoolError::InternalError);
+        }
+        total_debt.set(prev_user_debt.asset.clone(), total);
+    }
+
+    // and onto the isolated reserve now backing it. Any increase in the debt, including interest
+    // accrued since the user's last update, must respect the debt ceiling.
+    let user_debt = match isolated {
+        Some(isolated) if debt > 0 => {
+            let prev_debt = match &prev_user_debt {
+                Some(prev_user_debt) if prev_user_debt.asset == isolated.asset => {
+                    prev_user_debt.debt
+                }
+                _ => 0,
+            };
+            let total = total_debt
+                .get(isolated.asset.clone())
+                .unwrap_or_else(|| storage::get_isolated_debt(e, &isolated.asset))
+                + debt;
+            if check_rules
+                && debt > prev_debt
+                && isolated.debt_ceiling > 0
+                && total > isolated.debt_ceiling
+            {
+                return Err(PoolError::DebtCeilingExceeded);
+            }
+            total_debt.set(isolated.asset.clone(), total);
+            Some(UserIsolatedDebt {
+                asset: isolated.asset,
+                debt,
+            })
+        }
+        _ => None,
+    };
+    Ok(Some(IsolatedDebtUpdate {
+        user_debt,
+        total_debt,
+    }))
+}
+
+/// Store the debt backed by a user's isolated collateral
+///
+/// ### Arguments
+/// * user - The address of the user
+/// * update - The updated isolated debt
+fn store_isolated_debt(e: &Env, user: &Address, update: &IsolatedDebtUpdate) {
+    storage::set_user_isolated_debt(e, user, &update.user_debt);
+    for (asset, debt) in update.total_debt.iter() {
+        storage::set_isolated_debt(e, &asset, debt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{storage::PoolConfig, testutils};
+    use sep_40_oracle::testutils::Asset;
+    use soroban_sdk::{
+        map,
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec, Symbol, Vec,
+    };
+
+    /// Create an isolated reserve with a debt ceiling of 100, a reserve that can be borrowed
+    /// against it, a reserve that can't, and a second reserve that can be borrowed against it
+    /// priced at 100
+    fn setup_isolation(e: &Env) -> (Address, Vec<Address>) {
+        e.mock_all_auths();
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(e);
+        let pool = testutils::create_pool(e);
+
+        let (underlying_0, _) = testutils::create_token_contract(e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        reserve_config.isolated = true;
+        reserve_config.debt_ceiling = 100_0000000;
+        testutils::create_reserve(e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        reserve_config.index = 1;
+        reserve_config.isolated_borrow = true;
+        testutils::create_reserve(e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        let (underlying_2, _) = testutils::create_token_contract(e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        reserve_config.index = 2;
+        testutils::create_reserve(e, &pool, &underlying_2, &reserve_config, &reserve_data);
+
+        let (underlying_3, _) = testutils::create_token_contract(e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600;
+        reserve_config.index = 3;
+        reserve_config.isolated_borrow = true;
+        testutils::create_reserve(e, &pool, &underlying_3, &reserve_config, &reserve_data);
+
+        let (oracle, oracle_client) = testutils::create_mock_oracle(e);
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(e, "USD")),
+            &vec![
+                e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+                Asset::Stellar(underlying_2.clone()),
+                Asset::Stellar(underlying_3.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![e, 1_0000000, 1_0000000, 1_0000000, 100_0000000]);
+
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(e, &pool_config);
+        });
+        (
+            pool,
+            vec![e, underlying_0, underlying_1, underlying_2, underlying_3],
+        )
+    }
+
+    #[test]
+    fn test_require_isolation_tracks_debt() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let mut positions = Positions {
+            liabilities: map![&e, (1, 10_0000000)],
+            collateral: map![&e, (0, 50_0000000)],
+            supply: map![&e, (2, 5_0000000)],
+        };
+        e.as_contract(&pool, || {
+            storage::set_isolated_debt(&e, &assets.get_unchecked(0), 20_0000000);
+
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+            let user_debt = storage::get_user_isolated_debt(&e, &samwise).unwrap();
+            assert_eq!(user_debt.asset, assets.get_unchecked(0));
+            assert_eq!(user_debt.debt, 10_0000000);
+            assert_eq!(
+                storage::get_isolated_debt(&e, &assets.get_unchecked(0)),
+                30_0000000
+            );
+
+            positions.liabilities.set(1, 4_0000000);
+            require_isolation(&e, &samwise, &mut pool, &positions, false);
+            let user_debt = storage::get_user_isolated_debt(&e, &samwise).unwrap();
+            assert_eq!(user_debt.debt, 4_0000000);
+            assert_eq!(
+                storage::get_isolated_debt(&e, &assets.get_unchecked(0)),
+                24_0000000
+            );
+
+            positions.liabilities.remove(1);
+            require_isolation(&e, &samwise, &mut pool, &positions, false);
+            assert!(storage::get_user_isolated_debt(&e, &samwise).is_none());
+            assert_eq!(
+                storage::get_isolated_debt(&e, &assets.get_unchecked(0)),
+                20_0000000
+            );
+        });
+    }
+
+    #[test]
+    fn test_require_isolation_moves_debt_off_removed_collateral() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let positions = Positions {
+            liabilities: map![&e, (1, 10_0000000)],
+            collateral: map![&e, (2, 50_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_isolated_debt(&e, &assets.get_unchecked(0), 30_0000000);
+            storage::set_user_isolated_debt(
+                &e,
+                &samwise,
+                &Some(UserIsolatedDebt {
+                    asset: assets.get_unchecked(0),
+                    debt: 10_0000000,
+                }),
+            );
+
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+            assert!(storage::get_user_isolated_debt(&e, &samwise).is_none());
+            assert_eq!(
+                storage::get_isolated_debt(&e, &assets.get_unchecked(0)),
+                20_0000000
+            );
+        });
+    }
+
+    #[test]
+    fn test_require_isolation_ignores_regular_collateral() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let positions = Positions {
+            liabilities: map![&e, (2, 10_0000000)],
+            collateral: map![&e, (1, 50_0000000), (2, 50_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+            assert!(storage::get_user_isolated_debt(&e, &samwise).is_none());
+            assert_eq!(storage::get_isolated_debt(&e, &assets.get_unchecked(0)), 0);
+        });
+    }
+
+    #[test]
+    fn test_update_isolated_debt_skips_rules() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        // a liquidation can leave mixed collateral and debt over the ceiling in place
+        let positions = Positions {
+            liabilities: map![&e, (1, 60_0000000), (2, 10_0000000)],
+            collateral: map![&e, (0, 50_0000000), (2, 5_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_isolated_debt(&e, &assets.get_unchecked(0), 120_0000000);
+            storage::set_user_isolated_debt(
+                &e,
+                &samwise,
+                &Some(UserIsolatedDebt {
+                    asset: assets.get_unchecked(0),
+                    debt: 80_0000000,
+                }),
+            );
+
+            let mut pool = Pool::load(&e);
+            update_isolated_debt(&e, &samwise, &mut pool, &positions);
+            let user_debt = storage::get_user_isolated_debt(&e, &samwise).unwrap();
+            assert_eq!(user_debt.debt, 70_0000000);
+            assert_eq!(
+                storage::get_isolated_debt(&e, &assets.get_unchecked(0)),
+                110_0000000
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1)")]
+    fn test_require_isolation_inconsistent_debt() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let positions = Positions {
+            liabilities: map![&e, (1, 10_0000000)],
+            collateral: map![&e, (0, 50_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_isolated_debt(&e, &assets.get_unchecked(0), 5_0000000);
+            storage::set_user_isolated_debt(
+                &e,
+                &samwise,
+                &Some(UserIsolatedDebt {
+                    asset: assets.get_unchecked(0),
+                    debt: 10_0000000,
+                }),
+            );
+
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, false);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1228)")]
+    fn test_require_isolation_mixed_collateral() {
+        let e = Env::default();
+        let (pool, _) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let positions = Positions {
+            liabilities: map![&e],
+            collateral: map![&e, (0, 50_0000000), (2, 5_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1228)")]
+    fn test_require_isolation_borrow_not_allowed() {
+        let e = Env::default();
+        let (pool, _) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let positions = Positions {
+            liabilities: map![&e, (1, 10_0000000), (2, 1_0000000)],
+            collateral: map![&e, (0, 50_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1229)")]
+    fn test_require_isolation_over_debt_ceiling() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        let positions = Positions {
+            liabilities: map![&e, (1, 10_0000001)],
+            collateral: map![&e, (0, 50_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_isolated_debt(&e, &assets.get_unchecked(0), 90_0000000);
+
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+        });
+    }
+
+    #[test]
+    fn test_require_isolation_values_debt_in_base_asset() {
+        let e = Env::default();
+        let (pool, assets) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        // 10 tokens priced at 1 and 0.5 tokens priced at 100
+        let positions = Positions {
+            liabilities: map![&e, (1, 10_0000000), (3, 0_5000000)],
+            collateral: map![&e, (0, 500_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+            let user_debt = storage::get_user_isolated_debt(&e, &samwise).unwrap();
+            assert_eq!(user_debt.debt, 60_0000000);
+            assert_eq!(
+                storage::get_isolated_debt(&e, &assets.get_unchecked(0)),
+                60_0000000
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1229)")]
+    fn test_require_isolation_priced_debt_over_debt_ceiling() {
+        let e = Env::default();
+        let (pool, _) = setup_isolation(&e);
+        let samwise = Address::generate(&e);
+
+        // 1.01 tokens priced at 100 exceed the ceiling of 100
+        let positions = Positions {
+            liabilities: map![&e, (3, 1_0100000)],
+            collateral: map![&e, (0, 500_0000000)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            let mut pool = Pool::load(&e);
+            require_isolation(&e, &samwise, &mut pool, &positions, true);
+        });
+    }
+}
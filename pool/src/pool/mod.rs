@@ -2,33 +2,69 @@ mod actions;
 pub use actions::{Request, RequestType};
 
 mod bad_debt;
-pub use bad_debt::transfer_bad_debt_to_backstop;
+pub use bad_debt::{
+    burn_backstop_bad_debt, execute_set_bad_debt_floor, transfer_bad_debt_to_backstop,
+};
 
 mod config;
+#[cfg(feature = "retroshades")]
+pub use config::execute_set_reserve_oracle;
 pub use config::{
-    execute_cancel_queued_set_reserve, execute_initialize, execute_queue_set_reserve,
-    execute_set_reserve, execute_update_pool,
+    execute_accept_admin, execute_cancel_queued_set_reserve, execute_cancel_queued_update_pool,
+    execute_initialize, execute_queue_set_reserve, execute_queue_update_pool, execute_set_reserve,
+    execute_set_reserve_flags, execute_set_reserve_price_config, execute_update_pool,
+};
+
+mod delegation;
+pub use delegation::{
+    execute_approve_borrow, execute_approve_operator, execute_revoke_operator, is_operator,
+    load_borrow_allowance, require_delegated_auth,
 };
 
+mod emode;
+pub use emode::{
+    check_in_emode, execute_set_emode_category, execute_set_user_emode, load_user_emode,
+};
+
+mod flash_loan;
+pub use flash_loan::{execute_flash_loan, FlashLoan};
+
 mod health_factor;
 pub use health_factor::PositionData;
 
 mod interest;
 
+mod isolation;
+pub use isolation::{require_isolation, update_isolated_debt};
+
 mod submit;
 
 pub use submit::execute_submit;
 
 #[allow(clippy::module_inception)]
 mod pool;
-pub use pool::Pool;
+pub use pool::{Pool, PriceMode};
+
+mod preview;
+pub use preview::{preview_submit, SubmitPreview};
+
+mod price;
 
 mod reserve;
 pub use reserve::Reserve;
 
+mod sunset;
+pub use sunset::{
+    execute_cancel_sunset_reserve, execute_queue_sunset_reserve, execute_retire_reserve,
+    execute_sunset_reserve,
+};
+
 mod user;
 pub use user::{Positions, User};
 
+mod views;
+pub use views::{load_reserve_view, load_user_position_data, ReserveView, UserPositionData};
+
 mod status;
 pub use status::{
     calc_pool_backstop_threshold, execute_set_pool_status, execute_update_pool_status,
@@ -1,24 +1,34 @@
-use soroban_sdk::{
-    map, panic_with_error, token::TokenClient, unwrap::UnwrapOptimized, vec, Address, Env, Map, Vec,
-};
+use soroban_sdk::{map, panic_with_error, vec, Address, Env, Map, Vec};
 
-use sep_40_oracle::{Asset, PriceFeedClient};
+use sep_40_oracle::PriceFeedClient;
 
 use crate::{
+    catchup::{self, Relaxed},
     errors::PoolError,
-    reflector_oracle,
-    storage::{self, PoolConfig},
+    storage::{self, PoolConfig, ReservePriceConfig},
     Positions,
 };
 
-use super::reserve::Reserve;
+use super::{price, reserve::Reserve};
+
+/// The health check a position is valued for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PriceMode {
+    /// Checks that a user can take on more risk, like borrowing or withdrawing collateral
+    Borrow,
+    /// Checks that a user can be liquidated
+    Liquidation,
+}
 
 pub struct Pool {
     pub config: PoolConfig,
+    pub retired: Vec<u32>,
     pub reserves: Map<Address, Reserve>,
     reserves_to_store: Vec<Address>,
     price_decimals: Option<u32>,
     prices: Map<Address, i128>,
+    twaps: Map<Address, i128>,
+    preview: bool,
 }
 
 impl Pool {
@@ -27,13 +37,30 @@ impl Pool {
         let pool_config = storage::get_pool_config(e);
         Pool {
             config: pool_config,
+            retired: storage::get_retired_reserves(e),
             reserves: map![e],
             reserves_to_store: vec![e],
             price_decimals: None,
             prices: map![e],
+            twaps: map![e],
+            preview: false,
+        }
+    }
+
+    /// Load the Pool from the ledger to preview requests. Requests processed against a preview
+    /// pool do not store auctions or other users' positions, transfer tokens, or publish events.
+    pub fn load_preview(e: &Env) -> Self {
+        Pool {
+            preview: true,
+            ..Pool::load(e)
         }
     }
 
+    /// Check if the pool is only previewing requests
+    pub fn is_preview(&self) -> bool {
+        self.preview
+    }
+
     /// Load a Reserve from the ledger and update to the current ledger timestamp. Returns
     /// a cached version if it exists.
     ///
@@ -71,19 +98,48 @@ impl Pool {
         }
     }
 
-    /// Require that the action does not violate the pool status, or panic.
+    /// Check that the action does not violate the pool status
     ///
     /// ### Arguments
     /// * `action_type` - The type of action being performed
-    pub fn require_action_allowed(&self, e: &Env, action_type: u32) {
+    pub fn check_action_allowed(&self, action_type: u32) -> Result<(), PoolError> {
         // disable borrowing or auction cancellation for any non-active pool and disable supplying for any frozen pool
         if (self.config.status > 1 && (action_type == 4 || action_type == 9))
             || (self.config.status > 3 && (action_type == 2 || action_type == 0))
         {
-            panic_with_error!(e, PoolError::InvalidPoolStatus);
+            return Err(PoolError::InvalidPoolStatus);
+        }
+        Ok(())
+    }
+
+    /// Require that the action does not violate the pool status, or panic.
+    ///
+    /// ### Arguments
+    /// * `action_type` - The type of action being performed
+    pub fn require_action_allowed(&self, e: &Env, action_type: u32) {
+        if let Err(error) = self.check_action_allowed(action_type) {
+            panic_with_error!(e, error);
         }
     }
 
+    /// Get the number of effective positions (impacts health factor) the user holds, ignoring
+    /// positions in retired reserves.
+    ///
+    /// ### Arguments
+    /// * `positions` - The user's positions
+    pub fn count_positions(&self, positions: &Positions) -> u32 {
+        let mut count = positions.effective_count();
+        for index in self.retired.iter() {
+            if positions.collateral.contains_key(index) {
+                count -= 1;
+            }
+            if positions.liabilities.contains_key(index) {
+                count -= 1;
+            }
+        }
+        count
+    }
+
     /// Require that a position does not violate the maximum number of positions, or panic.
     ///
     /// ### Arguments
@@ -94,12 +150,32 @@ impl Pool {
     /// If the user has more positions than the maximum allowed and they are not
     /// decreasing their number of positions
     pub fn require_under_max(&self, e: &Env, positions: &Positions, previous_num: u32) {
-        let new_num = positions.effective_count();
-        if new_num > previous_num && self.config.max_positions < new_num {
-            // panic_with_error!(e, PoolError::MaxPositionsExceeded) // note: no need to panic here, we don't execute unsuccessful transactions in catchups.
+        if let Err(error) = self.check_under_max(e, positions, previous_num) {
+            panic_with_error!(e, error)
         }
     }
 
+    /// Check that a position does not violate the maximum number of positions
+    ///
+    /// ### Arguments
+    /// * `positions` - The user's positions
+    /// * `previous_num` - The number of positions the user previously had
+    pub fn check_under_max(
+        &self,
+        e: &Env,
+        positions: &Positions,
+        previous_num: u32,
+    ) -> Result<(), PoolError> {
+        let new_num = self.count_positions(positions);
+        if new_num > previous_num
+            && self.config.max_positions < new_num
+            && !catchup::relax(e, Relaxed::MaxPositions)
+        {
+            return Err(PoolError::MaxPositionsExceeded);
+        }
+        Ok(())
+    }
+
     /// Load the decimals of the prices for the Pool's oracle. Returns a cached version if one
     /// already exists.
     pub fn load_price_decimals(&mut self, e: &Env) -> u32 {
@@ -112,33 +188,149 @@ impl Pool {
         decimals
     }
 
-    /// Load a price from the Pool's oracle. Returns a cached version if one already exists.
+    /// Load a price for an asset. Returns a cached version if one already exists.
+    ///
+    /// If the reserve's price config lists oracles, the price is the median of the fresh prices
+    /// they report. Oracles that fail or report a stale price are skipped, so a secondary oracle
+    /// takes over if the primary is down. Otherwise, the Pool's oracle is used.
     ///
     /// ### Arguments
     /// * asset - The address of the underlying asset
     ///
     /// ### Panics
-    /// If the price is stale
+    /// If no fresh price is available, the oracle quorum is not met, or the oracle prices
+    /// deviate by more than allowed
     pub fn load_price(&mut self, e: &Env, asset: &Address) -> i128 {
         if let Some(price) = self.prices.get(asset.clone()) {
             return price;
         }
-        let oracle_client = PriceFeedClient::new(e, &self.config.oracle);
-        let oracle_asset = Asset::Stellar(asset.clone());
-
-        /*let price_data = reflector_oracle::get_token_amount_in_usdc_value(
-            e,
-            &self.config.oracle,
-            &TokenClient::new(&e, &asset),
-            1,
-        );*/
-        let price_data = oracle_client.lastprice(&oracle_asset).unwrap_optimized();
-
-        //if price_data.timestamp + 24 * 60 * 60 < e.ledger().timestamp() {
-        //    panic_with_error!(e, PoolError::StalePrice);
-        //}
-        self.prices.set(asset.clone(), price_data.price);
-        price_data.price
+        let price_config = storage::get_res_price_config(e, asset);
+        let max_age = self.load_max_price_age(&price_config);
+
+        let price = match price_config {
+            Some(price_config) if !price_config.oracles.is_empty() => {
+                let mut prices: Vec<i128> = vec![e];
+                for oracle in price_config.oracles.iter() {
+                    if let Some(price) = price::load_fresh_price(e, &oracle, asset, max_age) {
+                        prices.push_back(price);
+                    }
+                }
+                price::aggregate_prices(
+                    e,
+                    &prices,
+                    price_config.min_quorum,
+                    price_config.max_deviation,
+                )
+            }
+            _ => price::load_fresh_price(e, &self.config.oracle, asset, max_age)
+                .unwrap_or_else(|| panic_with_error!(e, PoolError::StalePrice)),
+        };
+        self.prices.set(asset.clone(), price);
+        price
+    }
+
+    /// Load the price to value an asset with as collateral.
+    ///
+    /// Reserves that opt into TWAP pricing for the health check's mode are valued at the lower of
+    /// the spot price and the TWAP for borrow checks, and at the higher for liquidation checks.
+    /// Otherwise, the spot price is used.
+    ///
+    /// ### Arguments
+    /// * asset - The address of the underlying asset
+    /// * mode - The health check the price is loaded for
+    ///
+    /// ### Panics
+    /// If the spot price or an opted into TWAP cannot be loaded
+    pub fn load_collateral_price(&mut self, e: &Env, asset: &Address, mode: PriceMode) -> i128 {
+        let spot = self.load_price(e, asset);
+        match (mode, self.load_twap(e, asset, mode)) {
+            (PriceMode::Borrow, Some(twap)) => spot.min(twap),
+            (PriceMode::Liquidation, Some(twap)) => spot.max(twap),
+            (_, None) => spot,
+        }
+    }
+
+    /// Load the price to value an asset with as a liability.
+    ///
+    /// Reserves that opt into TWAP pricing for the health check's mode are valued at the higher of
+    /// the spot price and the TWAP for borrow checks, and at the lower for liquidation checks.
+    /// Otherwise, the spot price is used.
+    ///
+    /// ### Arguments
+    /// * asset - The address of the underlying asset
+    /// * mode - The health check the price is loaded for
+    ///
+    /// ### Panics
+    /// If the spot price or an opted into TWAP cannot be loaded
+    pub fn load_liability_price(&mut self, e: &Env, asset: &Address, mode: PriceMode) -> i128 {
+        let spot = self.load_price(e, asset);
+        match (mode, self.load_twap(e, asset, mode)) {
+            (PriceMode::Borrow, Some(twap)) => spot.max(twap),
+            (PriceMode::Liquidation, Some(twap)) => spot.min(twap),
+            (_, None) => spot,
+        }
+    }
+
+    /// Load the TWAP for an asset if the reserve opts into TWAP pricing for the given mode.
+    /// Returns a cached version if one already exists.
+    ///
+    /// The TWAP is aggregated across the reserve's oracles the same way as the spot price, using
+    /// only records within the max price age.
+    ///
+    /// ### Arguments
+    /// * asset - The address of the underlying asset
+    /// * mode - The health check the TWAP is loaded for
+    ///
+    /// ### Panics
+    /// If no fresh TWAP is available, the oracle quorum is not met, or the TWAPs deviate by more
+    /// than allowed
+    fn load_twap(&mut self, e: &Env, asset: &Address, mode: PriceMode) -> Option<i128> {
+        let price_config = storage::get_res_price_config(e, asset);
+        let max_age = self.load_max_price_age(&price_config);
+        let price_config = price_config?;
+        let enabled = match mode {
+            PriceMode::Borrow => price_config.twap_borrow,
+            PriceMode::Liquidation => price_config.twap_liquidation,
+        };
+        if !enabled || price_config.twap_records == 0 {
+            return None;
+        }
+        if let Some(twap) = self.twaps.get(asset.clone()) {
+            return Some(twap);
+        }
+
+        let (oracles, min_quorum, max_deviation) = if price_config.oracles.is_empty() {
+            (vec![e, self.config.oracle.clone()], 1, 0)
+        } else {
+            (
+                price_config.oracles,
+                price_config.min_quorum,
+                price_config.max_deviation,
+            )
+        };
+        let mut twaps: Vec<i128> = vec![e];
+        for oracle in oracles.iter() {
+            if let Some(twap) =
+                price::load_twap(e, &oracle, asset, price_config.twap_records, max_age)
+            {
+                twaps.push_back(twap);
+            }
+        }
+        let twap = price::aggregate_prices(e, &twaps, min_quorum, max_deviation);
+        self.twaps.set(asset.clone(), twap);
+        Some(twap)
+    }
+
+    /// Load the maximum age of a price for an asset. Uses the reserve's price config if one
+    /// is set, otherwise the pool's max price age.
+    ///
+    /// ### Arguments
+    /// * price_config - The price config for the reserve, if any
+    fn load_max_price_age(&self, price_config: &Option<ReservePriceConfig>) -> u64 {
+        match price_config {
+            Some(price_config) if price_config.max_age > 0 => price_config.max_age,
+            _ => self.config.max_price_age,
+        }
     }
 }
 
@@ -147,10 +339,14 @@ mod tests {
     use sep_40_oracle::testutils::Asset;
     use soroban_sdk::{
         testutils::{Address as _, Ledger, LedgerInfo},
-        Symbol,
+        vec, Symbol,
     };
 
-    use crate::{pool::User, storage::ReserveData, testutils};
+    use crate::{
+        pool::User,
+        storage::{ReserveData, ReservePriceConfig},
+        testutils,
+    };
 
     use super::*;
 
@@ -183,6 +379,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -202,6 +401,7 @@ mod tests {
                     d_supply: 0,
                     last_time: 0,
                     backstop_credit: 0,
+                    flags: 0,
                 },
             );
 
@@ -254,6 +454,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -285,6 +488,7 @@ mod tests {
                     d_supply: 0,
                     last_time: 0,
                     backstop_credit: 0,
+                    flags: 0,
                 },
             );
 
@@ -342,6 +546,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -371,6 +578,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 2,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -391,6 +601,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 1,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -412,6 +625,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 2,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -432,6 +648,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 1,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -453,6 +672,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 4,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -474,6 +696,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 4,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -494,6 +719,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 4,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -526,6 +754,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -565,6 +796,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -617,9 +851,72 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            let mut pool = Pool::load(&e);
+
+            pool.load_price(&e, &asset);
+            assert!(false);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1210)")]
+    fn test_load_price_panics_if_stale_for_reserve_max_age() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 60 * 60 + 1,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, Asset::Stellar(asset.clone())],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 123], &1000);
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(
+                &e,
+                &asset,
+                &ReservePriceConfig {
+                    max_age: 60 * 60,
+                    oracles: vec![&e],
+                    min_quorum: 1,
+                    max_deviation: 0,
+                    twap_records: 0,
+                    twap_borrow: false,
+                    twap_liquidation: false,
+                },
+            );
             let mut pool = Pool::load(&e);
 
             pool.load_price(&e, &asset);
@@ -627,6 +924,489 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_load_price_reserve_max_age_overrides_pool() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 60 * 60,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset_0 = Address::generate(&e);
+        let asset_1 = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(asset_0.clone()),
+                Asset::Stellar(asset_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 123, 456], &1000);
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 60,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(
+                &e,
+                &asset_0,
+                &ReservePriceConfig {
+                    max_age: 60 * 60,
+                    oracles: vec![&e],
+                    min_quorum: 1,
+                    max_deviation: 0,
+                    twap_records: 0,
+                    twap_borrow: false,
+                    twap_liquidation: false,
+                },
+            );
+            let mut pool = Pool::load(&e);
+
+            let price = pool.load_price(&e, &asset_0);
+            assert_eq!(price, 123);
+
+            // a zero max age falls back to the pool's max price age
+            let price_config = ReservePriceConfig {
+                max_age: 0,
+                oracles: vec![&e],
+                min_quorum: 1,
+                max_deviation: 0,
+                twap_records: 0,
+                twap_borrow: false,
+                twap_liquidation: false,
+            };
+            assert_eq!(pool.load_max_price_age(&Some(price_config)), 60);
+            assert_eq!(pool.load_max_price_age(&None), 60);
+        });
+    }
+
+    #[test]
+    fn test_load_price_aggregates_reserve_oracles() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle_0, oracle_client_0) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_client_1) = testutils::create_mock_oracle(&e);
+        let (oracle_2, oracle_client_2) = testutils::create_mock_oracle(&e);
+        for oracle_client in [&oracle_client_0, &oracle_client_1, &oracle_client_2] {
+            oracle_client.set_data(
+                &bombadil,
+                &Asset::Other(Symbol::new(&e, "USD")),
+                &vec![&e, Asset::Stellar(asset.clone())],
+                &7,
+                &300,
+            );
+        }
+        oracle_client_0.set_price(&vec![&e, 1_0100000], &1000);
+        oracle_client_1.set_price(&vec![&e, 1_0000000], &1000);
+        // stale price is skipped
+        oracle_client_2.set_price(&vec![&e, 2_0000000], &900);
+        let pool_config = PoolConfig {
+            oracle: oracle_0.clone(),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 60,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(
+                &e,
+                &asset,
+                &ReservePriceConfig {
+                    max_age: 0,
+                    oracles: vec![&e, oracle_2, oracle_0, oracle_1],
+                    min_quorum: 2,
+                    max_deviation: 0_0100000,
+                    twap_records: 0,
+                    twap_borrow: false,
+                    twap_liquidation: false,
+                },
+            );
+            let mut pool = Pool::load(&e);
+
+            let price = pool.load_price(&e, &asset);
+            assert_eq!(price, 1_0050000);
+        });
+    }
+
+    #[test]
+    fn test_load_price_falls_back_to_secondary_oracle() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle_0, oracle_client_0) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_client_1) = testutils::create_mock_oracle(&e);
+        for oracle_client in [&oracle_client_0, &oracle_client_1] {
+            oracle_client.set_data(
+                &bombadil,
+                &Asset::Other(Symbol::new(&e, "USD")),
+                &vec![&e, Asset::Stellar(asset.clone())],
+                &7,
+                &300,
+            );
+        }
+        // primary oracle has no price for the asset
+        oracle_client_1.set_price(&vec![&e, 1_0000000], &1000);
+        let pool_config = PoolConfig {
+            oracle: oracle_0.clone(),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 60,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(
+                &e,
+                &asset,
+                &ReservePriceConfig {
+                    max_age: 0,
+                    oracles: vec![&e, oracle_0, oracle_1],
+                    min_quorum: 1,
+                    max_deviation: 0_0100000,
+                    twap_records: 0,
+                    twap_borrow: false,
+                    twap_liquidation: false,
+                },
+            );
+            let mut pool = Pool::load(&e);
+
+            let price = pool.load_price(&e, &asset);
+            assert_eq!(price, 1_0000000);
+        });
+    }
+
+    #[test]
+    fn test_load_collateral_and_liability_price_with_twap() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset_0 = Address::generate(&e);
+        let asset_1 = Address::generate(&e);
+        let asset_2 = Address::generate(&e);
+        let asset_3 = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(asset_0.clone()),
+                Asset::Stellar(asset_1.clone()),
+                Asset::Stellar(asset_2.clone()),
+                Asset::Stellar(asset_3.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 3_0000000, 3_0000000, 3_0000000, 3_0000000], &1000);
+        oracle_client.set_price(&vec![&e, 2_0000000, 2_0000000, 2_0000000, 2_0000000], &1300);
+        oracle_client.set_price(&vec![&e, 1_0000000, 1_0000000, 1_0000000, 1_0000000], &1600);
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 600,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let price_config = ReservePriceConfig {
+            max_age: 0,
+            oracles: vec![&e],
+            min_quorum: 1,
+            max_deviation: 0,
+            twap_records: 3,
+            twap_borrow: true,
+            twap_liquidation: false,
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(&e, &asset_0, &price_config);
+            storage::set_res_price_config(
+                &e,
+                &asset_1,
+                &ReservePriceConfig {
+                    twap_borrow: false,
+                    twap_liquidation: true,
+                    ..price_config.clone()
+                },
+            );
+            // the record at 1000 is stale
+            storage::set_res_price_config(
+                &e,
+                &asset_2,
+                &ReservePriceConfig {
+                    max_age: 300,
+                    twap_borrow: true,
+                    twap_liquidation: true,
+                    ..price_config.clone()
+                },
+            );
+            let mut pool = Pool::load(&e);
+
+            // TWAP of 2.5 against a spot of 1
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_0, PriceMode::Borrow),
+                1_0000000
+            );
+            assert_eq!(
+                pool.load_liability_price(&e, &asset_0, PriceMode::Borrow),
+                2_5000000
+            );
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_0, PriceMode::Liquidation),
+                1_0000000
+            );
+            assert_eq!(
+                pool.load_liability_price(&e, &asset_0, PriceMode::Liquidation),
+                1_0000000
+            );
+
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_1, PriceMode::Borrow),
+                1_0000000
+            );
+            assert_eq!(
+                pool.load_liability_price(&e, &asset_1, PriceMode::Borrow),
+                1_0000000
+            );
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_1, PriceMode::Liquidation),
+                2_5000000
+            );
+            assert_eq!(
+                pool.load_liability_price(&e, &asset_1, PriceMode::Liquidation),
+                1_0000000
+            );
+
+            // TWAP of 2 over the fresh records
+            assert_eq!(
+                pool.load_liability_price(&e, &asset_2, PriceMode::Borrow),
+                2_0000000
+            );
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_2, PriceMode::Liquidation),
+                2_0000000
+            );
+
+            // reserves without a price config use the spot price
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_3, PriceMode::Borrow),
+                1_0000000
+            );
+            assert_eq!(
+                pool.load_collateral_price(&e, &asset_3, PriceMode::Liquidation),
+                1_0000000
+            );
+        });
+    }
+
+    #[test]
+    fn test_load_twap_aggregates_reserve_oracles() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle_0, oracle_0_client) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_1_client) = testutils::create_mock_oracle(&e);
+        let (oracle_2, oracle_2_client) = testutils::create_mock_oracle(&e);
+        for (client, prices) in [
+            (&oracle_0_client, [3_0000000, 1_0000000]),
+            (&oracle_1_client, [3_1000000, 1_0000000]),
+            (&oracle_2_client, [2_9000000, 1_0000000]),
+        ] {
+            client.set_data(
+                &bombadil,
+                &Asset::Other(Symbol::new(&e, "USD")),
+                &vec![&e, Asset::Stellar(asset.clone())],
+                &7,
+                &300,
+            );
+            client.set_price(&vec![&e, prices[0]], &1000);
+            client.set_price(&vec![&e, prices[1]], &1300);
+        }
+        let pool_config = PoolConfig {
+            oracle: oracle_0.clone(),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 600,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(
+                &e,
+                &asset,
+                &ReservePriceConfig {
+                    max_age: 0,
+                    oracles: vec![&e, oracle_0, oracle_1, oracle_2],
+                    min_quorum: 3,
+                    max_deviation: 0_0500000,
+                    twap_records: 2,
+                    twap_borrow: true,
+                    twap_liquidation: true,
+                },
+            );
+            let mut pool = Pool::load(&e);
+
+            // median of the 2, 2.05 and 1.95 TWAPs
+            assert_eq!(
+                pool.load_liability_price(&e, &asset, PriceMode::Borrow),
+                2_0000000
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1220)")]
+    fn test_load_twap_quorum_not_met() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle_0, oracle_0_client) = testutils::create_mock_oracle(&e);
+        let (oracle_1, oracle_1_client) = testutils::create_mock_oracle(&e);
+        for client in [&oracle_0_client, &oracle_1_client] {
+            client.set_data(
+                &bombadil,
+                &Asset::Other(Symbol::new(&e, "USD")),
+                &vec![&e, Asset::Stellar(asset.clone())],
+                &7,
+                &300,
+            );
+        }
+        oracle_0_client.set_price(&vec![&e, 3_0000000], &1000);
+        oracle_0_client.set_price(&vec![&e, 1_0000000], &1300);
+        // the second oracle's TWAP is too stale to count towards the quorum
+        oracle_1_client.set_price(&vec![&e, 3_0000000], &200);
+        let pool_config = PoolConfig {
+            oracle: oracle_0.clone(),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 600,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_res_price_config(
+                &e,
+                &asset,
+                &ReservePriceConfig {
+                    max_age: 0,
+                    oracles: vec![&e, oracle_0, oracle_1],
+                    min_quorum: 2,
+                    max_deviation: 0,
+                    twap_records: 2,
+                    twap_borrow: true,
+                    twap_liquidation: true,
+                },
+            );
+            let mut pool = Pool::load(&e);
+            // cache the spot price so only the TWAP quorum is checked
+            pool.prices.set(asset.clone(), 1_0000000);
+
+            pool.load_liability_price(&e, &asset, PriceMode::Borrow);
+        });
+    }
+
     #[test]
     fn test_require_under_max_empty() {
         let e = Env::default();
@@ -644,6 +1424,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -676,6 +1459,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -691,6 +1477,46 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_require_under_max_ignores_retired() {
+        let e = Env::default();
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let mut reserve_0 = testutils::default_reserve(&e);
+        let mut reserve_1 = testutils::default_reserve(&e);
+        reserve_1.index = 1;
+
+        let (oracle, _) = testutils::create_mock_oracle(&e);
+        let mut user = User {
+            address: samwise.clone(),
+            positions: Positions::env_default(&e),
+        };
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_retired_reserves(&e, &vec![&e, 0]);
+            user.add_collateral(&e, &mut reserve_0, 42);
+            user.add_liabilities(&e, &mut reserve_0, 2);
+            user.add_collateral(&e, &mut reserve_1, 42);
+            let pool = Pool::load(&e);
+            let prev_positions = pool.count_positions(&user.positions);
+            assert_eq!(prev_positions, 1);
+
+            user.add_liabilities(&e, &mut reserve_1, 2);
+
+            pool.require_under_max(&e, &user.positions, prev_positions);
+        });
+    }
+
     #[test]
     fn test_require_under_max_allows_decreasing_change() {
         let e = Env::default();
@@ -711,6 +1537,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -728,6 +1557,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "catchup"))]
     #[should_panic(expected = "Error(Contract, #1208)")]
     fn test_require_under_max_panics_if_over() {
         let e = Env::default();
@@ -748,6 +1578,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -0,0 +1,428 @@
+use soroban_sdk::{contracttype, panic_with_error, Address, Env, Map, Vec};
+
+use crate::{errors::PoolError, EModeCategory};
+
+use super::{
+    actions::{apply_request, Actions, Request, RequestChecks},
+    emode::load_user_emode,
+    health_factor::PositionData,
+    isolation::check_isolation,
+    pool::{Pool, PriceMode},
+    Positions, User,
+};
+
+/// The outcome of submitting a set of requests to the pool, without any transfers or
+/// position changes being stored
+#[derive(Clone)]
+#[contracttype]
+pub struct SubmitPreview {
+    pub positions: Positions, // the user's positions after the requests that succeed
+    pub spender_transfer: Map<Address, i128>, // the tokens sent from the spender to the pool
+    pub pool_transfer: Map<Address, i128>, // the tokens sent from the pool to "to"
+    pub health_factor: i128,  // the resulting health factor, or i128::MAX if no liabilities
+    pub failed_request: Option<u32>, // the index of the first request that fails
+    pub error: u32,           // the error code of the failed request, or 0 if none failed
+}
+
+/// Preview submitting a set of requests for a user against the pool. Requests are processed in
+/// order, the same way as `execute_submit`, until one fails, and the returned positions and
+/// transfers are those of the requests before it.
+///
+/// Checks that are done once all requests are processed, like the health factor, max positions
+/// and isolation mode, report the last request as the failed request. The health factor is
+/// checked even in catch-up mode.
+///
+/// Nothing is stored and no tokens are transferred, including when filling auctions. Emissions
+/// are still accrued to the current ledger for the users involved, as any action would.
+///
+/// ### Arguments
+/// * from - The address of the user whose positions are being modified
+/// * requests - A vec of requests to be processed
+///
+/// ### Panics
+/// If `from` is the pool, a fill cannot be applied to the positions of the user being filled, or
+/// a price cannot be loaded for a position while calculating the health factor
+pub fn preview_submit(e: &Env, from: &Address, requests: &Vec<Request>) -> SubmitPreview {
+    if from == &e.current_contract_address() {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    let mut pool = Pool::load_preview(e);
+    let mut from_state = User::load(e, from);
+    let mut actions = Actions::new(e);
+    let emode = load_user_emode(e, from);
+    let prev_positions_count = pool.count_positions(&from_state.positions);
+    let mut checks = RequestChecks::default();
+
+    let mut failure: Option<(u32, PoolError)> = None;
+    for (index, request) in requests.iter().enumerate() {
+        // a failed request can leave the user and the transfers partially updated
+        let prev_from_state = from_state.clone();
+        let prev_actions = actions.clone();
+        if let Err(error) = apply_request(
+            e,
+            &mut pool,
+            &mut from_state,
+            &emode,
+            &mut actions,
+            &mut checks,
+            &request,
+        ) {
+            from_state = prev_from_state;
+            actions = prev_actions;
+            failure = Some((index as u32, error));
+            break;
+        }
+    }
+
+    if failure.is_none() && !requests.is_empty() {
+        if let Err(error) = check_positions(
+            e,
+            &mut pool,
+            &emode,
+            &from_state,
+            prev_positions_count,
+            &checks,
+        ) {
+            failure = Some((requests.len() - 1, error));
+        }
+    }
+
+    let health_factor = if from_state.has_liabilities() {
+        PositionData::calculate_from_positions(
+            e,
+            &mut pool,
+            &from_state.positions,
+            &emode,
+            PriceMode::Borrow,
+        )
+        .as_health_factor()
+    } else {
+        i128::MAX
+    };
+    let (failed_request, error) = match failure {
+        Some((index, error)) => (Some(index), error as u32),
+        None => (None, 0),
+    };
+    SubmitPreview {
+        positions: from_state.positions,
+        spender_transfer: actions.spender_transfer,
+        pool_transfer: actions.pool_transfer,
+        health_factor,
+        failed_request,
+        error,
+    }
+}
+
+/// Check the positions once all requests are processed, in the same order as `execute_submit`
+fn check_positions(
+    e: &Env,
+    pool: &mut Pool,
+    emode: &Option<EModeCategory>,
+    from_state: &User,
+    prev_positions_count: u32,
+    checks: &RequestChecks,
+) -> Result<(), PoolError> {
+    pool.check_under_max(e, &from_state.positions, prev_positions_count)?;
+    check_isolation(
+        e,
+        &from_state.address,
+        pool,
+        &from_state.positions,
+        checks.check_isolation,
+    )?;
+    if checks.check_health
+        && from_state.has_liabilities()
+        && PositionData::calculate_from_positions(
+            e,
+            pool,
+            &from_state.positions,
+            emode,
+            PriceMode::Borrow,
+        )
+        .is_hf_under(1_0000100)
+    {
+        return Err(PoolError::InvalidHf);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pool::execute_submit,
+        storage::{self, PoolConfig},
+        testutils, AuctionData, AuctionType, RequestType,
+    };
+
+    use super::*;
+    use sep_40_oracle::testutils::Asset;
+    use sep_41_token::testutils::MockTokenClient;
+    use soroban_sdk::{
+        map,
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec, Symbol,
+    };
+
+    /// Create a pool with two reserves priced at 1 and 5
+    fn setup_preview(e: &Env) -> (Address, Address, Address) {
+        e.budget().reset_unlimited();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.ledger().set(LedgerInfo {
+            timestamp: 600,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(e);
+        let pool = testutils::create_pool(e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(e);
+
+        let (underlying_0, _) = testutils::create_token_contract(e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(e, "USD")),
+            &vec![
+                e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![e, 1_0000000, 5_0000000]);
+
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(e, &pool_config);
+        });
+        (pool, underlying_0, underlying_1)
+    }
+
+    #[test]
+    fn test_preview_submit_matches_submit() {
+        let e = Env::default();
+        let (pool, underlying_0, underlying_1) = setup_preview(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+        MockTokenClient::new(&e, &underlying_0).mint(&frodo, &16_0000000);
+
+        let requests = vec![
+            &e,
+            Request {
+                request_type: RequestType::SupplyCollateral as u32,
+                address: underlying_0.clone(),
+                amount: 15_0000000,
+            },
+            Request {
+                request_type: RequestType::Borrow as u32,
+                address: underlying_1.clone(),
+                amount: 1_5000000,
+            },
+        ];
+        e.as_contract(&pool, || {
+            let preview = preview_submit(&e, &samwise, &requests);
+            assert_eq!(preview.failed_request, None);
+            assert_eq!(preview.error, 0);
+            assert_eq!(preview.positions.collateral.get_unchecked(0), 14_9999884);
+            assert_eq!(preview.positions.liabilities.get_unchecked(1), 1_4999983);
+            assert_eq!(
+                preview.spender_transfer.get_unchecked(underlying_0),
+                15_0000000
+            );
+            assert_eq!(preview.pool_transfer.get_unchecked(underlying_1), 1_5000000);
+            assert_eq!(preview.health_factor, 1_1249998);
+
+            // nothing is stored
+            let stored_positions = storage::get_user_positions(&e, &samwise);
+            assert_eq!(stored_positions.collateral.len(), 0);
+            assert_eq!(stored_positions.liabilities.len(), 0);
+
+            let positions = execute_submit(&e, &samwise, &frodo, &frodo, requests);
+            assert_eq!(positions.collateral, preview.positions.collateral);
+            assert_eq!(positions.liabilities, preview.positions.liabilities);
+        });
+    }
+
+    #[test]
+    fn test_preview_submit_reports_first_failed_request() {
+        let e = Env::default();
+        let (pool, underlying_0, underlying_1) = setup_preview(&e);
+        let samwise = Address::generate(&e);
+
+        let requests = vec![
+            &e,
+            Request {
+                request_type: RequestType::SupplyCollateral as u32,
+                address: underlying_0.clone(),
+                amount: 15_0000000,
+            },
+            Request {
+                request_type: RequestType::Borrow as u32,
+                address: underlying_1.clone(),
+                amount: -1,
+            },
+            Request {
+                request_type: RequestType::Borrow as u32,
+                address: Address::generate(&e),
+                amount: 1_0000000,
+            },
+        ];
+        e.as_contract(&pool, || {
+            let preview = preview_submit(&e, &samwise, &requests);
+            assert_eq!(preview.failed_request, Some(1));
+            assert_eq!(preview.error, PoolError::NegativeAmountError as u32);
+            assert_eq!(preview.positions.collateral.get_unchecked(0), 14_9999884);
+            assert_eq!(preview.positions.liabilities.len(), 0);
+            assert_eq!(preview.spender_transfer.len(), 1);
+            assert_eq!(preview.pool_transfer.len(), 0);
+            assert_eq!(preview.health_factor, i128::MAX);
+        });
+    }
+
+    #[test]
+    fn test_preview_submit_reports_invalid_hf() {
+        let e = Env::default();
+        let (pool, underlying_0, underlying_1) = setup_preview(&e);
+        let samwise = Address::generate(&e);
+
+        let requests = vec![
+            &e,
+            Request {
+                request_type: RequestType::SupplyCollateral as u32,
+                address: underlying_0.clone(),
+                amount: 15_0000000,
+            },
+            Request {
+                request_type: RequestType::Borrow as u32,
+                address: underlying_1.clone(),
+                amount: 2_5000000,
+            },
+            Request {
+                request_type: RequestType::Repay as u32,
+                address: underlying_1.clone(),
+                amount: 0_1000000,
+            },
+        ];
+        e.as_contract(&pool, || {
+            let preview = preview_submit(&e, &samwise, &requests);
+            assert_eq!(preview.failed_request, Some(2));
+            assert_eq!(preview.error, PoolError::InvalidHf as u32);
+            assert_eq!(preview.positions.liabilities.get_unchecked(1), 2_3999974);
+            assert!(preview.health_factor < 1_0000000);
+        });
+    }
+
+    #[test]
+    fn test_preview_submit_fill_matches_submit() {
+        let e = Env::default();
+        let (pool, underlying_0, underlying_1) = setup_preview(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+        MockTokenClient::new(&e, &underlying_0).mint(&frodo, &20_0000000);
+
+        let samwise_positions = Positions {
+            collateral: map![&e, (0, 20_0000000)],
+            liabilities: map![&e, (1, 3_0000000)],
+            supply: map![&e],
+        };
+        let auction_data = AuctionData {
+            bid: map![&e, (underlying_1.clone(), 1_5000000)],
+            lot: map![&e, (underlying_0.clone(), 10_0000000)],
+            block: 1034,
+            curve: Default::default(),
+        };
+        let requests = vec![
+            &e,
+            Request {
+                request_type: RequestType::SupplyCollateral as u32,
+                address: underlying_0.clone(),
+                amount: 20_0000000,
+            },
+            Request {
+                request_type: RequestType::FillUserLiquidationAuction as u32,
+                address: samwise.clone(),
+                amount: 100,
+            },
+        ];
+        e.as_contract(&pool, || {
+            storage::set_user_positions(&e, &samwise, &samwise_positions);
+            storage::set_auction(
+                &e,
+                &(AuctionType::UserLiquidation as u32),
+                &samwise,
+                &auction_data,
+            );
+
+            let preview = preview_submit(&e, &frodo, &requests);
+            assert_eq!(preview.failed_request, None);
+            assert_eq!(preview.error, 0);
+            assert_eq!(preview.positions.collateral.get_unchecked(0), 29_9999845);
+            assert_eq!(preview.positions.liabilities.get_unchecked(1), 1_5000000);
+            assert_eq!(
+                preview.spender_transfer.get_unchecked(underlying_0.clone()),
+                20_0000000
+            );
+            assert_eq!(preview.pool_transfer.len(), 0);
+
+            // nothing is stored
+            let stored_auction =
+                storage::get_auction(&e, &(AuctionType::UserLiquidation as u32), &samwise);
+            assert_eq!(stored_auction.bid, auction_data.bid);
+            assert_eq!(stored_auction.lot, auction_data.lot);
+            let stored_positions = storage::get_user_positions(&e, &samwise);
+            assert_eq!(stored_positions.collateral, samwise_positions.collateral);
+            assert_eq!(stored_positions.liabilities, samwise_positions.liabilities);
+            assert_eq!(storage::get_user_positions(&e, &frodo).collateral.len(), 0);
+
+            let positions = execute_submit(&e, &frodo, &frodo, &frodo, requests);
+            assert_eq!(positions.collateral, preview.positions.collateral);
+            assert_eq!(positions.liabilities, preview.positions.liabilities);
+            assert!(!storage::has_auction(
+                &e,
+                &(AuctionType::UserLiquidation as u32),
+                &samwise
+            ));
+        });
+    }
+
+    #[test]
+    fn test_preview_submit_fill_without_auction_is_bad_request() {
+        let e = Env::default();
+        let (pool, _, _) = setup_preview(&e);
+        let samwise = Address::generate(&e);
+
+        let requests = vec![
+            &e,
+            Request {
+                request_type: RequestType::FillUserLiquidationAuction as u32,
+                address: Address::generate(&e),
+                amount: 50,
+            },
+        ];
+        e.as_contract(&pool, || {
+            let preview = preview_submit(&e, &samwise, &requests);
+            assert_eq!(preview.failed_request, Some(0));
+            assert_eq!(preview.error, PoolError::BadRequest as u32);
+        });
+    }
+}
@@ -0,0 +1,266 @@
+use sep_40_oracle::{Asset, PriceData, PriceFeedClient};
+use soroban_sdk::{panic_with_error, vec, Address, Env, Vec};
+
+use crate::{constants::SCALAR_7, errors::PoolError};
+
+/// Fetch the latest price for an asset from a SEP-40 oracle.
+///
+/// Returns None if the oracle call fails, the oracle has no price for the asset, or the
+/// price is older than `max_age` seconds.
+///
+/// ### Arguments
+/// * `oracle` - The address of the oracle
+/// * `asset` - The address of the underlying asset
+/// * `max_age` - The maximum age of the price in seconds
+pub fn load_fresh_price(e: &Env, oracle: &Address, asset: &Address, max_age: u64) -> Option<i128> {
+    let oracle_client = PriceFeedClient::new(e, oracle);
+    match oracle_client.try_lastprice(&Asset::Stellar(asset.clone())) {
+        Ok(Ok(Some(price_data))) => {
+            if price_data.timestamp + max_age < e.ledger().timestamp() {
+                None
+            } else {
+                Some(price_data.price)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Fetch the time-weighted average price for an asset over the last `records` records of a
+/// SEP-40 oracle. Each record is weighted by the time until the next record, and the newest
+/// record by the time until the current ledger timestamp. Records older than `max_age` seconds
+/// are skipped.
+///
+/// Returns None if the oracle call fails or the oracle has no fresh records for the asset.
+///
+/// ### Arguments
+/// * `oracle` - The address of the oracle
+/// * `asset` - The address of the underlying asset
+/// * `records` - The number of records to average
+/// * `max_age` - The maximum age of a record in seconds
+pub fn load_twap(
+    e: &Env,
+    oracle: &Address,
+    asset: &Address,
+    records: u32,
+    max_age: u64,
+) -> Option<i128> {
+    let oracle_client = PriceFeedClient::new(e, oracle);
+    let history = match oracle_client.try_prices(&Asset::Stellar(asset.clone()), &records) {
+        Ok(Ok(Some(history))) => history,
+        _ => return None,
+    };
+
+    // order records from oldest to newest - the number of records is bounded by `MAX_TWAP_RECORDS`
+    let mut sorted: Vec<PriceData> = vec![e];
+    for record in history.iter() {
+        if record.timestamp + max_age < e.ledger().timestamp() {
+            continue;
+        }
+        let mut index = sorted.len();
+        while index > 0 && sorted.get_unchecked(index - 1).timestamp > record.timestamp {
+            index -= 1;
+        }
+        sorted.insert(index, record);
+    }
+
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let mut weighted_sum: i128 = 0;
+    let mut total_time: i128 = 0;
+    for i in 0..sorted.len() {
+        let record = sorted.get_unchecked(i);
+        let end = if i + 1 < sorted.len() {
+            sorted.get_unchecked(i + 1).timestamp
+        } else {
+            e.ledger().timestamp().max(record.timestamp)
+        };
+        let elapsed = i128::from(end - record.timestamp);
+        weighted_sum += record.price * elapsed;
+        total_time += elapsed;
+    }
+    if total_time == 0 {
+        return Some(sorted.get_unchecked(sorted.len() - 1).price);
+    }
+    Some(weighted_sum / total_time)
+}
+
+/// Aggregate a set of fresh prices into a single price by taking the median.
+///
+/// ### Arguments
+/// * `prices` - The fresh prices, in any order
+/// * `min_quorum` - The minimum number of prices required
+/// * `max_deviation` - The maximum deviation of any price from the median expressed in 7
+///                     decimals, or 0 for no bound
+///
+/// ### Panics
+/// If there are no prices, fewer than `min_quorum` prices, or any price deviates from the
+/// median by more than `max_deviation`
+pub fn aggregate_prices(e: &Env, prices: &Vec<i128>, min_quorum: u32, max_deviation: u32) -> i128 {
+    if prices.is_empty() {
+        panic_with_error!(e, PoolError::StalePrice);
+    }
+    if prices.len() < min_quorum {
+        panic_with_error!(e, PoolError::OracleQuorumNotMet);
+    }
+
+    // insertion sort - the number of prices is bounded by `MAX_RESERVE_ORACLES`
+    let mut sorted: Vec<i128> = vec![e];
+    for price in prices.iter() {
+        let mut index = sorted.len();
+        while index > 0 && sorted.get_unchecked(index - 1) > price {
+            index -= 1;
+        }
+        sorted.insert(index, price);
+    }
+    let mid = sorted.len() / 2;
+    let median = if sorted.len() % 2 == 0 {
+        (sorted.get_unchecked(mid - 1) + sorted.get_unchecked(mid)) / 2
+    } else {
+        sorted.get_unchecked(mid)
+    };
+
+    if max_deviation > 0 {
+        for price in sorted.iter() {
+            let diff = (price - median).abs();
+            if diff * SCALAR_7 > median * i128::from(max_deviation) {
+                panic_with_error!(e, PoolError::PriceDeviationTooLarge);
+            }
+        }
+    }
+    median
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils;
+    use sep_40_oracle::testutils::Asset as MockAsset;
+    use soroban_sdk::{
+        testutils::{Address as _, Ledger, LedgerInfo},
+        Symbol,
+    };
+
+    #[test]
+    fn test_load_fresh_price() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 60,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let asset_0 = Address::generate(&e);
+        let asset_1 = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, MockAsset::Stellar(asset_0.clone())],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 1_0000000], &1000);
+
+        assert_eq!(load_fresh_price(&e, &oracle, &asset_0, 60), Some(1_0000000));
+        assert_eq!(load_fresh_price(&e, &oracle, &asset_0, 59), None);
+        // asset unknown to the oracle
+        assert_eq!(load_fresh_price(&e, &oracle, &asset_1, 60), None);
+        // oracle is down
+        let missing_oracle = Address::generate(&e);
+        assert_eq!(load_fresh_price(&e, &missing_oracle, &asset_0, 60), None);
+    }
+
+    #[test]
+    fn test_load_twap() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+        e.ledger().set(LedgerInfo {
+            timestamp: 1000 + 750,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let asset_0 = Address::generate(&e);
+        let asset_1 = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, MockAsset::Stellar(asset_0.clone())],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 1_0000000], &1000);
+        oracle_client.set_price(&vec![&e, 2_0000000], &1300);
+        oracle_client.set_price(&vec![&e, 4_0000000], &1600);
+
+        // (1 * 300 + 2 * 300 + 4 * 150) / 750
+        assert_eq!(load_twap(&e, &oracle, &asset_0, 3, 750), Some(2_0000000));
+        // (2 * 300 + 4 * 150) / 450
+        assert_eq!(load_twap(&e, &oracle, &asset_0, 2, 750), Some(2_6666666));
+        // the oldest record is stale - (2 * 300 + 4 * 150) / 450
+        assert_eq!(load_twap(&e, &oracle, &asset_0, 3, 749), Some(2_6666666));
+        // every record is stale
+        assert_eq!(load_twap(&e, &oracle, &asset_0, 3, 149), None);
+        // asset unknown to the oracle
+        assert_eq!(load_twap(&e, &oracle, &asset_1, 3, 750), None);
+        // oracle is down
+        let missing_oracle = Address::generate(&e);
+        assert_eq!(load_twap(&e, &missing_oracle, &asset_0, 3, 750), None);
+    }
+
+    #[test]
+    fn test_aggregate_prices_median() {
+        let e = Env::default();
+
+        assert_eq!(aggregate_prices(&e, &vec![&e, 1_0000000], 1, 0), 1_0000000);
+        assert_eq!(
+            aggregate_prices(&e, &vec![&e, 1_0100000, 0_9900000, 1_0000000], 2, 0_0200000),
+            1_0000000
+        );
+        assert_eq!(
+            aggregate_prices(&e, &vec![&e, 1_0200000, 1_0000000], 2, 0_0200000),
+            1_0100000
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1210)")]
+    fn test_aggregate_prices_no_prices() {
+        let e = Env::default();
+
+        aggregate_prices(&e, &vec![&e], 1, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1220)")]
+    fn test_aggregate_prices_quorum_not_met() {
+        let e = Env::default();
+
+        aggregate_prices(&e, &vec![&e, 1_0000000, 1_0000000], 3, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1221)")]
+    fn test_aggregate_prices_deviation_too_large() {
+        let e = Env::default();
+
+        aggregate_prices(&e, &vec![&e, 1_0000000, 1_0000000, 1_0300000], 1, 0_0200000);
+    }
+}
@@ -3,12 +3,15 @@ use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::{contracttype, panic_with_error, unwrap::UnwrapOptimized, Address, Env};
 
 use crate::{
-    constants::{SCALAR_7, SCALAR_9},
+    constants::{
+        RESERVE_BORROW_DISABLED, RESERVE_COLLATERAL_DISABLED, RESERVE_FROZEN, RESERVE_SUNSET,
+        RESERVE_SUPPLY_DISABLED, SCALAR_7, SCALAR_9,
+    },
     errors::PoolError,
     storage::{self, PoolConfig, ReserveData},
 };
 
-use super::interest::calc_accrual;
+use super::{interest::calc_accrual, sunset::load_sunset_c_factor};
 
 #[derive(Clone)]
 #[contracttype]
@@ -18,14 +21,21 @@ pub struct Reserve {
     pub l_factor: u32,         // the liability factor for the reserve
     pub c_factor: u32,         // the collateral factor for the reserve
     pub max_util: u32,         // the maximum utilization rate for the reserve
-    pub last_time: u64,        // the last block the data was updated
-    pub scalar: i128,          // scalar used for positions, b/d token supply, and credit
-    pub d_rate: i128,          // the conversion rate from dToken to underlying (9 decimals)
-    pub b_rate: i128,          // the conversion rate from bToken to underlying (9 decimals)
-    pub ir_mod: i128,          // the interest rate curve modifier (9 decimals)
-    pub b_supply: i128,        // the total supply of b tokens
-    pub d_supply: i128,        // the total supply of d tokens
+    pub supply_cap: i128,      // the maximum total supply, or 0 for no cap
+    pub borrow_cap: i128,      // the maximum total liabilities, or 0 for no cap
+    pub flash_fee: u32,        // the fee charged on flash loans (7 decimals)
+    pub isolated: bool,        // if the reserve can only be used as collateral in isolation
+    pub debt_ceiling: i128, // the maximum debt backed by the reserve in isolation in the oracle's base asset, or 0
+    pub isolated_borrow: bool, // if the reserve can be borrowed against isolated collateral
+    pub last_time: u64,     // the last block the data was updated
+    pub scalar: i128,       // scalar used for positions, b/d token supply, and credit
+    pub d_rate: i128,       // the conversion rate from dToken to underlying (9 decimals)
+    pub b_rate: i128,       // the conversion rate from bToken to underlying (9 decimals)
+    pub ir_mod: i128,       // the interest rate curve modifier (9 decimals)
+    pub b_supply: i128,     // the total supply of b tokens
+    pub d_supply: i128,     // the total supply of d tokens
     pub backstop_credit: i128, // the total amount of underlying tokens owed to the backstop
+    pub flags: u32,         // the status flags that disable actions for the reserve
 }
 
 impl Reserve {
@@ -50,6 +60,12 @@ impl Reserve {
             l_factor: reserve_config.l_factor,
             c_factor: reserve_config.c_factor,
             max_util: reserve_config.max_util,
+            supply_cap: reserve_config.supply_cap,
+            borrow_cap: reserve_config.borrow_cap,
+            flash_fee: reserve_config.flash_fee,
+            isolated: reserve_config.isolated,
+            debt_ceiling: reserve_config.debt_ceiling,
+            isolated_borrow: reserve_config.isolated_borrow,
             last_time: reserve_data.last_time,
             scalar: 10i128.pow(reserve_config.decimals),
             d_rate: reserve_data.d_rate,
@@ -58,8 +74,13 @@ impl Reserve {
             b_supply: reserve_data.b_supply,
             d_supply: reserve_data.d_supply,
             backstop_credit: reserve_data.backstop_credit,
+            flags: reserve_data.flags,
         };
 
+        if reserve.flags & RESERVE_SUNSET != 0 {
+            reserve.c_factor = load_sunset_c_factor(e, asset);
+        }
+
         // short circuit if the reserve has already been updated this ledger
         if e.ledger().timestamp() == reserve.last_time {
             return reserve;
@@ -115,16 +136,40 @@ impl Reserve {
 
     /// Store the updated reserve to the ledger.
     pub fn store(&self, e: &Env) {
-        let reserve_data = ReserveData {
+        storage::set_res_data(e, &self.asset, &self.to_data());
+    }
+
+    /// Fetch the reserve's data as it would be stored to the ledger
+    pub fn to_data(&self) -> ReserveData {
+        ReserveData {
             d_rate: self.d_rate,
             b_rate: self.b_rate,
             ir_mod: self.ir_mod,
             b_supply: self.b_supply,
             d_supply: self.d_supply,
             backstop_credit: self.backstop_credit,
+            flags: self.flags,
             last_time: self.last_time,
-        };
-        storage::set_res_data(e, &self.asset, &reserve_data);
+        }
+    }
+
+    /// Credit a fee paid to the reserve to the backstop and the suppliers
+    ///
+    /// ### Arguments
+    /// * `bstop_rate` - The pool's backstop take rate (7 decimals)
+    /// * `fee` - The amount of underlying tokens paid
+    pub fn credit_fee(&mut self, bstop_rate: u32, fee: i128) {
+        let pre_update_supply = self.total_supply();
+        let mut new_backstop_credit: i128 = 0;
+        if bstop_rate > 0 {
+            new_backstop_credit = fee
+                .fixed_mul_floor(i128(bstop_rate), SCALAR_7)
+                .unwrap_optimized();
+            self.backstop_credit += new_backstop_credit;
+        }
+        self.b_rate = (pre_update_supply + fee - new_backstop_credit)
+            .fixed_div_floor(self.b_supply, SCALAR_9)
+            .unwrap_optimized();
     }
 
     /// Fetch the current utilization rate for the reserve normalized to 7 decimals
@@ -134,11 +179,84 @@ impl Reserve {
             .unwrap_optimized()
     }
 
+    /// Require that the action is not disabled by the reserve's status flags, or panic.
+    ///
+    /// A frozen or sunset reserve disables supplying, supplying collateral and borrowing, but
+    /// still allows positions to be withdrawn and repaid.
+    ///
+    /// ### Arguments
+    /// * `action_type` - The type of action being performed
+    pub fn require_action_allowed(&self, e: &Env, action_type: u32) {
+        if let Err(error) = self.check_action_allowed(action_type) {
+            panic_with_error!(e, error)
+        }
+    }
+
+    /// Check that the reserve's flags allow the action
+    ///
+    /// ### Arguments
+    /// * `action_type` - The type of action being performed
+    pub fn check_action_allowed(&self, action_type: u32) -> Result<(), PoolError> {
+        let disabled = match action_type {
+            0 => RESERVE_SUPPLY_DISABLED | RESERVE_FROZEN | RESERVE_SUNSET,
+            2 => {
+                RESERVE_SUPPLY_DISABLED
+                    | RESERVE_COLLATERAL_DISABLED
+                    | RESERVE_FROZEN
+                    | RESERVE_SUNSET
+            }
+            4 => RESERVE_BORROW_DISABLED | RESERVE_FROZEN | RESERVE_SUNSET,
+            _ => 0,
+        };
+        if self.flags & disabled != 0 {
+            return Err(PoolError::ReserveActionDisabled);
+        }
+        Ok(())
+    }
+
     /// Require that the utilization rate is below the maximum allowed, or panic.
     pub fn require_utilization_below_max(&self, e: &Env) {
+        if let Err(error) = self.check_utilization_below_max() {
+            panic_with_error!(e, error)
+        }
+    }
+
+    /// Check that the utilization rate is below the maximum allowed
+    pub fn check_utilization_below_max(&self) -> Result<(), PoolError> {
         if self.utilization() > i128(self.max_util) {
-            panic_with_error!(e, PoolError::InvalidUtilRate)
+            return Err(PoolError::InvalidUtilRate);
+        }
+        Ok(())
+    }
+
+    /// Require that the total supply is at or below the supply cap, or panic.
+    pub fn require_supply_under_cap(&self, e: &Env) {
+        if let Err(error) = self.check_supply_under_cap() {
+            panic_with_error!(e, error)
+        }
+    }
+
+    /// Check that the total supply is at or below the supply cap
+    pub fn check_supply_under_cap(&self) -> Result<(), PoolError> {
+        if self.supply_cap > 0 && self.total_supply() > self.supply_cap {
+            return Err(PoolError::SupplyCapExceeded);
+        }
+        Ok(())
+    }
+
+    /// Require that the total liabilities are at or below the borrow cap, or panic.
+    pub fn require_borrow_under_cap(&self, e: &Env) {
+        if let Err(error) = self.check_borrow_under_cap() {
+            panic_with_error!(e, error)
+        }
+    }
+
+    /// Check that the total liabilities are at or below the borrow cap
+    pub fn check_borrow_under_cap(&self) -> Result<(), PoolError> {
+        if self.borrow_cap > 0 && self.total_liabilities() > self.borrow_cap {
+            return Err(PoolError::BorrowCapExceeded);
         }
+        Ok(())
     }
 
     /// Fetch the total liabilities for the reserve in underlying tokens
@@ -277,6 +395,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 5,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -326,6 +447,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -373,6 +497,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -421,6 +548,9 @@ mod tests {
             bstop_rate: 0,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -470,6 +600,9 @@ mod tests {
             bstop_rate: 0_2000000,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -489,6 +622,22 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_credit_fee() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.b_rate = 1_100_000_000;
+        reserve.backstop_credit = 0_1000000;
+
+        reserve.credit_fee(0_2000000, 1_0000000);
+
+        assert_eq!(reserve.backstop_credit, 0_3000000);
+        // 0.8 tokens are credited to the 100 b_tokens
+        assert_eq!(reserve.b_rate, 1_108_000_000);
+        assert_eq!(reserve.d_rate, 1_000_000_000);
+    }
+
     #[test]
     fn test_utilization() {
         let e = Env::default();
@@ -529,6 +678,100 @@ mod tests {
         reserve.require_utilization_below_max(&e);
     }
 
+    #[test]
+    fn test_require_action_allowed() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.flags = RESERVE_COLLATERAL_DISABLED | RESERVE_BORROW_DISABLED;
+        reserve.require_action_allowed(&e, 0);
+        reserve.require_action_allowed(&e, 1);
+        reserve.require_action_allowed(&e, 3);
+        reserve.require_action_allowed(&e, 5);
+
+        reserve.flags = RESERVE_FROZEN;
+        reserve.require_action_allowed(&e, 1);
+        reserve.require_action_allowed(&e, 3);
+        reserve.require_action_allowed(&e, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1224)")]
+    fn test_require_action_allowed_collateral_disabled() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.flags = RESERVE_COLLATERAL_DISABLED;
+
+        reserve.require_action_allowed(&e, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1224)")]
+    fn test_require_action_allowed_borrow_disabled() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.flags = RESERVE_BORROW_DISABLED;
+
+        reserve.require_action_allowed(&e, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1224)")]
+    fn test_require_action_allowed_frozen() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.flags = RESERVE_FROZEN;
+
+        reserve.require_action_allowed(&e, 0);
+    }
+
+    #[test]
+    fn test_require_supply_under_cap() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.require_supply_under_cap(&e);
+
+        reserve.supply_cap = 100_0000000;
+        reserve.require_supply_under_cap(&e);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1222)")]
+    fn test_require_supply_under_cap_panics() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.supply_cap = 99_9999999;
+
+        reserve.require_supply_under_cap(&e);
+    }
+
+    #[test]
+    fn test_require_borrow_under_cap() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.require_borrow_under_cap(&e);
+
+        reserve.borrow_cap = 75_0000000;
+        reserve.require_borrow_under_cap(&e);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1223)")]
+    fn test_require_borrow_under_cap_panics() {
+        let e = Env::default();
+
+        let mut reserve = testutils::default_reserve(&e);
+        reserve.borrow_cap = 74_9999999;
+
+        reserve.require_borrow_under_cap(&e);
+    }
+
     /***** Token Transfer Math *****/
 
     #[test]
@@ -1,27 +1,31 @@
 use crate::{
-    constants::SCALAR_7,
+    constants::{
+        SCALAR_7, STATUS_TRIGGER_NONE, STATUS_TRIGGER_Q4W_FROZEN, STATUS_TRIGGER_Q4W_ON_ICE,
+        STATUS_TRIGGER_THRESHOLD,
+    },
     dependencies::{BackstopClient, PoolBackstopData},
     storage, PoolError,
 };
+use cast::i128;
 use soroban_sdk::{panic_with_error, Env};
 
 /// Update the pool status based on the backstop module
-#[allow(clippy::zero_prefixed_literal)]
-#[allow(clippy::inconsistent_digit_grouping)]
-pub fn execute_update_pool_status(e: &Env) -> u32 {
+///
+/// Returns the new pool status and the backstop condition that determined it
+pub fn execute_update_pool_status(e: &Env) -> (u32, u32) {
     let mut pool_config = storage::get_pool_config(e);
+    let status_config = &pool_config.status_config;
 
     // check the pool has met minimum backstop deposits
     let backstop_id = storage::get_backstop(e);
     let backstop_client = BackstopClient::new(e, &backstop_id);
 
     let pool_backstop_data = backstop_client.pool_data(&e.current_contract_address());
-    let threshold = calc_pool_backstop_threshold(&pool_backstop_data);
-    let mut met_threshold = true;
-    if threshold < SCALAR_7 {
-        met_threshold = false;
-    }
+    let threshold = calc_pool_backstop_threshold(&pool_backstop_data, status_config.backstop_pc);
+    let met_threshold = threshold >= SCALAR_7;
+    let q4w_pct = pool_backstop_data.q4w_pct;
 
+    let mut trigger = STATUS_TRIGGER_NONE;
     match pool_config.status {
         // Setup
         6 => {
@@ -35,26 +39,38 @@ pub fn execute_update_pool_status(e: &Env) -> u32 {
         }
         // Admin on-ice
         2 => {
-            if pool_backstop_data.q4w_pct >= 0_7500000 {
-                // Q4W over 75% freezes the pool
+            if q4w_pct >= i128(status_config.admin_frozen_q4w) {
+                // Q4W over the admin frozen limit freezes the pool
                 pool_config.status = 5;
+                trigger = STATUS_TRIGGER_Q4W_FROZEN;
             }
         }
         // Admin active
         0 => {
-            if !met_threshold || pool_backstop_data.q4w_pct >= 0_5000000 {
-                // Q4w over 50% or being under threshold puts the pool on-ice
+            if q4w_pct >= i128(status_config.admin_on_ice_q4w) {
+                // Q4w over the admin on-ice limit puts the pool on-ice
+                pool_config.status = 3;
+                trigger = STATUS_TRIGGER_Q4W_ON_ICE;
+            } else if !met_threshold {
+                // Being under threshold puts the pool on-ice
                 pool_config.status = 3;
+                trigger = STATUS_TRIGGER_THRESHOLD;
             }
         }
         // Admin status isn't set
         _ => {
-            if pool_backstop_data.q4w_pct >= 0_6000000 {
-                // Q4w over 60% sets pool to Frozen
+            if q4w_pct >= i128(status_config.frozen_q4w) {
+                // Q4w over the frozen limit sets pool to Frozen
                 pool_config.status = 5;
-            } else if pool_backstop_data.q4w_pct >= 0_3000000 || !met_threshold {
-                // Q4w over 30% sets pool to On-Ice
+                trigger = STATUS_TRIGGER_Q4W_FROZEN;
+            } else if q4w_pct >= i128(status_config.on_ice_q4w) {
+                // Q4w over the on-ice limit sets pool to On-Ice
                 pool_config.status = 3;
+                trigger = STATUS_TRIGGER_Q4W_ON_ICE;
+            } else if !met_threshold {
+                // Being under threshold sets pool to On-Ice
+                pool_config.status = 3;
+                trigger = STATUS_TRIGGER_THRESHOLD;
             } else {
                 // Backstop is healthy and the pool is set to Active
                 pool_config.status = 1;
@@ -62,14 +78,13 @@ pub fn execute_update_pool_status(e: &Env) -> u32 {
         }
     }
     storage::set_pool_config(e, &pool_config);
-    pool_config.status
+    (pool_config.status, trigger)
 }
 
 /// Admin set the pool status
-#[allow(clippy::zero_prefixed_literal)]
-#[allow(clippy::inconsistent_digit_grouping)]
 pub fn execute_set_pool_status(e: &Env, pool_status: u32) {
     let mut pool_config = storage::get_pool_config(e);
+    let status_config = &pool_config.status_config;
 
     // check the pool has met minimum backstop deposits
     let backstop_id = storage::get_backstop(e);
@@ -79,9 +94,11 @@ pub fn execute_set_pool_status(e: &Env, pool_status: u32) {
 
     match pool_status {
         0 => {
-            // Threshold must be met and q4w must be under 50% for the admin to set Active
-            if calc_pool_backstop_threshold(&pool_backstop_data) < SCALAR_7
-                || pool_backstop_data.q4w_pct >= 0_5000000
+            // Threshold must be met and q4w must be under the admin on-ice limit for the admin to
+            // set Active
+            if calc_pool_backstop_threshold(&pool_backstop_data, status_config.backstop_pc)
+                < SCALAR_7
+                || pool_backstop_data.q4w_pct >= i128(status_config.admin_on_ice_q4w)
             {
                 panic_with_error!(e, PoolError::StatusNotAllowed);
             }
@@ -89,16 +106,16 @@ pub fn execute_set_pool_status(e: &Env, pool_status: u32) {
             pool_config.status = 0;
         }
         2 => {
-            // Q4w must be under 75% for admin to set On-Ice
-            if pool_backstop_data.q4w_pct >= 0_7500000 {
+            // Q4w must be under the admin frozen limit for admin to set On-Ice
+            if pool_backstop_data.q4w_pct >= i128(status_config.admin_frozen_q4w) {
                 panic_with_error!(e, PoolError::StatusNotAllowed);
             }
             // Admin On-Ice
             pool_config.status = 2;
         }
         3 => {
-            // Q4w must be under 75% for admin to set permissionless On-Ice
-            if pool_backstop_data.q4w_pct >= 0_7500000 {
+            // Q4w must be under the admin frozen limit for admin to set permissionless On-Ice
+            if pool_backstop_data.q4w_pct >= i128(status_config.admin_frozen_q4w) {
                 panic_with_error!(e, PoolError::StatusNotAllowed);
             }
             // On-Ice
@@ -126,13 +143,20 @@ pub fn execute_set_pool_status(e: &Env, pool_status: u32) {
 ///         - 0_0000100 = ~10%
 ///         - 0_0000003 = ~5%
 ///         - 0_0000000 = ~0-4%
-pub fn calc_pool_backstop_threshold(pool_backstop_data: &PoolBackstopData) -> i128 {
+///
+/// ### Arguments
+/// * `pool_backstop_data` - The backstop data for the pool
+/// * `backstop_pc` - The product constant the backstop must reach, in whole tokens
+pub fn calc_pool_backstop_threshold(
+    pool_backstop_data: &PoolBackstopData,
+    backstop_pc: i128,
+) -> i128 {
     // @dev: Calculation for pools product constant of underlying will often overflow i128
     //       so saturating mul is used. This is safe because the threshold is below i128::MAX and the
     //       protocol does not need to differentiate between pools over the threshold product constant.
     //       The calculation is:
-    //        - Threshold % = (bal_blnd^4 * bal_usdc) / PC^5 such that PC is 200k
-    let threshold_pc = 320_000_000_000_000_000_000_000_000i128; // 3.2e26 (200k^5)
+    //        - Threshold % = (bal_blnd^4 * bal_usdc) / PC^5 such that PC is the backstop_pc
+    let threshold_pc = backstop_pc.pow(5);
 
     // floor balances to nearest full unit and calculate saturated pool product constant
     // and scale to SCALAR_7 to get final division result in SCALAR_7 points
@@ -150,7 +174,7 @@ pub fn calc_pool_backstop_threshold(pool_backstop_data: &PoolBackstopData) -> i1
 #[cfg(test)]
 mod tests {
     use crate::{
-        storage::PoolConfig,
+        storage::{PoolConfig, StatusConfig},
         testutils::{
             create_backstop, create_comet_lp_pool, create_pool, create_token_contract,
             setup_backstop,
@@ -195,6 +219,9 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -243,6 +270,9 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -289,6 +319,9 @@ mod tests {
             bstop_rate: 0,
             status: 2,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -332,6 +365,9 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -381,6 +417,9 @@ mod tests {
             bstop_rate: 0,
             status: 5,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -426,6 +465,9 @@ mod tests {
             bstop_rate: 0,
             status: 6,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -469,6 +511,9 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -516,6 +561,9 @@ mod tests {
             bstop_rate: 0,
             status: 2,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -560,16 +608,20 @@ mod tests {
             bstop_rate: 0,
             status: 3,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 1);
+            assert_eq!(trigger, STATUS_TRIGGER_NONE);
         });
     }
 
@@ -608,16 +660,20 @@ mod tests {
             bstop_rate: 0,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 0);
+            assert_eq!(trigger, STATUS_TRIGGER_NONE);
         });
     }
 
@@ -656,16 +712,20 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 3);
+            assert_eq!(trigger, STATUS_TRIGGER_THRESHOLD);
         });
     }
 
@@ -705,16 +765,90 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 3);
+            assert_eq!(trigger, STATUS_TRIGGER_Q4W_ON_ICE);
+        });
+    }
+
+    #[test]
+    fn test_update_pool_status_custom_limits() {
+        let e = Env::default();
+        e.budget().reset_unlimited();
+        e.mock_all_auths_allowing_non_root_auth();
+        let pool_id = create_pool(&e);
+        let oracle_id = Address::generate(&e);
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+
+        let (blnd, blnd_client) = create_token_contract(&e, &bombadil);
+        let (usdc, usdc_client) = create_token_contract(&e, &bombadil);
+        let (lp_token, lp_token_client) = create_comet_lp_pool(&e, &bombadil, &blnd, &usdc);
+        let (backstop_id, backstop_client) = create_backstop(&e);
+        setup_backstop(&e, &pool_id, &backstop_id, &lp_token, &usdc, &blnd);
+
+        // mint lp tokens
+        blnd_client.mint(&samwise, &500_001_0000000);
+        blnd_client.approve(&samwise, &lp_token, &i128::MAX, &99999);
+        usdc_client.mint(&samwise, &12_501_0000000);
+        usdc_client.approve(&samwise, &lp_token, &i128::MAX, &99999);
+        lp_token_client.join_pool(
+            &50_000_0000000,
+            &vec![&e, 500_001_0000000, 12_501_0000000],
+            &samwise,
+        );
+        backstop_client.deposit(&samwise, &pool_id, &50_000_0000000);
+        backstop_client.update_tkn_val();
+        backstop_client.queue_withdrawal(&samwise, &pool_id, &15_000_0000000);
+
+        let mut pool_config = PoolConfig {
+            oracle: oracle_id,
+            bstop_rate: 0,
+            status: 1,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: StatusConfig {
+                on_ice_q4w: 0_4000000,
+                frozen_q4w: 0_7000000,
+                admin_on_ice_q4w: 0_5000000,
+                admin_frozen_q4w: 0_7500000,
+                backstop_pc: 200_000,
+            },
+        };
+        e.as_contract(&pool_id, || {
+            storage::set_admin(&e, &bombadil);
+            storage::set_pool_config(&e, &pool_config);
+
+            // 30% q4w is under the custom on-ice limit
+            let (status, trigger) = execute_update_pool_status(&e);
+
+            let new_pool_config = storage::get_pool_config(&e);
+            assert_eq!(new_pool_config.status, status);
+            assert_eq!(status, 1);
+            assert_eq!(trigger, STATUS_TRIGGER_NONE);
+
+            // the backstop is under a larger minimum deposit
+            pool_config.status_config.backstop_pc = 300_000;
+            storage::set_pool_config(&e, &pool_config);
+            let (status, trigger) = execute_update_pool_status(&e);
+
+            let new_pool_config = storage::get_pool_config(&e);
+            assert_eq!(new_pool_config.status, status);
+            assert_eq!(status, 3);
+            assert_eq!(trigger, STATUS_TRIGGER_THRESHOLD);
         });
     }
 
@@ -754,16 +888,20 @@ mod tests {
             bstop_rate: 0,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 0);
+            assert_eq!(trigger, STATUS_TRIGGER_NONE);
         });
     }
 
@@ -803,16 +941,20 @@ mod tests {
             bstop_rate: 0,
             status: 0,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 3);
+            assert_eq!(trigger, STATUS_TRIGGER_Q4W_ON_ICE);
         });
     }
 
@@ -852,16 +994,20 @@ mod tests {
             bstop_rate: 0,
             status: 1,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 5);
+            assert_eq!(trigger, STATUS_TRIGGER_Q4W_FROZEN);
         });
     }
     #[test]
@@ -900,16 +1046,20 @@ mod tests {
             bstop_rate: 0,
             status: 2,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 2);
+            assert_eq!(trigger, STATUS_TRIGGER_NONE);
         });
     }
 
@@ -949,16 +1099,20 @@ mod tests {
             bstop_rate: 0,
             status: 2,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
             storage::set_pool_config(&e, &pool_config);
 
-            let status = execute_update_pool_status(&e);
+            let (status, trigger) = execute_update_pool_status(&e);
 
             let new_pool_config = storage::get_pool_config(&e);
             assert_eq!(new_pool_config.status, status);
             assert_eq!(status, 5);
+            assert_eq!(trigger, STATUS_TRIGGER_Q4W_FROZEN);
         });
     }
 
@@ -998,6 +1152,9 @@ mod tests {
             bstop_rate: 0,
             status: 4,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -1043,6 +1200,9 @@ mod tests {
             bstop_rate: 0,
             status: 6,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -1089,6 +1249,9 @@ mod tests {
             bstop_rate: 0,
             status: 5,
             max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool_id, || {
             storage::set_admin(&e, &bombadil);
@@ -1113,10 +1276,26 @@ mod tests {
             usdc: 25_000_0000000,
         }; // ~91.2% threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 0_6328125);
     }
 
+    #[test]
+    fn test_calc_pool_backstop_threshold_custom_pc() {
+        let e = Env::default();
+        e.budget().reset_unlimited();
+
+        let pool_backstop_data = PoolBackstopData {
+            blnd: 300_000_0000000,
+            q4w_pct: 0,
+            tokens: 20_000_0000000,
+            usdc: 25_000_0000000,
+        }; // ~182.4% threshold
+
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 100_000);
+        assert_eq!(result, 20_2500000);
+    }
+
     #[test]
     fn test_calc_pool_backstop_threshold_10_percent() {
         let e = Env::default();
@@ -1129,7 +1308,7 @@ mod tests {
             usdc: 3_975_0000000,
         }; // ~10% threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 0_0000100);
     }
 
@@ -1145,7 +1324,7 @@ mod tests {
             usdc: 1_000_0000000,
         }; // ~3.6% threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 0);
     }
 
@@ -1161,7 +1340,7 @@ mod tests {
             usdc: 18_100_0000000,
         }; // 100% threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 1_0000002);
     }
 
@@ -1177,7 +1356,7 @@ mod tests {
             usdc: 10_000_000_0000000,
         }; // 181x threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 53169_1198313);
     }
 
@@ -1193,7 +1372,7 @@ mod tests {
             usdc: 20_000_0000000,
         }; // 10% threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 0_0000100);
     }
 
@@ -1209,7 +1388,7 @@ mod tests {
             usdc: 10_000_0000000,
         }; // 5% threshold
 
-        let result = calc_pool_backstop_threshold(&pool_backstop_data);
+        let result = calc_pool_backstop_threshold(&pool_backstop_data, 200_000);
         assert_eq!(result, 0_0000003);
     }
 }
@@ -1,13 +1,15 @@
-use sep_41_token::TokenClient;
-use soroban_fixed_point_math::FixedPoint;
-use soroban_sdk::{panic_with_error, Address, Env, Vec};
+use soroban_sdk::{map, panic_with_error, Address, Env, Map, Vec};
 
-use crate::{constants::SCALAR_7, PoolError};
+use crate::{
+    catchup::{self, Relaxed},
+    PoolError,
+};
 
 use super::{
     actions::{build_actions_from_request, Request},
+    emode::load_user_emode,
     health_factor::PositionData,
-    pool::Pool,
+    pool::{Pool, PriceMode},
     Positions,
 };
 
@@ -27,6 +29,30 @@ pub fn execute_submit(
     spender: &Address,
     to: &Address,
     requests: Vec<Request>,
+) -> Positions {
+    execute_submit_with_credit(e, from, spender, to, requests, &mut map![e])
+}
+
+/// Execute a set of updates for a user against the pool. Tokens owed by the spender are taken
+/// from `credit` first, which holds tokens already in the pool that were lent to the spender,
+/// like a flash loan.
+///
+/// ### Arguments
+/// * from - The address of the user whose positions are being modified
+/// * spender - The address of the user who is sending tokens to the pool
+/// * to - The address of the user who is receiving tokens from the pool
+/// * requests - A vec of requests to be processed
+/// * credit - The tokens lent to the spender, reduced by the amount used
+///
+/// ### Panics
+/// If the request is unable to be fully executed
+pub fn execute_submit_with_credit(
+    e: &Env,
+    from: &Address,
+    spender: &Address,
+    to: &Address,
+    requests: Vec<Request>,
+    credit: &mut Map<Address, i128>,
 ) -> Positions {
     if from == &e.current_contract_address()
         || spender == &e.current_contract_address()
@@ -39,33 +65,42 @@ pub fn execute_submit(
     let (actions, new_from_state, check_health) =
         build_actions_from_request(e, &mut pool, from, requests);
 
-    let positions_data =
-        PositionData::calculate_from_positions(e, &mut pool, &new_from_state.positions);
-    let min_health_factor = positions_data
-        .scalar
-        .fixed_mul_floor(1_0000100, SCALAR_7)
-        .unwrap();
-
-    // Note: checking Hf in catchups is currently not possible because it's not an entry
-    // encompassed by the readwrite footprint and is subject to change. Once the custom write
-    // state feature is live for catchup this can be addressed.
-    let positions_data = if e.ledger().sequence() > env!("DEPLOYMENT_SEQ").parse().unwrap() {
-        positions_data.is_hf_under(1_0000100)
-    } else {
-        false
-    };
-
     // panics if the new positions set does not meet the health factor requirement
     // min is 1.0000100 to prevent rounding errors
-    if check_health && new_from_state.has_liabilities() && positions_data {
+    // @dev: prices are only loaded when a health check is required, so stale oracle prices
+    //       do not block supplying or repaying. In catch-up mode the check is relaxed before
+    //       the prices are loaded, as they are not in a replayed footprint.
+    if check_health
+        && new_from_state.has_liabilities()
+        && !catchup::relax(e, Relaxed::HealthCheck)
+        && PositionData::calculate_from_positions(
+            e,
+            &mut pool,
+            &new_from_state.positions,
+            &load_user_emode(e, from),
+            PriceMode::Borrow,
+        )
+        .is_hf_under(1_0000100)
+    {
         panic_with_error!(e, PoolError::InvalidHf);
     }
 
-    // transfer tokens from sender to pool
+    // transfer tokens from sender to pool, paying with any credit first
     for (address, amount) in actions.spender_transfer.iter() {
-        // note: we try the transfer because it's not strictly required for the indexing that
-        // this will indeed resolve, something that is not always possible with catchups
-        TokenClient::new(e, &address).try_transfer(spender, &e.current_contract_address(), &amount);
+        let available = credit.get(address.clone()).unwrap_or(0);
+        let from_credit = available.min(amount);
+        if from_credit > 0 {
+            credit.set(address.clone(), available - from_credit);
+        }
+        if amount > from_credit {
+            catchup::transfer(
+                e,
+                &address,
+                spender,
+                &e.current_contract_address(),
+                amount - from_credit,
+            );
+        }
     }
 
     // store updated info to ledger
@@ -74,9 +109,7 @@ pub fn execute_submit(
 
     // transfer tokens from pool to "to"
     for (address, amount) in actions.pool_transfer.iter() {
-        // note: we try the transfer because it's not strictly required for the indexing that
-        // this will indeed resolve, something that is not always possible with catchups
-        TokenClient::new(e, &address).try_transfer(&e.current_contract_address(), to, &amount);
+        catchup::transfer(e, &address, &e.current_contract_address(), to, amount);
     }
 
     new_from_state.positions
@@ -92,6 +125,7 @@ mod tests {
     use super::*;
     use sep_40_oracle::testutils::Asset;
     use soroban_sdk::{
+        map,
         testutils::{Address as _, Ledger, LedgerInfo},
         vec, Symbol,
     };
@@ -148,6 +182,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             e.mock_all_auths_allowing_non_root_auth();
@@ -230,6 +267,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             e.mock_all_auths_allowing_non_root_auth();
@@ -279,6 +319,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "catchup"))]
     #[should_panic(expected = "Error(Contract, #1205)")]
     fn test_submit_requires_healhty() {
         let e = Env::default();
@@ -329,6 +370,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             storage::set_pool_config(&e, &pool_config);
@@ -393,6 +437,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             e.mock_all_auths_allowing_non_root_auth();
@@ -453,6 +500,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             e.mock_all_auths_allowing_non_root_auth();
@@ -513,6 +563,9 @@ mod tests {
             bstop_rate: 0_1000000,
             status: 0,
             max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
         };
         e.as_contract(&pool, || {
             e.mock_all_auths_allowing_non_root_auth();
@@ -529,4 +582,173 @@ mod tests {
             execute_submit(&e, &samwise, &samwise, &pool, requests);
         });
     }
+
+    #[test]
+    #[cfg(not(feature = "catchup"))]
+    #[should_panic(expected = "Error(Contract, #1210)")]
+    fn test_submit_borrow_panics_if_stale_price() {
+        let e = Env::default();
+        e.budget().reset_unlimited();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600 + 86401,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 1_0000000, 5_0000000], &600);
+
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            e.mock_all_auths_allowing_non_root_auth();
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(
+                &e,
+                &samwise,
+                &Positions {
+                    liabilities: map![&e],
+                    collateral: map![&e, (0, 15_0000000)],
+                    supply: map![&e],
+                },
+            );
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::Borrow as u32,
+                    address: underlying_1,
+                    amount: 1_5000000,
+                },
+            ];
+            execute_submit(&e, &samwise, &frodo, &frodo, requests);
+        });
+    }
+
+    #[test]
+    fn test_submit_repay_and_supply_with_stale_price() {
+        let e = Env::default();
+        e.budget().reset_unlimited();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 600 + 86401,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+
+        let (underlying_0, underlying_0_client) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_data.last_time = 600 + 86401;
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, underlying_1_client) = testutils::create_token_contract(&e, &bombadil);
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        underlying_0_client.mint(&frodo, &10_0000000);
+        underlying_1_client.mint(&frodo, &10_0000000);
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0.clone()),
+                Asset::Stellar(underlying_1.clone()),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price(&vec![&e, 1_0000000, 5_0000000], &600);
+
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_1000000,
+            status: 0,
+            max_positions: 2,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            e.mock_all_auths_allowing_non_root_auth();
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(
+                &e,
+                &samwise,
+                &Positions {
+                    liabilities: map![&e, (1, 2_0000000)],
+                    collateral: map![&e, (0, 15_0000000)],
+                    supply: map![&e],
+                },
+            );
+
+            let requests = vec![
+                &e,
+                Request {
+                    request_type: RequestType::SupplyCollateral as u32,
+                    address: underlying_0,
+                    amount: 5_0000000,
+                },
+                Request {
+                    request_type: RequestType::Repay as u32,
+                    address: underlying_1,
+                    amount: 1_0000000,
+                },
+            ];
+            let positions = execute_submit(&e, &samwise, &frodo, &frodo, requests);
+
+            assert_eq!(positions.collateral.get_unchecked(0), 20_0000000);
+            assert_eq!(positions.liabilities.get_unchecked(1), 1_0000000);
+        });
+    }
 }
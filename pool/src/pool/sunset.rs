@@ -0,0 +1,479 @@
+use cast::i128;
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{panic_with_error, unwrap::UnwrapOptimized, Address, Env};
+
+use crate::{
+    constants::{RESERVE_SUNSET, SECONDS_PER_WEEK},
+    errors::PoolError,
+    storage::{self, ReserveSunset},
+};
+
+use super::pool::Pool;
+
+impl ReserveSunset {
+    /// Check if the sunset has started
+    pub fn is_started(&self) -> bool {
+        self.start_time != 0
+    }
+
+    /// The time the collateral factor reaches zero
+    pub fn end_time(&self) -> u64 {
+        self.start_time + self.duration
+    }
+
+    /// Calculate the collateral factor at a timestamp, ramped down linearly from the collateral
+    /// factor when the sunset started to zero at the end time
+    ///
+    /// ### Arguments
+    /// * `timestamp` - The timestamp to calculate the collateral factor at
+    pub fn c_factor_at(&self, timestamp: u64) -> u32 {
+        let end_time = self.end_time();
+        if timestamp >= end_time {
+            return 0;
+        }
+        let remaining = end_time - timestamp.max(self.start_time);
+        i128(self.c_factor)
+            .fixed_mul_floor(i128(remaining), i128(self.duration))
+            .unwrap_optimized() as u32
+    }
+}
+
+/// Load the current collateral factor of a reserve that is being sunset
+///
+/// ### Arguments
+/// * `asset` - The underlying asset of the reserve
+///
+/// ### Panics
+/// If the reserve is not being sunset
+pub fn load_sunset_c_factor(e: &Env, asset: &Address) -> u32 {
+    storage::get_res_sunset(e, asset)
+        .unwrap_optimized()
+        .c_factor_at(e.ledger().timestamp())
+}
+
+/// Execute queueing a reserve to be sunset. Returns the time the sunset can start.
+///
+/// ### Arguments
+/// * `asset` - The underlying asset of the reserve
+/// * `duration` - The time to ramp the collateral factor down to zero in seconds
+///
+/// ### Panics
+/// If the reserve does not exist, is already queued or being sunset, or the duration is zero
+pub fn execute_queue_sunset_reserve(e: &Env, asset: &Address, duration: u64) -> u64 {
+    if !storage::has_res(e, asset) || storage::get_res_sunset(e, asset).is_some() || duration == 0 {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    let mut unlock_time = e.ledger().timestamp();
+    // require a timelock if pool status is not setup
+    if storage::get_pool_config(e).status != 6 {
+        unlock_time += SECONDS_PER_WEEK;
+    }
+    storage::set_res_sunset(
+        e,
+        asset,
+        &ReserveSunset {
+            unlock_time,
+            duration,
+            c_factor: 0,
+            start_time: 0,
+        },
+    );
+    unlock_time
+}
+
+/// Execute cancelling a queued reserve sunset
+///
+/// ### Panics
+/// If the reserve is not queued to be sunset, or the sunset has already started
+pub fn execute_cancel_sunset_reserve(e: &Env, asset: &Address) {
+    match storage::get_res_sunset(e, asset) {
+        Some(sunset) if !sunset.is_started() => storage::del_res_sunset(e, asset),
+        _ => panic_with_error!(e, PoolError::BadRequest),
+    }
+}
+
+/// Execute starting a queued reserve sunset. Supplying to and borrowing from the reserve is
+/// disabled, and its collateral factor ramps down to zero over the sunset's duration, after which
+/// any positions that are unhealthy without it can be liquidated. Returns the time the collateral
+/// factor reaches zero.
+///
+/// ### Arguments
+/// * `asset` - The underlying asset of the reserve
+///
+/// ### Panics
+/// If the reserve is not queued to be sunset, the sunset has already started, or the timelock
+/// has not passed
+pub fn execute_sunset_reserve(e: &Env, asset: &Address) -> u64 {
+    let mut sunset = match storage::get_res_sunset(e, asset) {
+        Some(sunset) if !sunset.is_started() => sunset,
+        _ => panic_with_error!(e, PoolError::BadRequest),
+    };
+    if sunset.unlock_time > e.ledger().timestamp() {
+        panic_with_error!(e, PoolError::InitNotUnlocked);
+    }
+
+    // accrue the reserve before storing it
+    let mut pool = Pool::load(e);
+    let mut reserve = pool.load_reserve(e, asset, false);
+    sunset.c_factor = reserve.c_factor;
+    sunset.start_time = e.ledger().timestamp();
+    reserve.flags |= RESERVE_SUNSET;
+    reserve.store(e);
+    storage::set_res_sunset(e, asset, &sunset);
+    sunset.end_time()
+}
+
+/// Execute retiring a reserve whose collateral factor has been ramped down to zero. Positions in
+/// a retired reserve no longer count towards the pool's maximum positions. Returns the index of
+/// the reserve.
+///
+/// ### Arguments
+/// * `asset` - The underlying asset of the reserve
+///
+/// ### Panics
+/// If the reserve's sunset has not ended, or the reserve is already retired
+pub fn execute_retire_reserve(e: &Env, asset: &Address) -> u32 {
+    match storage::get_res_sunset(e, asset) {
+        Some(sunset) if sunset.is_started() && sunset.end_time() <= e.ledger().timestamp() => (),
+        _ => panic_with_error!(e, PoolError::BadRequest),
+    }
+    let index = storage::get_res_config(e, asset).index;
+    let mut retired = storage::get_retired_reserves(e);
+    if retired.contains(index) {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    retired.push_back(index);
+    storage::set_retired_reserves(e, &retired);
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{pool::Reserve, storage::PoolConfig, testutils};
+
+    use super::*;
+    use soroban_sdk::{
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec,
+    };
+
+    fn setup_ledger(e: &Env) {
+        e.ledger().set(LedgerInfo {
+            timestamp: 123456 * 5,
+            protocol_version: 20,
+            sequence_number: 123456,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+    }
+
+    #[test]
+    fn test_sunset_reserve() {
+        let e = Env::default();
+        e.mock_all_auths();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let (oracle, _) = testutils::create_mock_oracle(&e);
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        let start_time = e.ledger().timestamp() + SECONDS_PER_WEEK;
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let unlock_time = execute_queue_sunset_reserve(&e, &underlying_1, 1000);
+            assert_eq!(unlock_time, start_time);
+        });
+
+        e.ledger().with_mut(|li| li.timestamp = start_time);
+        e.as_contract(&pool, || {
+            let end_time = execute_sunset_reserve(&e, &underlying_1);
+            assert_eq!(end_time, start_time + 1000);
+
+            let sunset = storage::get_res_sunset(&e, &underlying_1).unwrap_optimized();
+            assert_eq!(sunset.c_factor, reserve_config.c_factor);
+            assert_eq!(sunset.start_time, start_time);
+            let res_data = storage::get_res_data(&e, &underlying_1);
+            assert_eq!(res_data.flags, RESERVE_SUNSET);
+            assert_eq!(res_data.last_time, start_time);
+            let res_config = storage::get_res_config(&e, &underlying_1);
+            assert_eq!(res_config.c_factor, reserve_config.c_factor);
+        });
+
+        e.ledger().with_mut(|li| li.timestamp = start_time + 250);
+        e.as_contract(&pool, || {
+            let reserve = Reserve::load(&e, &pool_config, &underlying_1);
+            assert_eq!(reserve.c_factor, 0_5625000);
+            let reserve = Reserve::load(&e, &pool_config, &underlying_0);
+            assert_eq!(reserve.c_factor, reserve_config.c_factor);
+        });
+
+        e.ledger().with_mut(|li| li.timestamp = start_time + 1000);
+        e.as_contract(&pool, || {
+            let reserve = Reserve::load(&e, &pool_config, &underlying_1);
+            assert_eq!(reserve.c_factor, 0);
+
+            let index = execute_retire_reserve(&e, &underlying_1);
+            assert_eq!(index, 1);
+            assert_eq!(storage::get_retired_reserves(&e), vec![&e, 1]);
+            assert_eq!(Pool::load(&e).retired, vec![&e, 1]);
+        });
+    }
+
+    #[test]
+    fn test_queue_sunset_reserve_status_6() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 6,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let unlock_time = execute_queue_sunset_reserve(&e, &underlying, 1000);
+            assert_eq!(unlock_time, e.ledger().timestamp());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_sunset_reserve_duplicate() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_queue_sunset_reserve_requires_reserve() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let pool = testutils::create_pool(&e);
+
+        e.as_contract(&pool, || {
+            execute_queue_sunset_reserve(&e, &Address::generate(&e), 1000);
+        });
+    }
+
+    #[test]
+    fn test_cancel_sunset_reserve() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+            execute_cancel_sunset_reserve(&e, &underlying);
+            assert!(storage::get_res_sunset(&e, &underlying).is_none());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_cancel_sunset_reserve_after_start() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 6,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+            execute_sunset_reserve(&e, &underlying);
+            execute_cancel_sunset_reserve(&e, &underlying);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1203)")]
+    fn test_sunset_reserve_requires_unlock() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+        });
+
+        e.ledger()
+            .with_mut(|li| li.timestamp += SECONDS_PER_WEEK - 1);
+        e.as_contract(&pool, || {
+            execute_sunset_reserve(&e, &underlying);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_retire_reserve_requires_sunset_ended() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 6,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+            execute_sunset_reserve(&e, &underlying);
+        });
+
+        e.ledger().with_mut(|li| li.timestamp += 999);
+        e.as_contract(&pool, || {
+            execute_retire_reserve(&e, &underlying);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_retire_reserve_twice() {
+        let e = Env::default();
+        setup_ledger(&e);
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        let pool_config = PoolConfig {
+            oracle: Address::generate(&e),
+            bstop_rate: 0_2000000,
+            status: 6,
+            max_positions: 4,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            execute_queue_sunset_reserve(&e, &underlying, 1000);
+            execute_sunset_reserve(&e, &underlying);
+        });
+
+        e.ledger().with_mut(|li| li.timestamp += 1000);
+        e.as_contract(&pool, || {
+            execute_retire_reserve(&e, &underlying);
+            execute_retire_reserve(&e, &underlying);
+        });
+    }
+}
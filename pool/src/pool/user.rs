@@ -1,6 +1,7 @@
-use soroban_sdk::{contracttype, panic_with_error, Address, Env, Map};
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{contracttype, panic_with_error, unwrap::UnwrapOptimized, Address, Env, Map};
 
-use crate::{emissions, storage, validator::require_nonnegative, PoolError};
+use crate::{constants::SCALAR_9, emissions, storage, validator::require_nonnegative, PoolError};
 
 use super::{Pool, Reserve};
 
@@ -99,6 +100,28 @@ impl User {
         emissions
     }
 
+    /// Default on liabilities of the position expressed in debtTokens. The liabilities are
+    /// removed and the underlying value of the defaulted liabilities is deducted from the
+    /// reserve's b_rate, socializing the loss to the reserve's suppliers. The loss is capped
+    /// so the b_rate stays positive, and nothing is socialized if the reserve has no suppliers.
+    ///
+    /// Returns the amount of underlying tokens socialized to the reserve's suppliers
+    pub fn default_liabilities(&mut self, e: &Env, reserve: &mut Reserve, amount: i128) -> i128 {
+        self.remove_liabilities(e, reserve, amount);
+
+        if reserve.b_supply == 0 {
+            return 0;
+        }
+        let default_amount = reserve.to_asset_from_d_token(amount);
+        let b_rate_loss = default_amount
+            .fixed_div_ceil(reserve.b_supply, SCALAR_9)
+            .unwrap_optimized()
+            .min(reserve.b_rate - 1);
+        let supply_before = reserve.total_supply();
+        reserve.b_rate -= b_rate_loss;
+        (supply_before - reserve.total_supply()).min(default_amount)
+    }
+
     /// Get the collateralized blendToken position for the reserve at the given index
     pub fn get_collateral(&self, reserve_index: u32) -> i128 {
         self.positions.collateral.get(reserve_index).unwrap_or(0)
@@ -252,7 +275,6 @@ mod tests {
         constants::SCALAR_7, storage, testutils, ReserveEmissionsConfig, ReserveEmissionsData,
         UserEmissionData,
     };
-    use soroban_fixed_point_math::FixedPoint;
     use soroban_sdk::{
         map,
         testutils::{Address as _, Ledger, LedgerInfo},
@@ -530,6 +552,91 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_default_liabilities() {
+        let e = Env::default();
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let mut reserve_0 = testutils::default_reserve(&e);
+        reserve_0.d_rate = 1_100_000_000;
+        reserve_0.b_rate = 1_050_000_000;
+        let starting_d_supply_0 = reserve_0.d_supply;
+
+        let mut user = User {
+            address: samwise.clone(),
+            positions: Positions {
+                liabilities: map![&e, (reserve_0.index, 20_0000000)],
+                collateral: map![&e],
+                supply: map![&e],
+            },
+        };
+        e.as_contract(&pool, || {
+            let default_amount = user.default_liabilities(&e, &mut reserve_0, 20_0000000);
+            assert_eq!(default_amount, 22_0000000);
+            assert_eq!(user.get_liabilities(0), 0);
+            assert_eq!(user.positions.liabilities.len(), 0);
+            assert_eq!(reserve_0.d_supply, starting_d_supply_0 - 20_0000000);
+            // 22 tokens lost across 100 b_tokens
+            assert_eq!(reserve_0.b_rate, 0_830_000_000);
+        });
+    }
+
+    #[test]
+    fn test_default_liabilities_caps_loss_at_supply() {
+        let e = Env::default();
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let mut reserve_0 = testutils::default_reserve(&e);
+        reserve_0.d_rate = 2_000_000_000;
+        reserve_0.b_rate = 1_050_000_000;
+
+        let mut user = User {
+            address: samwise.clone(),
+            positions: Positions {
+                liabilities: map![&e, (reserve_0.index, 70_0000000)],
+                collateral: map![&e],
+                supply: map![&e],
+            },
+        };
+        e.as_contract(&pool, || {
+            let default_amount = user.default_liabilities(&e, &mut reserve_0, 70_0000000);
+            // 140 tokens defaulted, but only the 105 tokens supplied can be socialized, less
+            // the dust left behind by keeping the b_rate positive
+            assert_eq!(default_amount, 104_9999999);
+            assert_eq!(user.positions.liabilities.len(), 0);
+            assert_eq!(reserve_0.b_rate, 1);
+        });
+    }
+
+    #[test]
+    fn test_default_liabilities_no_supply() {
+        let e = Env::default();
+        let samwise = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+
+        let mut reserve_0 = testutils::default_reserve(&e);
+        reserve_0.b_supply = 0;
+        reserve_0.b_rate = 1_050_000_000;
+        let starting_d_supply_0 = reserve_0.d_supply;
+
+        let mut user = User {
+            address: samwise.clone(),
+            positions: Positions {
+                liabilities: map![&e, (reserve_0.index, 20_0000000)],
+                collateral: map![&e],
+                supply: map![&e],
+            },
+        };
+        e.as_contract(&pool, || {
+            let default_amount = user.default_liabilities(&e, &mut reserve_0, 20_0000000);
+            assert_eq!(default_amount, 0);
+            assert_eq!(user.positions.liabilities.len(), 0);
+            assert_eq!(reserve_0.d_supply, starting_d_supply_0 - 20_0000000);
+            assert_eq!(reserve_0.b_rate, 1_050_000_000);
+        });
+    }
     #[test]
     fn test_collateral() {
         let e = Env::default();
@@ -0,0 +1,258 @@
+use soroban_sdk::{contracttype, panic_with_error, Address, Env};
+
+use crate::{
+    errors::PoolError,
+    storage::{self, ReserveConfig, ReserveData},
+};
+
+use super::{
+    load_user_emode,
+    pool::{Pool, PriceMode},
+    PositionData, User,
+};
+
+/// A reserve's config and its data accrued to the current ledger timestamp
+#[derive(Clone)]
+#[contracttype]
+pub struct ReserveView {
+    pub asset: Address,        // the underlying asset address
+    pub config: ReserveConfig, // the reserve's config
+    pub data: ReserveData,     // the reserve's data accrued to the current ledger timestamp
+}
+
+/// A user's collateral and liabilities valued in the oracle's base asset
+#[derive(Clone)]
+#[contracttype]
+pub struct UserPositionData {
+    pub collateral_base: i128, // the effective collateral balance
+    pub collateral_raw: i128,  // the raw collateral balance
+    pub liability_base: i128,  // the effective liability balance
+    pub liability_raw: i128,   // the raw liability balance
+    pub scalar: i128,          // the scalar for the base asset
+    pub health_factor: i128, // the effective collateral over effective liabilities, or i128::MAX if no liabilities
+}
+
+/// Load a reserve with interest accrued to the current ledger timestamp, without storing it
+///
+/// ### Arguments
+/// * `asset` - The underlying asset of the reserve
+///
+/// ### Panics
+/// If the reserve does not exist
+pub fn load_reserve_view(e: &Env, asset: &Address) -> ReserveView {
+    if !storage::has_res(e, asset) {
+        panic_with_error!(e, PoolError::BadRequest);
+    }
+    let mut pool = Pool::load(e);
+    let reserve = pool.load_reserve(e, asset, false);
+    ReserveView {
+        asset: asset.clone(),
+        config: storage::get_res_config(e, asset),
+        data: reserve.to_data(),
+    }
+}
+
+/// Load a user's position data using the current oracle prices and the user's efficiency
+/// mode category. Positions are valued the same way as the health check on borrows.
+///
+/// ### Arguments
+/// * `user` - The address of the user
+///
+/// ### Panics
+/// If a price for one of the user's positions cannot be loaded
+pub fn load_user_position_data(e: &Env, user: &Address) -> UserPositionData {
+    let mut pool = Pool::load(e);
+    let user_state = User::load(e, user);
+    let position_data = PositionData::calculate_from_positions(
+        e,
+        &mut pool,
+        &user_state.positions,
+        &load_user_emode(e, user),
+        PriceMode::Borrow,
+    );
+    let health_factor = if position_data.liability_base == 0 {
+        i128::MAX
+    } else {
+        position_data.as_health_factor()
+    };
+    UserPositionData {
+        collateral_base: position_data.collateral_base,
+        collateral_raw: position_data.collateral_raw,
+        liability_base: position_data.liability_base,
+        liability_raw: position_data.liability_raw,
+        scalar: position_data.scalar,
+        health_factor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::SCALAR_7, pool::Positions, storage::PoolConfig, testutils};
+    use sep_40_oracle::testutils::Asset;
+    use soroban_sdk::{
+        map,
+        testutils::{Address as _, Ledger, LedgerInfo},
+        vec, Symbol,
+    };
+
+    #[test]
+    fn test_load_reserve_view_accrues_without_storing() {
+        let e = Env::default();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let (oracle, _) = testutils::create_mock_oracle(&e);
+
+        let (underlying, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying, &reserve_config, &reserve_data);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 123456,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 5,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+
+            let view = load_reserve_view(&e, &underlying);
+            assert_eq!(view.asset, underlying);
+            assert_eq!(view.config.index, reserve_config.index);
+            assert_eq!(view.data.last_time, 123456);
+            assert!(view.data.d_rate > reserve_data.d_rate);
+            assert!(view.data.b_rate > reserve_data.b_rate);
+
+            let stored_data = storage::get_res_data(&e, &underlying);
+            assert_eq!(stored_data.last_time, reserve_data.last_time);
+            assert_eq!(stored_data.d_rate, reserve_data.d_rate);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1200)")]
+    fn test_load_reserve_view_no_reserve_panics() {
+        let e = Env::default();
+        let pool = testutils::create_pool(&e);
+
+        e.as_contract(&pool, || {
+            load_reserve_view(&e, &Address::generate(&e));
+        });
+    }
+
+    #[test]
+    fn test_load_user_position_data() {
+        let e = Env::default();
+        e.budget().reset_unlimited();
+        e.mock_all_auths();
+
+        let bombadil = Address::generate(&e);
+        let samwise = Address::generate(&e);
+        let frodo = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+
+        let (underlying_0, _) = testutils::create_token_contract(&e, &bombadil);
+        let (reserve_config, reserve_data) = testutils::default_reserve_meta();
+        testutils::create_reserve(&e, &pool, &underlying_0, &reserve_config, &reserve_data);
+
+        let (underlying_1, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.decimals = 9;
+        reserve_config.c_factor = 0_8500000;
+        reserve_config.l_factor = 0_8000000;
+        reserve_data.b_supply = 100_000_000_000;
+        reserve_data.d_supply = 70_000_000_000;
+        reserve_data.b_rate = 1_100_000_000;
+        reserve_data.d_rate = 1_150_000_000;
+        reserve_config.index = 1;
+        testutils::create_reserve(&e, &pool, &underlying_1, &reserve_config, &reserve_data);
+
+        let (underlying_2, _) = testutils::create_token_contract(&e, &bombadil);
+        let (mut reserve_config, mut reserve_data) = testutils::default_reserve_meta();
+        reserve_config.decimals = 6;
+        reserve_config.index = 2;
+        reserve_data.b_supply = 10_000_000;
+        reserve_data.d_supply = 5_000_000;
+        reserve_data.b_rate = 1_001_100_000;
+        reserve_data.d_rate = 1_001_200_000;
+        testutils::create_reserve(&e, &pool, &underlying_2, &reserve_config, &reserve_data);
+
+        oracle_client.set_data(
+            &bombadil,
+            &Asset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                Asset::Stellar(underlying_0),
+                Asset::Stellar(underlying_1),
+                Asset::Stellar(underlying_2),
+            ],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 1_0000000, 2_5000000, 1000_0000000]);
+
+        e.ledger().set(LedgerInfo {
+            timestamp: 0,
+            protocol_version: 20,
+            sequence_number: 1234,
+            network_id: Default::default(),
+            base_reserve: 10,
+            min_temp_entry_ttl: 10,
+            min_persistent_entry_ttl: 10,
+            max_entry_ttl: 3110400,
+        });
+        let pool_config = PoolConfig {
+            oracle,
+            bstop_rate: 0_2000000,
+            status: 0,
+            max_positions: 5,
+            max_price_age: 86400,
+            auction_config: Default::default(),
+            status_config: Default::default(),
+        };
+
+        let positions = Positions {
+            liabilities: map![&e, (0, 1_5000000), (1, 50_987_654_321)],
+            collateral: map![&e, (0, 100_1234567), (2, 0_250_000)],
+            supply: map![&e, (1, 120_987_654_321)],
+        };
+        let no_liabilities = Positions {
+            liabilities: map![&e],
+            collateral: map![&e, (0, 100_1234567)],
+            supply: map![&e],
+        };
+        e.as_contract(&pool, || {
+            storage::set_pool_config(&e, &pool_config);
+            storage::set_user_positions(&e, &samwise, &positions);
+            storage::set_user_positions(&e, &frodo, &no_liabilities);
+
+            let position_data = load_user_position_data(&e, &samwise);
+            assert_eq!(position_data.collateral_base, 262_7985925);
+            assert_eq!(position_data.liability_base, 185_2368828);
+            assert_eq!(position_data.collateral_raw, 350_3984567);
+            assert_eq!(position_data.liability_raw, 148_0895062);
+            assert_eq!(position_data.scalar, SCALAR_7);
+            assert_eq!(position_data.health_factor, 1_4187163);
+
+            let position_data = load_user_position_data(&e, &frodo);
+            assert_eq!(position_data.liability_base, 0);
+            assert_eq!(position_data.health_factor, i128::MAX);
+        });
+    }
+}
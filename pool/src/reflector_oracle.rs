@@ -0,0 +1,325 @@
+use sep_40_oracle::{Asset, PriceFeedClient};
+use soroban_fixed_point_math::SorobanFixedPoint;
+use soroban_sdk::{Address, Env, Map, Vec};
+
+use crate::storage::{self, OracleAsset, OracleSource};
+
+/// Value an amount of a reserve's underlying asset in USDC based on the reserve's oracle routing.
+///
+/// Returns 0 if the reserve has no oracle route, the routed price feed is not set, or the
+/// price feed fails or has no price for the asset. Valuation is only used for indexing, so
+/// an oracle outage never reverts the action being indexed.
+///
+/// ### Arguments
+/// * `asset` - The address of the underlying asset
+/// * `amount` - The amount of the underlying asset to value
+pub fn get_token_amount_in_usdc_value(e: &Env, asset: &Address, amount: i128) -> i128 {
+    let oracle_config = match storage::get_res_oracle(e, asset) {
+        Some(oracle_config) => oracle_config,
+        None => return 0,
+    };
+    let oracle = match oracle_config.source {
+        OracleSource::Offchain => match storage::get_reflector_feeds(e) {
+            Some(feeds) => feeds.offchain,
+            None => return 0,
+        },
+        OracleSource::Pubnet => match storage::get_reflector_feeds(e) {
+            Some(feeds) => feeds.pubnet,
+            None => return 0,
+        },
+        OracleSource::Custom(address) => address,
+    };
+    let oracle_client = PriceFeedClient::new(e, &oracle);
+    let decimals = match oracle_client.try_decimals() {
+        Ok(Ok(decimals)) => decimals,
+        _ => return 0,
+    };
+
+    let oracle_asset = match oracle_config.asset {
+        OracleAsset::Stellar(address) => Asset::Stellar(address),
+        OracleAsset::Other(symbol) => Asset::Other(symbol),
+    };
+    let price = match oracle_client.try_lastprice(&oracle_asset) {
+        Ok(Ok(Some(price_data))) => price_data.price,
+        _ => return 0,
+    };
+    price.fixed_mul_ceil(e, amount, 10i128.pow(decimals))
+}
+
+/// Split a map of asset amounts into its assets, amounts and the USDC value of each amount.
+///
+/// ### Arguments
+/// * `amounts` - The amounts of each asset
+pub fn split_amounts_with_usdc_value(
+    e: &Env,
+    amounts: &Map<Address, i128>,
+) -> (Vec<Address>, Vec<i128>, Vec<i128>) {
+    let mut assets = Vec::new(e);
+    let mut asset_amounts = Vec::new(e);
+    let mut usdc_amounts = Vec::new(e);
+    for (asset, amount) in amounts.iter() {
+        usdc_amounts.push_back(get_token_amount_in_usdc_value(e, &asset, amount));
+        assets.push_back(asset);
+        asset_amounts.push_back(amount);
+    }
+    (assets, asset_amounts, usdc_amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::{ReflectorFeeds, ReserveOracleConfig},
+        testutils,
+    };
+    use sep_40_oracle::testutils::Asset as MockAsset;
+    use soroban_sdk::{map, testutils::Address as _, vec, Symbol};
+
+    #[test]
+    fn test_get_token_amount_in_usdc_value_pubnet_route() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, MockAsset::Stellar(asset.clone())],
+            &14,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 2_00000000000000]);
+
+        e.as_contract(&pool, || {
+            storage::set_res_oracle(
+                &e,
+                &asset,
+                &ReserveOracleConfig {
+                    source: OracleSource::Pubnet,
+                    asset: OracleAsset::Stellar(asset.clone()),
+                },
+            );
+            // no feeds set
+            assert_eq!(get_token_amount_in_usdc_value(&e, &asset, 10_0000000), 0);
+
+            storage::set_reflector_feeds(
+                &e,
+                &ReflectorFeeds {
+                    offchain: Address::generate(&e),
+                    pubnet: oracle.clone(),
+                },
+            );
+            assert_eq!(
+                get_token_amount_in_usdc_value(&e, &asset, 10_0000000),
+                20_0000000
+            );
+        });
+    }
+
+    #[test]
+    fn test_get_token_amount_in_usdc_value_custom_route() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, MockAsset::Other(Symbol::new(&e, "BTC"))],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 60_000_0000000]);
+
+        e.as_contract(&pool, || {
+            storage::set_res_oracle(
+                &e,
+                &asset,
+                &ReserveOracleConfig {
+                    source: OracleSource::Custom(oracle.clone()),
+                    asset: OracleAsset::Other(Symbol::new(&e, "BTC")),
+                },
+            );
+            assert_eq!(
+                get_token_amount_in_usdc_value(&e, &asset, 0_5000000),
+                30_000_0000000
+            );
+        });
+    }
+
+    #[test]
+    fn test_get_token_amount_in_usdc_value_offchain_route_queries_decimals() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, MockAsset::Other(Symbol::new(&e, "BTC"))],
+            &7,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 60_000_0000000]);
+
+        e.as_contract(&pool, || {
+            storage::set_reflector_feeds(
+                &e,
+                &ReflectorFeeds {
+                    offchain: oracle.clone(),
+                    pubnet: Address::generate(&e),
+                },
+            );
+            storage::set_res_oracle(
+                &e,
+                &asset,
+                &ReserveOracleConfig {
+                    source: OracleSource::Offchain,
+                    asset: OracleAsset::Other(Symbol::new(&e, "BTC")),
+                },
+            );
+            assert_eq!(
+                get_token_amount_in_usdc_value(&e, &asset, 0_5000000),
+                30_000_0000000
+            );
+        });
+    }
+
+    #[test]
+    fn test_get_token_amount_in_usdc_value_no_route() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![&e, MockAsset::Stellar(asset.clone())],
+            &14,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 2_00000000000000]);
+
+        e.as_contract(&pool, || {
+            storage::set_reflector_feeds(
+                &e,
+                &ReflectorFeeds {
+                    offchain: oracle.clone(),
+                    pubnet: oracle.clone(),
+                },
+            );
+            assert_eq!(get_token_amount_in_usdc_value(&e, &asset, 10_0000000), 0);
+        });
+    }
+
+    #[test]
+    fn test_get_token_amount_in_usdc_value_oracle_fails() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        let pool = testutils::create_pool(&e);
+        let asset = Address::generate(&e);
+        let missing_oracle = Address::generate(&e);
+
+        e.as_contract(&pool, || {
+            storage::set_reflector_feeds(
+                &e,
+                &ReflectorFeeds {
+                    offchain: missing_oracle.clone(),
+                    pubnet: missing_oracle.clone(),
+                },
+            );
+            storage::set_res_oracle(
+                &e,
+                &asset,
+                &ReserveOracleConfig {
+                    source: OracleSource::Pubnet,
+                    asset: OracleAsset::Stellar(asset.clone()),
+                },
+            );
+            assert_eq!(get_token_amount_in_usdc_value(&e, &asset, 10_0000000), 0);
+
+            storage::set_res_oracle(
+                &e,
+                &asset,
+                &ReserveOracleConfig {
+                    source: OracleSource::Custom(missing_oracle.clone()),
+                    asset: OracleAsset::Stellar(asset.clone()),
+                },
+            );
+            assert_eq!(get_token_amount_in_usdc_value(&e, &asset, 10_0000000), 0);
+        });
+    }
+
+    #[test]
+    fn test_split_amounts_with_usdc_value() {
+        let e = Env::default();
+        e.mock_all_auths_allowing_non_root_auth();
+
+        let bombadil = Address::generate(&e);
+        let pool = testutils::create_pool(&e);
+        let asset_0 = Address::generate(&e);
+        let asset_1 = Address::generate(&e);
+        let (oracle, oracle_client) = testutils::create_mock_oracle(&e);
+        oracle_client.set_data(
+            &bombadil,
+            &MockAsset::Other(Symbol::new(&e, "USD")),
+            &vec![
+                &e,
+                MockAsset::Stellar(asset_0.clone()),
+                MockAsset::Stellar(asset_1.clone()),
+            ],
+            &14,
+            &300,
+        );
+        oracle_client.set_price_stable(&vec![&e, 2_00000000000000, 0_50000000000000]);
+
+        e.as_contract(&pool, || {
+            storage::set_reflector_feeds(
+                &e,
+                &ReflectorFeeds {
+                    offchain: Address::generate(&e),
+                    pubnet: oracle.clone(),
+                },
+            );
+            for asset in [asset_0.clone(), asset_1.clone()] {
+                storage::set_res_oracle(
+                    &e,
+                    &asset,
+                    &ReserveOracleConfig {
+                        source: OracleSource::Pubnet,
+                        asset: OracleAsset::Stellar(asset.clone()),
+                    },
+                );
+            }
+            let amounts = map![
+                &e,
+                (asset_0.clone(), 10_0000000),
+                (asset_1.clone(), 4_0000000)
+            ];
+            let (assets, asset_amounts, usdc_amounts) = split_amounts_with_usdc_value(&e, &amounts);
+            assert_eq!(assets, amounts.keys());
+            assert_eq!(asset_amounts, amounts.values());
+            for i in 0..assets.len() {
+                let expected = if assets.get_unchecked(i) == asset_0 {
+                    20_0000000
+                } else {
+                    2_0000000
+                };
+                assert_eq!(usdc_amounts.get_unchecked(i), expected);
+            }
+        });
+    }
+}
@@ -24,10 +24,75 @@ const LEDGER_BUMP_USER: u32 = LEDGER_THRESHOLD_USER + 20 * ONE_DAY_LEDGERS; // ~
 #[derive(Clone)]
 #[contracttype]
 pub struct PoolConfig {
-    pub oracle: Address,    // the contract address of the oracle
+    pub oracle: Address,               // the contract address of the oracle
     pub bstop_rate: u32, // the rate the backstop takes on accrued debt interest, expressed in 7 decimals
     pub status: u32,     // the status of the pool
     pub max_positions: u32, // the maximum number of effective positions (collateral + liabilities) a single user can hold
+    pub max_price_age: u64, // the maximum age of an oracle price in seconds before it is considered stale
+    pub auction_config: AuctionConfig, // the Dutch auction curves of the pool
+    pub status_config: StatusConfig, // the backstop thresholds that drive the pool's status
+}
+
+/// The Dutch auction curve an auction is scaled by as blocks pass
+#[derive(Clone)]
+#[contracttype]
+pub struct AuctionCurve {
+    pub ramp: u32,                  // the number of blocks for the lot to ramp up to 100%
+    pub decay: u32, // the number of blocks for the bid to decay to 0% once the lot has ramped up
+    pub step: u32,  // the number of blocks between each change of the lot and bid modifiers
+    pub start_premium: Option<u32>, // the premium over the full auction price the lot ramp starts at, expressed in 7 decimals. If None, the lot ramps up from 0%
+}
+
+impl Default for AuctionCurve {
+    fn default() -> Self {
+        AuctionCurve {
+            ramp: 200,
+            decay: 200,
+            step: 1,
+            start_premium: None,
+        }
+    }
+}
+
+/// The auction curves for each auction type of the pool
+#[derive(Clone, Default)]
+#[contracttype]
+pub struct AuctionConfig {
+    pub liquidation: AuctionCurve, // the curve for user liquidation auctions
+    pub bad_debt: AuctionCurve,    // the curve for bad debt auctions
+    pub interest: AuctionCurve,    // the curve for interest auctions
+}
+
+/// The backstop thresholds that drive the pool's status
+#[derive(Clone)]
+#[contracttype]
+pub struct StatusConfig {
+    pub on_ice_q4w: u32, // the backstop q4w percent that puts the pool on-ice, expressed in 7 decimals
+    pub frozen_q4w: u32, // the backstop q4w percent that freezes the pool, expressed in 7 decimals
+    pub admin_on_ice_q4w: u32, // the backstop q4w percent that puts an admin active pool on-ice, expressed in 7 decimals
+    pub admin_frozen_q4w: u32, // the backstop q4w percent that freezes an admin on-ice pool, expressed in 7 decimals
+    pub backstop_pc: i128, // the product constant of the backstop's BLND and USDC in whole tokens required for the pool to be active
+}
+
+impl Default for StatusConfig {
+    #[allow(clippy::zero_prefixed_literal)]
+    fn default() -> Self {
+        StatusConfig {
+            on_ice_q4w: 0_3000000,
+            frozen_q4w: 0_6000000,
+            admin_on_ice_q4w: 0_5000000,
+            admin_frozen_q4w: 0_7500000,
+            backstop_pc: 200_000,
+        }
+    }
+}
+
+/// A pool config update queued behind the timelock
+#[derive(Clone)]
+#[contracttype]
+pub struct QueuedPoolUpdate {
+    pub new_config: PoolConfig,
+    pub unlock_time: u64,
 }
 
 /// The pool's emission config
@@ -42,17 +107,75 @@ pub struct PoolEmissionConfig {
 #[derive(Clone)]
 #[contracttype]
 pub struct ReserveConfig {
-    pub index: u32,      // the index of the reserve in the list
-    pub decimals: u32,   // the decimals used in both the bToken and underlying contract
-    pub c_factor: u32,   // the collateral factor for the reserve scaled expressed in 7 decimals
-    pub l_factor: u32,   // the liability factor for the reserve scaled expressed in 7 decimals
-    pub util: u32,       // the target utilization rate scaled expressed in 7 decimals
-    pub max_util: u32,   // the maximum allowed utilization rate scaled expressed in 7 decimals
+    pub index: u32,            // the index of the reserve in the list
+    pub decimals: u32,         // the decimals used in both the bToken and underlying contract
+    pub c_factor: u32, // the collateral factor for the reserve scaled expressed in 7 decimals
+    pub l_factor: u32, // the liability factor for the reserve scaled expressed in 7 decimals
+    pub util: u32,     // the target utilization rate scaled expressed in 7 decimals
+    pub max_util: u32, // the maximum allowed utilization rate scaled expressed in 7 decimals
+    pub supply_cap: i128, // the maximum total supply in underlying tokens, or 0 for no cap
+    pub borrow_cap: i128, // the maximum total debt in underlying tokens, or 0 for no cap
+    pub flash_fee: u32, // the fee charged on flash loans expressed in 7 decimals
+    pub isolated: bool, // if the reserve can only be used as collateral in isolation
+    pub debt_ceiling: i128, // the debt ceiling in isolation valued in the oracle's base asset, or 0 for no ceiling
+    pub isolated_borrow: bool, // if the reserve can be borrowed against isolated collateral
     pub r_base: u32, // the R0 value (base rate) in the interest rate formula scaled expressed in 7 decimals
     pub r_one: u32,  // the R1 value in the interest rate formula scaled expressed in 7 decimals
     pub r_two: u32,  // the R2 value in the interest rate formula scaled expressed in 7 decimals
     pub r_three: u32, // the R3 value in the interest rate formula scaled expressed in 7 decimals
     pub reactivity: u32, // the reactivity constant for the reserve scaled expressed in 7 decimals
+    pub rate_model: u32, // the interest rate model for the reserve
+    pub kink: u32, // the utilization where the two slope model's second slope begins expressed in 7 decimals
+}
+
+/// The pricing configuration for a reserve asset
+#[derive(Clone)]
+#[contracttype]
+pub struct ReservePriceConfig {
+    pub max_age: u64, // the maximum age of a price in seconds, or 0 to use the pool's `max_price_age`
+    pub oracles: Vec<Address>, // the SEP-40 oracles to aggregate in priority order, or empty to use the pool's oracle
+    pub min_quorum: u32,       // the minimum number of fresh prices required to price the asset
+    pub max_deviation: u32, // the maximum deviation of any fresh price from the median expressed in 7 decimals, or 0 for no bound
+    pub twap_records: u32, // the number of oracle records to average into a TWAP, or 0 to use the spot price only
+    pub twap_borrow: bool, // if borrow health checks value collateral at min(spot, TWAP) and liabilities at max(spot, TWAP)
+    pub twap_liquidation: bool, // if liquidation checks value collateral at max(spot, TWAP) and liabilities at min(spot, TWAP)
+}
+
+/// The price feed a reserve asset is valued against
+#[cfg(feature = "retroshades")]
+#[derive(Clone)]
+#[contracttype]
+pub enum OracleSource {
+    Offchain,        // the Reflector feed sourcing prices from external exchanges
+    Pubnet,          // the Reflector feed sourcing prices from the Stellar DEX
+    Custom(Address), // a SEP-40 compatible oracle contract
+}
+
+/// The asset key a price feed quotes a reserve asset by
+#[cfg(feature = "retroshades")]
+#[derive(Clone)]
+#[contracttype]
+pub enum OracleAsset {
+    Stellar(Address), // a Stellar Classic or Soroban asset
+    Other(Symbol),    // an external asset symbol (e.g. "BTC")
+}
+
+/// The oracle routing for a reserve asset
+#[cfg(feature = "retroshades")]
+#[derive(Clone)]
+#[contracttype]
+pub struct ReserveOracleConfig {
+    pub source: OracleSource, // the price feed to query
+    pub asset: OracleAsset,   // the asset key to query the price feed with
+}
+
+/// The Reflector price feeds available to `OracleSource::Offchain` and `OracleSource::Pubnet`
+#[cfg(feature = "retroshades")]
+#[derive(Clone)]
+#[contracttype]
+pub struct ReflectorFeeds {
+    pub offchain: Address, // the contract address of the Reflector offchain feed
+    pub pubnet: Address,   // the contract address of the Reflector pubnet feed
 }
 
 #[derive(Clone)]
@@ -62,6 +185,41 @@ pub struct QueuedReserveInit {
     pub unlock_time: u64,
 }
 
+/// An efficiency mode category of correlated reserves
+#[derive(Clone)]
+#[contracttype]
+pub struct EModeCategory {
+    pub c_factor: u32, // the collateral factor applied to the category's reserves expressed in 7 decimals
+    pub l_factor: u32, // the liability factor applied to the category's reserves expressed in 7 decimals
+    pub assets: Vec<Address>, // the underlying assets of the reserves in the category
+}
+
+/// The debt a user's isolated collateral backs
+#[derive(Clone)]
+#[contracttype]
+pub struct UserIsolatedDebt {
+    pub asset: Address, // the underlying asset of the isolated collateral
+    pub debt: i128, // the user's liabilities valued in the oracle's base asset as of their last update
+}
+
+/// An amount of a reserve a delegatee can borrow against an owner's collateral
+#[derive(Clone)]
+#[contracttype]
+pub struct BorrowAllowance {
+    pub amount: i128,           // the amount of underlying tokens that can be borrowed
+    pub expiration_ledger: u32, // the last ledger the allowance can be used
+}
+
+/// The schedule to sunset a reserve
+#[derive(Clone)]
+#[contracttype]
+pub struct ReserveSunset {
+    pub unlock_time: u64, // the time the sunset can start
+    pub duration: u64,    // the time to ramp the collateral factor down to zero in seconds
+    pub c_factor: u32,    // the collateral factor when the sunset started expressed in 7 decimals
+    pub start_time: u64,  // the time the sunset started, or 0 if it has not started
+}
+
 /// The data for a reserve asset
 #[derive(Clone)]
 #[contracttype]
@@ -72,6 +230,7 @@ pub struct ReserveData {
     pub b_supply: i128, // the total supply of b tokens
     pub d_supply: i128, // the total supply of d tokens
     pub backstop_credit: i128, // the amount of underlying tokens currently owed to the backstop
+    pub flags: u32,   // the status flags that disable actions for the reserve
     pub last_time: u64, // the last block the data was updated
 }
 
@@ -105,12 +264,21 @@ pub struct UserEmissionData {
 
 const IS_INIT_KEY: &str = "IsInit";
 const ADMIN_KEY: &str = "Admin";
+const PENDING_ADMIN_KEY: &str = "PendAdmin";
+const RISK_MANAGER_KEY: &str = "RiskMgr";
+const EMIS_MANAGER_KEY: &str = "EmisMgr";
+const GUARDIAN_KEY: &str = "Guardian";
 const NAME_KEY: &str = "Name";
 const BACKSTOP_KEY: &str = "Backstop";
+const BAD_DEBT_FLOOR_KEY: &str = "BDFloor";
 const BLND_TOKEN_KEY: &str = "BLNDTkn";
 const POOL_CONFIG_KEY: &str = "Config";
+const POOL_UPDATE_KEY: &str = "PoolUpd";
 const RES_LIST_KEY: &str = "ResList";
+const RES_RETIRED_KEY: &str = "ResRetired";
 const POOL_EMIS_KEY: &str = "PoolEmis";
+#[cfg(feature = "retroshades")]
+const REFLECTOR_KEY: &str = "Reflector";
 
 #[derive(Clone)]
 #[contracttype]
@@ -126,6 +294,21 @@ pub struct AuctionKey {
     auct_type: u32, // the type of auction taking place
 }
 
+#[derive(Clone)]
+#[contracttype]
+pub struct OperatorKey {
+    owner: Address,    // the Address whose positions can be managed
+    operator: Address, // the Address approved to manage the positions
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceKey {
+    owner: Address,     // the Address whose collateral backs the borrow
+    delegatee: Address, // the Address approved to borrow
+    asset: Address,     // the underlying asset that can be borrowed
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub enum PoolDataKey {
@@ -135,6 +318,10 @@ pub enum PoolDataKey {
     ResInit(Address),
     // A map of underlying asset's contract address to reserve data
     ResData(Address),
+    // A map of underlying asset's contract address to reserve oracle config
+    ResOracle(Address),
+    // A map of underlying asset's contract address to reserve price config
+    ResPrice(Address),
     // The reserve's emission config
     EmisConfig(u32),
     // The reserve's emission data
@@ -147,6 +334,20 @@ pub enum PoolDataKey {
     Auction(AuctionKey),
     // A list of auctions and their associated data
     AuctData(Address),
+    // The efficiency mode category config
+    EMode(u32),
+    // The efficiency mode category a user has opted into
+    UserEMode(Address),
+    // The total debt backed by an isolated reserve
+    IsoDebt(Address),
+    // The debt backed by a user's isolated collateral
+    UserIsoDebt(Address),
+    // The expiration ledger of an operator approval
+    Operator(OperatorKey),
+    // A borrow allowance granted by an owner to a delegatee
+    BorrowAllow(AllowanceKey),
+    // A map of underlying asset's contract address to reserve sunset schedule
+    ResSunset(Address),
 }
 
 /********** Storage **********/
@@ -245,6 +446,84 @@ pub fn set_admin(e: &Env, new_admin: &Address) {
         .set::<Symbol, Address>(&Symbol::new(e, ADMIN_KEY), new_admin);
 }
 
+/// Fetch the Address proposed to become the admin, if any
+pub fn get_pending_admin(e: &Env) -> Option<Address> {
+    e.storage()
+        .instance()
+        .get(&Symbol::new(e, PENDING_ADMIN_KEY))
+}
+
+/// Set the Address proposed to become the admin
+///
+/// ### Arguments
+/// * `pending_admin` - The Address proposed to become the admin
+pub fn set_pending_admin(e: &Env, pending_admin: &Address) {
+    e.storage()
+        .instance()
+        .set::<Symbol, Address>(&Symbol::new(e, PENDING_ADMIN_KEY), pending_admin);
+}
+
+/// Remove the Address proposed to become the admin
+pub fn del_pending_admin(e: &Env) {
+    e.storage()
+        .instance()
+        .remove(&Symbol::new(e, PENDING_ADMIN_KEY));
+}
+
+/// Fetch the risk manager Address, or the admin if no risk manager is set
+pub fn get_risk_manager(e: &Env) -> Address {
+    e.storage()
+        .instance()
+        .get(&Symbol::new(e, RISK_MANAGER_KEY))
+        .unwrap_or_else(|| get_admin(e))
+}
+
+/// Set a new risk manager
+///
+/// ### Arguments
+/// * `risk_manager` - The Address for the risk manager
+pub fn set_risk_manager(e: &Env, risk_manager: &Address) {
+    e.storage()
+        .instance()
+        .set::<Symbol, Address>(&Symbol::new(e, RISK_MANAGER_KEY), risk_manager);
+}
+
+/// Fetch the emissions manager Address, or the admin if no emissions manager is set
+pub fn get_emissions_manager(e: &Env) -> Address {
+    e.storage()
+        .instance()
+        .get(&Symbol::new(e, EMIS_MANAGER_KEY))
+        .unwrap_or_else(|| get_admin(e))
+}
+
+/// Set a new emissions manager
+///
+/// ### Arguments
+/// * `emissions_manager` - The Address for the emissions manager
+pub fn set_emissions_manager(e: &Env, emissions_manager: &Address) {
+    e.storage()
+        .instance()
+        .set::<Symbol, Address>(&Symbol::new(e, EMIS_MANAGER_KEY), emissions_manager);
+}
+
+/// Fetch the guardian Address, or the admin if no guardian is set
+pub fn get_guardian(e: &Env) -> Address {
+    e.storage()
+        .instance()
+        .get(&Symbol::new(e, GUARDIAN_KEY))
+        .unwrap_or_else(|| get_admin(e))
+}
+
+/// Set a new guardian
+///
+/// ### Arguments
+/// * `guardian` - The Address for the guardian
+pub fn set_guardian(e: &Env, guardian: &Address) {
+    e.storage()
+        .instance()
+        .set::<Symbol, Address>(&Symbol::new(e, GUARDIAN_KEY), guardian);
+}
+
 /********** Metadata **********/
 
 /// Set a pool name
@@ -280,6 +559,25 @@ pub fn set_backstop(e: &Env, backstop: &Address) {
         .set::<Symbol, Address>(&Symbol::new(e, BACKSTOP_KEY), backstop);
 }
 
+/// Fetch the backstop token balance below which the backstop's bad debt can be defaulted.
+/// Defaults to 0, which disables defaulting.
+pub fn get_bad_debt_floor(e: &Env) -> i128 {
+    e.storage()
+        .instance()
+        .get(&Symbol::new(e, BAD_DEBT_FLOOR_KEY))
+        .unwrap_or(0)
+}
+
+/// Set the backstop token balance below which the backstop's bad debt can be defaulted
+///
+/// ### Arguments
+/// * `floor` - The backstop token balance
+pub fn set_bad_debt_floor(e: &Env, floor: &i128) {
+    e.storage()
+        .instance()
+        .set::<Symbol, i128>(&Symbol::new(e, BAD_DEBT_FLOOR_KEY), floor);
+}
+
 /********** External Token Contracts **********/
 
 /// Fetch the BLND token ID
@@ -323,6 +621,34 @@ pub fn set_pool_config(e: &Env, config: &PoolConfig) {
         .set::<Symbol, PoolConfig>(&Symbol::new(e, POOL_CONFIG_KEY), config);
 }
 
+/// Fetch the queued pool config update, if any
+pub fn get_queued_pool_update(e: &Env) -> Option<QueuedPoolUpdate> {
+    e.storage()
+        .temporary()
+        .get::<Symbol, QueuedPoolUpdate>(&Symbol::new(e, POOL_UPDATE_KEY))
+}
+
+/// Set the queued pool config update
+///
+/// ### Arguments
+/// * `pool_update` - The queued pool config update
+pub fn set_queued_pool_update(e: &Env, pool_update: &QueuedPoolUpdate) {
+    let key = Symbol::new(e, POOL_UPDATE_KEY);
+    e.storage()
+        .temporary()
+        .set::<Symbol, QueuedPoolUpdate>(&key, pool_update);
+    e.storage()
+        .temporary()
+        .extend_ttl(&key, LEDGER_THRESHOLD_USER, LEDGER_BUMP_USER);
+}
+
+/// Delete the queued pool config update
+pub fn del_queued_pool_update(e: &Env) {
+    e.storage()
+        .temporary()
+        .remove(&Symbol::new(e, POOL_UPDATE_KEY));
+}
+
 /********** Reserve Config (ResConfig) **********/
 
 /// Fetch the reserve data for an asset
@@ -453,6 +779,319 @@ pub fn set_res_data(e: &Env, asset: &Address, data: &ReserveData) {
         .extend_ttl(&key, LEDGER_THRESHOLD_SHARED, LEDGER_BUMP_SHARED);
 }
 
+/********** Reserve Oracle (ResOracle) **********/
+
+/// Fetch the oracle routing for an asset, if one has been set
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+#[cfg(feature = "retroshades")]
+pub fn get_res_oracle(e: &Env, asset: &Address) -> Option<ReserveOracleConfig> {
+    let key = PoolDataKey::ResOracle(asset.clone());
+    get_persistent_default(
+        e,
+        &key,
+        || None,
+        LEDGER_THRESHOLD_SHARED,
+        LEDGER_BUMP_SHARED,
+    )
+}
+
+/// Set the oracle routing for an asset
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+/// * `config` - The oracle routing for the asset
+#[cfg(feature = "retroshades")]
+pub fn set_res_oracle(e: &Env, asset: &Address, config: &ReserveOracleConfig) {
+    let key = PoolDataKey::ResOracle(asset.clone());
+    e.storage()
+        .persistent()
+        .set::<PoolDataKey, ReserveOracleConfig>(&key, config);
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, LEDGER_THRESHOLD_SHARED, LEDGER_BUMP_SHARED);
+}
+
+/********** Reserve Price (ResPrice) **********/
+
+/// Fetch the price config for an asset, if one has been set
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+pub fn get_res_price_config(e: &Env, asset: &Address) -> Option<ReservePriceConfig> {
+    let key = PoolDataKey::ResPrice(asset.clone());
+    get_persistent_default(
+        e,
+        &key,
+        || None,
+        LEDGER_THRESHOLD_SHARED,
+        LEDGER_BUMP_SHARED,
+    )
+}
+
+/// Set the price config for an asset
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+/// * `config` - The price config for the asset
+pub fn set_res_price_config(e: &Env, asset: &Address, config: &ReservePriceConfig) {
+    let key = PoolDataKey::ResPrice(asset.clone());
+    e.storage()
+        .persistent()
+        .set::<PoolDataKey, ReservePriceConfig>(&key, config);
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, LEDGER_THRESHOLD_SHARED, LEDGER_BUMP_SHARED);
+}
+
+/********** Isolation **********/
+
+/// Fetch the total debt backed by an isolated reserve, valued in the oracle's base asset
+///
+/// ### Arguments
+/// * `asset` - The contract address of the isolated asset
+pub fn get_isolated_debt(e: &Env, asset: &Address) -> i128 {
+    let key = PoolDataKey::IsoDebt(asset.clone());
+    get_persistent_default(
+        e,
+        &key,
+        || 0i128,
+        LEDGER_THRESHOLD_SHARED,
+        LEDGER_BUMP_SHARED,
+    )
+}
+
+/// Set the total debt backed by an isolated reserve, valued in the oracle's base asset
+///
+/// ### Arguments
+/// * `asset` - The contract address of the isolated asset
+/// * `debt` - The total debt
+pub fn set_isolated_debt(e: &Env, asset: &Address, debt: i128) {
+    let key = PoolDataKey::IsoDebt(asset.clone());
+    e.storage()
+        .persistent()
+        .set::<PoolDataKey, i128>(&key, &debt);
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, LEDGER_THRESHOLD_SHARED, LEDGER_BUMP_SHARED);
+}
+
+/// Fetch the debt backed by a user's isolated collateral, if any
+///
+/// ### Arguments
+/// * `user` - The address of the user
+pub fn get_user_isolated_debt(e: &Env, user: &Address) -> Option<UserIsolatedDebt> {
+    let key = PoolDataKey::UserIsoDebt(user.clone());
+    get_persistent_default(e, &key, || None, LEDGER_THRESHOLD_USER, LEDGER_BUMP_USER)
+}
+
+/// Set the debt backed by a user's isolated collateral
+///
+/// ### Arguments
+/// * `user` - The address of the user
+/// * `debt` - The debt backed by the user's isolated collateral, or None to remove it
+pub fn set_user_isolated_debt(e: &Env, user: &Address, debt: &Option<UserIsolatedDebt>) {
+    let key = PoolDataKey::UserIsoDebt(user.clone());
+    match debt {
+        Some(debt) => {
+            e.storage()
+                .persistent()
+                .set::<PoolDataKey, UserIsolatedDebt>(&key, debt);
+            e.storage()
+                .persistent()
+                .extend_ttl(&key, LEDGER_THRESHOLD_USER, LEDGER_BUMP_USER);
+        }
+        None => e.storage().persistent().remove(&key),
+    }
+}
+
+/********** E-Mode **********/
+
+/// Fetch an efficiency mode category, if it exists
+///
+/// ### Arguments
+/// * `id` - The id of the category
+pub fn get_emode_category(e: &Env, id: u32) -> Option<EModeCategory> {
+    let key = PoolDataKey::EMode(id);
+    get_persistent_default(
+        e,
+        &key,
+        || None,
+        LEDGER_THRESHOLD_SHARED,
+        LEDGER_BUMP_SHARED,
+    )
+}
+
+/// Set an efficiency mode category
+///
+/// ### Arguments
+/// * `id` - The id of the category
+/// * `category` - The category config
+pub fn set_emode_category(e: &Env, id: u32, category: &EModeCategory) {
+    let key = PoolDataKey::EMode(id);
+    e.storage()
+        .persistent()
+        .set::<PoolDataKey, EModeCategory>(&key, category);
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, LEDGER_THRESHOLD_SHARED, LEDGER_BUMP_SHARED);
+}
+
+/// Fetch the efficiency mode category a user has opted into, or 0 if none
+///
+/// ### Arguments
+/// * `user` - The address of the user
+pub fn get_user_emode(e: &Env, user: &Address) -> u32 {
+    let key = PoolDataKey::UserEMode(user.clone());
+    get_persistent_default(e, &key, || 0, LEDGER_THRESHOLD_USER, LEDGER_BUMP_USER)
+}
+
+/// Set the efficiency mode category a user has opted into
+///
+/// ### Arguments
+/// * `user` - The address of the user
+/// * `id` - The id of the category, or 0 to opt out
+pub fn set_user_emode(e: &Env, user: &Address, id: u32) {
+    let key = PoolDataKey::UserEMode(user.clone());
+    if id == 0 {
+        e.storage().persistent().remove(&key);
+        return;
+    }
+    e.storage().persistent().set::<PoolDataKey, u32>(&key, &id);
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, LEDGER_THRESHOLD_USER, LEDGER_BUMP_USER);
+}
+
+/********** Delegation **********/
+
+/// Fetch the expiration ledger of an operator approval, if it exists
+///
+/// ### Arguments
+/// * `owner` - The address whose positions can be managed
+/// * `operator` - The address approved to manage the positions
+pub fn get_operator(e: &Env, owner: &Address, operator: &Address) -> Option<u32> {
+    let key = PoolDataKey::Operator(OperatorKey {
+        owner: owner.clone(),
+        operator: operator.clone(),
+    });
+    e.storage().temporary().get::<PoolDataKey, u32>(&key)
+}
+
+/// Set an operator approval. The approval is archived after its expiration ledger.
+///
+/// ### Arguments
+/// * `owner` - The address whose positions can be managed
+/// * `operator` - The address approved to manage the positions
+/// * `expiration_ledger` - The last ledger the approval can be used
+pub fn set_operator(e: &Env, owner: &Address, operator: &Address, expiration_ledger: u32) {
+    let key = PoolDataKey::Operator(OperatorKey {
+        owner: owner.clone(),
+        operator: operator.clone(),
+    });
+    e.storage()
+        .temporary()
+        .set::<PoolDataKey, u32>(&key, &expiration_ledger);
+    let live_for = expiration_ledger - e.ledger().sequence();
+    e.storage().temporary().extend_ttl(&key, live_for, live_for);
+}
+
+/// Delete an operator approval
+///
+/// ### Arguments
+/// * `owner` - The address whose positions can be managed
+/// * `operator` - The address approved to manage the positions
+pub fn del_operator(e: &Env, owner: &Address, operator: &Address) {
+    let key = PoolDataKey::Operator(OperatorKey {
+        owner: owner.clone(),
+        operator: operator.clone(),
+    });
+    e.storage().temporary().remove(&key);
+}
+
+/// Fetch a borrow allowance, if it exists
+///
+/// ### Arguments
+/// * `owner` - The address whose collateral backs the borrow
+/// * `delegatee` - The address approved to borrow
+/// * `asset` - The underlying asset that can be borrowed
+pub fn get_borrow_allowance(
+    e: &Env,
+    owner: &Address,
+    delegatee: &Address,
+    asset: &Address,
+) -> Option<BorrowAllowance> {
+    let key = PoolDataKey::BorrowAllow(AllowanceKey {
+        owner: owner.clone(),
+        delegatee: delegatee.clone(),
+        asset: asset.clone(),
+    });
+    e.storage()
+        .temporary()
+        .get::<PoolDataKey, BorrowAllowance>(&key)
+}
+
+/// Set a borrow allowance. The allowance is archived after its expiration ledger.
+///
+/// ### Arguments
+/// * `owner` - The address whose collateral backs the borrow
+/// * `delegatee` - The address approved to borrow
+/// * `asset` - The underlying asset that can be borrowed
+/// * `allowance` - The allowance
+pub fn set_borrow_allowance(
+    e: &Env,
+    owner: &Address,
+    delegatee: &Address,
+    asset: &Address,
+    allowance: &BorrowAllowance,
+) {
+    let key = PoolDataKey::BorrowAllow(AllowanceKey {
+        owner: owner.clone(),
+        delegatee: delegatee.clone(),
+        asset: asset.clone(),
+    });
+    e.storage()
+        .temporary()
+        .set::<PoolDataKey, BorrowAllowance>(&key, allowance);
+    let live_for = allowance.expiration_ledger - e.ledger().sequence();
+    e.storage().temporary().extend_ttl(&key, live_for, live_for);
+}
+
+/// Delete a borrow allowance
+///
+/// ### Arguments
+/// * `owner` - The address whose collateral backs the borrow
+/// * `delegatee` - The address approved to borrow
+/// * `asset` - The underlying asset that can be borrowed
+pub fn del_borrow_allowance(e: &Env, owner: &Address, delegatee: &Address, asset: &Address) {
+    let key = PoolDataKey::BorrowAllow(AllowanceKey {
+        owner: owner.clone(),
+        delegatee: delegatee.clone(),
+        asset: asset.clone(),
+    });
+    e.storage().temporary().remove(&key);
+}
+
+/********** Reflector Feeds **********/
+
+/// Fetch the Reflector price feeds, if they have been set
+#[cfg(feature = "retroshades")]
+pub fn get_reflector_feeds(e: &Env) -> Option<ReflectorFeeds> {
+    e.storage().instance().get(&Symbol::new(e, REFLECTOR_KEY))
+}
+
+/// Set the Reflector price feeds
+///
+/// ### Arguments
+/// * `feeds` - The Reflector price feeds
+#[cfg(feature = "retroshades")]
+pub fn set_reflector_feeds(e: &Env, feeds: &ReflectorFeeds) {
+    e.storage()
+        .instance()
+        .set::<Symbol, ReflectorFeeds>(&Symbol::new(e, REFLECTOR_KEY), feeds);
+}
+
 /********** Reserve List (ResList) **********/
 
 /// Fetch the list of reserves
@@ -493,6 +1132,65 @@ pub fn push_res_list(e: &Env, asset: &Address) -> u32 {
     new_index
 }
 
+/********** Reserve Sunset (ResSunset) **********/
+
+/// Fetch the sunset schedule for a reserve, if one exists
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+pub fn get_res_sunset(e: &Env, asset: &Address) -> Option<ReserveSunset> {
+    let key = PoolDataKey::ResSunset(asset.clone());
+    get_persistent_default(
+        e,
+        &key,
+        || None,
+        LEDGER_THRESHOLD_SHARED,
+        LEDGER_BUMP_SHARED,
+    )
+}
+
+/// Set the sunset schedule for a reserve
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+/// * `sunset` - The sunset schedule for the reserve
+pub fn set_res_sunset(e: &Env, asset: &Address, sunset: &ReserveSunset) {
+    let key = PoolDataKey::ResSunset(asset.clone());
+    e.storage()
+        .persistent()
+        .set::<PoolDataKey, ReserveSunset>(&key, sunset);
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, LEDGER_THRESHOLD_SHARED, LEDGER_BUMP_SHARED);
+}
+
+/// Delete the sunset schedule for a reserve
+///
+/// ### Arguments
+/// * `asset` - The contract address of the asset
+pub fn del_res_sunset(e: &Env, asset: &Address) {
+    let key = PoolDataKey::ResSunset(asset.clone());
+    e.storage().persistent().remove(&key);
+}
+
+/// Fetch the indexes of the retired reserves
+pub fn get_retired_reserves(e: &Env) -> Vec<u32> {
+    e.storage()
+        .instance()
+        .get(&Symbol::new(e, RES_RETIRED_KEY))
+        .unwrap_or(vec![e])
+}
+
+/// Set the indexes of the retired reserves
+///
+/// ### Arguments
+/// * `retired` - The indexes of the retired reserves
+pub fn set_retired_reserves(e: &Env, retired: &Vec<u32>) {
+    e.storage()
+        .instance()
+        .set::<Symbol, Vec<u32>>(&Symbol::new(e, RES_RETIRED_KEY), retired);
+}
+
 /********** Reserve Emissions **********/
 
 /// Fetch the emission config for the reserve b or d token
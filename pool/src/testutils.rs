@@ -168,6 +168,12 @@ pub(crate) fn default_reserve(e: &Env) -> Reserve {
         l_factor: 0_7500000,
         c_factor: 0_7500000,
         max_util: 0_9500000,
+        supply_cap: 0,
+        borrow_cap: 0,
+        flash_fee: 0,
+        isolated: false,
+        debt_ceiling: 0,
+        isolated_borrow: false,
         last_time: 0,
         scalar: 1_0000000,
         d_rate: 1_000_000_000,
@@ -176,6 +182,7 @@ pub(crate) fn default_reserve(e: &Env) -> Reserve {
         b_supply: 100_0000000,
         d_supply: 75_0000000,
         backstop_credit: 0,
+        flags: 0,
     }
 }
 
@@ -192,6 +199,14 @@ pub(crate) fn default_reserve_meta() -> (ReserveConfig, ReserveData) {
             r_two: 0_5000000,
             r_three: 1_5000000,
             reactivity: 0_0000020, // 2e-6
+            rate_model: 0,
+            kink: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            flash_fee: 0,
+            isolated: false,
+            debt_ceiling: 0,
+            isolated_borrow: false,
             index: 0,
         },
         ReserveData {
@@ -202,6 +217,7 @@ pub(crate) fn default_reserve_meta() -> (ReserveConfig, ReserveData) {
             d_supply: 75_0000000,
             last_time: 0,
             backstop_credit: 0,
+            flags: 0,
         },
     )
 }
@@ -17,6 +17,14 @@ pub fn default_reserve_metadata() -> ReserveConfig {
         r_two: 0_5000000,
         r_three: 1_5000000,
         reactivity: 0_0000020, // 2e-6
+        rate_model: 0,
+        kink: 0,
+        supply_cap: 0,
+        borrow_cap: 0,
+        flash_fee: 0,
+        isolated: false,
+        debt_ceiling: 0,
+        isolated_borrow: false,
         index: 0,
     }
 }
@@ -1,10 +1,12 @@
 #![cfg(test)]
 use cast::i128;
-use pool::{PoolDataKey, Positions, Request, RequestType, ReserveConfig, ReserveData};
+use pool::{FlashLoan, PoolDataKey, Positions, Request, RequestType, ReserveConfig, ReserveData};
+use sep_41_token::TokenClient;
 use soroban_fixed_point_math::FixedPoint;
 use soroban_sdk::{
+    contract, contractimpl, symbol_short,
     testutils::{Address as AddressTestTrait, Events},
-    vec, Address, Error, IntoVal, Symbol, Val, Vec,
+    vec, Address, Env, Error, IntoVal, Symbol, TryFromVal, Val, Vec,
 };
 use test_suites::{
     assertions::assert_approx_eq_abs,
@@ -1124,3 +1126,413 @@ fn test_user_restore_position_and_delete_liquidation() {
     );
     assert!(pool_fixture.pool.try_get_auction(&0, &samwise).is_err());
 }
+
+#[test]
+fn test_batch_liquidations() {
+    let fixture = create_fixture_with_data(false);
+    let frodo = fixture.users.get(0).unwrap();
+    let pool_fixture = &fixture.pools[0];
+    let stable_pool_index = pool_fixture.reserves[&TokenIndex::STABLE];
+    let xlm_pool_index = pool_fixture.reserves[&TokenIndex::XLM];
+
+    // Create users that supply STABLE (cf = 90%, $1) and borrow XLM (lf = 75%, $0.10)
+    let samwise = Address::generate(&fixture.env);
+    let pippin = Address::generate(&fixture.env);
+    let merry = Address::generate(&fixture.env);
+    for (user, borrow) in [
+        (&samwise, 6075 * SCALAR_7),
+        (&pippin, 6075 * SCALAR_7),
+        (&merry, 3000 * SCALAR_7),
+    ] {
+        fixture.tokens[TokenIndex::STABLE].mint(user, &(1000 * 10i128.pow(6)));
+        let setup_request: Vec<Request> = vec![
+            &fixture.env,
+            Request {
+                request_type: RequestType::SupplyCollateral as u32,
+                address: fixture.tokens[TokenIndex::STABLE].address.clone(),
+                amount: 1000 * 10i128.pow(6),
+            },
+            Request {
+                request_type: RequestType::Borrow as u32,
+                address: fixture.tokens[TokenIndex::XLM].address.clone(),
+                amount: borrow,
+            },
+        ];
+        pool_fixture.pool.submit(user, user, user, &setup_request);
+    }
+
+    // simulate 20% XLM price increase - samwise and pippin are underwater, merry is still healthy
+    fixture.oracle.set_price_stable(&vec![
+        &fixture.env,
+        2000_0000000, // eth
+        1_0000000,    // usdc
+        0_1200000,    // xlm
+        1_0000000,    // stable
+    ]);
+
+    // validate batch limits
+    let empty_batch = pool_fixture
+        .pool
+        .try_new_liquidation_auctions(&vec![&fixture.env]);
+    assert_eq!(
+        empty_batch.err(),
+        Some(Ok(Error::from_contract_error(1200)))
+    );
+    let mut oversized_batch: Vec<(Address, u64)> = vec![&fixture.env];
+    for _ in 0..11 {
+        oversized_batch.push_back((Address::generate(&fixture.env), 50));
+    }
+    let oversized = pool_fixture
+        .pool
+        .try_new_liquidation_auctions(&oversized_batch);
+    assert_eq!(oversized.err(), Some(Ok(Error::from_contract_error(1200))));
+
+    // create liquidations for all three users - merry is skipped
+    let auctions = pool_fixture.pool.new_liquidation_auctions(&vec![
+        &fixture.env,
+        (samwise.clone(), 50),
+        (merry.clone(), 50),
+        (pippin.clone(), 50),
+    ]);
+    assert_eq!(auctions.len(), 2);
+    assert!(auctions.contains_key(samwise.clone()));
+    assert!(auctions.contains_key(pippin.clone()));
+    assert!(pool_fixture.pool.try_get_auction(&0, &samwise).is_ok());
+    assert!(pool_fixture.pool.try_get_auction(&0, &pippin).is_ok());
+    assert!(pool_fixture.pool.try_get_auction(&0, &merry).is_err());
+    let events = fixture.env.events().all();
+    let new_auction_events = events
+        .iter()
+        .filter(|event| {
+            event.0 == pool_fixture.pool.address
+                && Symbol::try_from_val(&fixture.env, &event.1.get_unchecked(0))
+                    == Ok(Symbol::new(&fixture.env, "new_liquidation_auction"))
+        })
+        .count();
+    assert_eq!(new_auction_events, 2);
+
+    // jump 100 blocks and fill both auctions in a single submit
+    fixture.jump_with_sequence(100 * 5);
+    let frodo_positions_pre = pool_fixture.pool.get_positions(&frodo);
+    let fill_requests: Vec<Request> = vec![
+        &fixture.env,
+        Request {
+            request_type: RequestType::FillUserLiquidationAuction as u32,
+            address: samwise.clone(),
+            amount: 100,
+        },
+        Request {
+            request_type: RequestType::FillUserLiquidationAuction as u32,
+            address: pippin.clone(),
+            amount: 100,
+        },
+    ];
+    let frodo_positions_post = pool_fixture
+        .pool
+        .submit(&frodo, &frodo, &frodo, &fill_requests);
+    assert!(pool_fixture.pool.try_get_auction(&0, &samwise).is_err());
+    assert!(pool_fixture.pool.try_get_auction(&0, &pippin).is_err());
+
+    // frodo took on half of each user's position
+    let sam_positions = pool_fixture.pool.get_positions(&samwise);
+    let pippin_positions = pool_fixture.pool.get_positions(&pippin);
+    let liabilities_taken = frodo_positions_post
+        .liabilities
+        .get(xlm_pool_index)
+        .unwrap_or(0)
+        - frodo_positions_pre
+            .liabilities
+            .get(xlm_pool_index)
+            .unwrap_or(0);
+    let collateral_taken = frodo_positions_post
+        .collateral
+        .get(stable_pool_index)
+        .unwrap_or(0)
+        - frodo_positions_pre
+            .collateral
+            .get(stable_pool_index)
+            .unwrap_or(0);
+    // fuzz assert wide to account for b and d rates (only verify actions occurred)
+    assert_approx_eq_abs(
+        liabilities_taken,
+        2 * 6075 * SCALAR_7
+            - sam_positions.liabilities.get_unchecked(xlm_pool_index)
+            - pippin_positions.liabilities.get_unchecked(xlm_pool_index),
+        SCALAR_7,
+    );
+    assert_approx_eq_abs(
+        collateral_taken,
+        2 * 1000 * 10i128.pow(6)
+            - sam_positions.collateral.get_unchecked(stable_pool_index)
+            - pippin_positions.collateral.get_unchecked(stable_pool_index),
+        10i128.pow(6),
+    );
+    assert!(liabilities_taken > 0);
+    assert!(collateral_taken > 0);
+}
+
+#[test]
+fn test_default_bad_debt() {
+    let fixture = create_fixture_with_data(false);
+    let pool_fixture = &fixture.pools[0];
+    let xlm_pool_index = pool_fixture.reserves[&TokenIndex::XLM];
+
+    // Create a user that is supply STABLE (cf = 90%, $1) and borrowing XLM (lf = 75%, $0.10)
+    let samwise = Address::generate(&fixture.env);
+    fixture.tokens[TokenIndex::STABLE].mint(&samwise, &(1000 * 10i128.pow(6)));
+    let setup_request: Vec<Request> = vec![
+        &fixture.env,
+        Request {
+            request_type: RequestType::SupplyCollateral as u32,
+            address: fixture.tokens[TokenIndex::STABLE].address.clone(),
+            amount: 1000 * 10i128.pow(6),
+        },
+        Request {
+            request_type: RequestType::Borrow as u32,
+            address: fixture.tokens[TokenIndex::XLM].address.clone(),
+            amount: 6075 * SCALAR_7,
+        },
+    ];
+    pool_fixture
+        .pool
+        .submit(&samwise, &samwise, &samwise, &setup_request);
+
+    // simulate a 10x XLM price increase ($6075 liabilities, $1000 collateral) and fully liquidate
+    fixture.oracle.set_price_stable(&vec![
+        &fixture.env,
+        2000_0000000, // eth
+        1_0000000,    // usdc
+        1_0000000,    // xlm
+        1_0000000,    // stable
+    ]);
+    pool_fixture.pool.new_liquidation_auction(&samwise, &100);
+
+    // fill the auction once the bid has decayed, leaving samwise with debt and no collateral
+    fixture.jump_with_sequence(380 * 5);
+    let merry = Address::generate(&fixture.env);
+    fixture.tokens[TokenIndex::STABLE].mint(&merry, &(10_000 * 10i128.pow(6)));
+    let fill_request: Vec<Request> = vec![
+        &fixture.env,
+        Request {
+            request_type: RequestType::SupplyCollateral as u32,
+            address: fixture.tokens[TokenIndex::STABLE].address.clone(),
+            amount: 10_000 * 10i128.pow(6),
+        },
+        Request {
+            request_type: RequestType::FillUserLiquidationAuction as u32,
+            address: samwise.clone(),
+            amount: 100,
+        },
+    ];
+    pool_fixture
+        .pool
+        .submit(&merry, &merry, &merry, &fill_request);
+    let sam_positions = pool_fixture.pool.get_positions(&samwise);
+    assert_eq!(sam_positions.collateral.len(), 0);
+    let bad_debt = sam_positions.liabilities.get_unchecked(xlm_pool_index);
+
+    // transfer the bad debt to the backstop
+    pool_fixture.pool.bad_debt(&samwise);
+    let backstop_positions = pool_fixture.pool.get_positions(&fixture.backstop.address);
+    assert_eq!(
+        backstop_positions.liabilities.get_unchecked(xlm_pool_index),
+        bad_debt
+    );
+
+    // validate bad debt can't be defaulted while the backstop holds tokens
+    let backstop_tokens = fixture
+        .backstop
+        .pool_data(&pool_fixture.pool.address)
+        .tokens;
+    assert!(backstop_tokens > 0);
+    let default_over_floor = pool_fixture.pool.try_default_bad_debt();
+    assert_eq!(
+        default_over_floor.err(),
+        Some(Ok(Error::from_contract_error(1231)))
+    );
+
+    // the backstop is not exhausted while its balance sits at the floor
+    pool_fixture.pool.set_bad_debt_floor(&backstop_tokens);
+    let default_at_floor = pool_fixture.pool.try_default_bad_debt();
+    assert_eq!(
+        default_at_floor.err(),
+        Some(Ok(Error::from_contract_error(1231)))
+    );
+
+    // treat the backstop as exhausted and default the bad debt
+    pool_fixture.pool.set_bad_debt_floor(&(backstop_tokens + 1));
+    let xlm_data_pre = fixture.read_reserve_data(0, TokenIndex::XLM);
+    pool_fixture.pool.default_bad_debt();
+
+    let events = fixture.env.events().all();
+    let event = vec![&fixture.env, events.get_unchecked(events.len() - 1)];
+    let xlm_data = fixture.read_reserve_data(0, TokenIndex::XLM);
+    let default_amount = bad_debt
+        .fixed_mul_ceil(xlm_data.d_rate, 1_000_000_000)
+        .unwrap();
+    assert_eq!(
+        event,
+        vec![
+            &fixture.env,
+            (
+                pool_fixture.pool.address.clone(),
+                (
+                    Symbol::new(&fixture.env, "defaulted_debt"),
+                    fixture.tokens[TokenIndex::XLM].address.clone(),
+                )
+                    .into_val(&fixture.env),
+                (bad_debt, default_amount).into_val(&fixture.env)
+            )
+        ]
+    );
+
+    // the backstop's debt is burnt and the loss is socialized to XLM suppliers
+    let backstop_positions = pool_fixture.pool.get_positions(&fixture.backstop.address);
+    assert!(backstop_positions.liabilities.get(xlm_pool_index).is_none());
+    assert_eq!(xlm_data.d_supply, xlm_data_pre.d_supply - bad_debt);
+    assert_eq!(xlm_data.b_supply, xlm_data_pre.b_supply);
+    assert_approx_eq_abs(
+        (xlm_data_pre.b_rate - xlm_data.b_rate)
+            .fixed_mul_floor(xlm_data.b_supply, 1_000_000_000)
+            .unwrap(),
+        default_amount,
+        SCALAR_7,
+    );
+    assert!(xlm_data.b_rate < xlm_data_pre.b_rate);
+
+    // the pool no longer holds bad debt, so the default can't be repeated
+    let default_again = pool_fixture.pool.try_default_bad_debt();
+    assert_eq!(
+        default_again.err(),
+        Some(Ok(Error::from_contract_error(1200)))
+    );
+}
+
+/// Flash loan receiver that swaps the lot it holds for the loaned asset with a mock dex and
+/// repays the loan and fee
+#[contract]
+pub struct SwapReceiver;
+
+#[contractimpl]
+impl SwapReceiver {
+    pub fn exec_op(e: Env, _caller: Address, asset: Address, amount: i128, fee: i128) {
+        let pool: Address = e.storage().instance().get(&symbol_short!("pool")).unwrap();
+        let dex: Address = e.storage().instance().get(&symbol_short!("dex")).unwrap();
+        let lot: Address = e.storage().instance().get(&symbol_short!("lot")).unwrap();
+
+        let receiver = e.current_contract_address();
+        let lot_client = TokenClient::new(&e, &lot);
+        lot_client.transfer(&receiver, &dex, &lot_client.balance(&receiver));
+        let asset_client = TokenClient::new(&e, &asset);
+        asset_client.transfer(&dex, &receiver, &(amount + fee));
+        asset_client.transfer(&receiver, &pool, &(amount + fee));
+    }
+
+    pub fn setup(e: Env, pool: Address, dex: Address, lot: Address) {
+        e.storage().instance().set(&symbol_short!("pool"), &pool);
+        e.storage().instance().set(&symbol_short!("dex"), &dex);
+        e.storage().instance().set(&symbol_short!("lot"), &lot);
+    }
+}
+
+#[test]
+fn test_flash_loan_fills_liquidation_without_inventory() {
+    let fixture = create_fixture_with_data(false);
+    fixture.env.mock_all_auths_allowing_non_root_auth();
+    let pool_fixture = &fixture.pools[0];
+    let stable = &fixture.tokens[TokenIndex::STABLE];
+    let xlm = &fixture.tokens[TokenIndex::XLM];
+
+    // Create a user that is supply STABLE (cf = 90%, $1) and borrowing XLM (lf = 75%, $0.10)
+    let samwise = Address::generate(&fixture.env);
+    stable.mint(&samwise, &(1000 * 10i128.pow(6)));
+    let setup_request: Vec<Request> = vec![
+        &fixture.env,
+        Request {
+            request_type: RequestType::SupplyCollateral as u32,
+            address: stable.address.clone(),
+            amount: 1000 * 10i128.pow(6),
+        },
+        Request {
+            request_type: RequestType::Borrow as u32,
+            address: xlm.address.clone(),
+            amount: 6075 * SCALAR_7,
+        },
+    ];
+    pool_fixture
+        .pool
+        .submit(&samwise, &samwise, &samwise, &setup_request);
+
+    // simulate 20% XLM price increase and create user liquidation
+    fixture.oracle.set_price_stable(&vec![
+        &fixture.env,
+        2000_0000000, // eth
+        1_0000000,    // usdc
+        0_1200000,    // xlm
+        1_0000000,    // stable
+    ]);
+    pool_fixture.pool.new_liquidation_auction(&samwise, &50);
+    fixture.jump_with_sequence(200 * 5);
+    let auction = pool_fixture.pool.get_auction(&0, &samwise);
+    let xlm_bid = auction.bid.get_unchecked(xlm.address.clone());
+
+    // the bot holds no tokens, and the dex holds the XLM it swaps for the lot
+    let bot = Address::generate(&fixture.env);
+    let dex = Address::generate(&fixture.env);
+    xlm.mint(&dex, &(10_000 * SCALAR_7));
+    let receiver = fixture.env.register_contract(None, SwapReceiver);
+    SwapReceiverClient::new(&fixture.env, &receiver).setup(
+        &pool_fixture.pool.address,
+        &dex,
+        &stable.address,
+    );
+    let pool_xlm_balance = xlm.balance(&pool_fixture.pool.address);
+
+    // loan a bit more than the bid to cover interest accrued since the auction was created
+    let loan_amount = xlm_bid + SCALAR_7;
+    let fill_requests: Vec<Request> = vec![
+        &fixture.env,
+        Request {
+            request_type: RequestType::FillUserLiquidationAuction as u32,
+            address: samwise.clone(),
+            amount: 100,
+        },
+        Request {
+            request_type: RequestType::Repay as u32,
+            address: xlm.address.clone(),
+            amount: loan_amount,
+        },
+        Request {
+            request_type: RequestType::WithdrawCollateral as u32,
+            address: stable.address.clone(),
+            amount: 1000 * 10i128.pow(6), // more than the lot, so the whole lot is withdrawn
+        },
+    ];
+    let positions = pool_fixture.pool.flash_loan(
+        &bot,
+        &FlashLoan {
+            contract: receiver.clone(),
+            asset: xlm.address.clone(),
+            amount: loan_amount,
+        },
+        &fill_requests,
+    );
+
+    assert_eq!(positions.liabilities.len(), 0);
+    assert_eq!(positions.collateral.len(), 0);
+    assert_eq!(positions.supply.len(), 0);
+    assert!(pool_fixture.pool.try_get_auction(&0, &samwise).is_err());
+    assert_eq!(xlm.balance(&bot), 0);
+    assert_eq!(stable.balance(&bot), 0);
+    assert_eq!(stable.balance(&receiver), 0);
+    assert!(stable.balance(&dex) > 0);
+    // the pool was repaid the loan and the bid, and the excess repayment was refunded to the
+    // receiver
+    let refund = xlm.balance(&receiver);
+    assert!(refund < SCALAR_7);
+    assert_eq!(
+        xlm.balance(&pool_fixture.pool.address),
+        pool_xlm_balance + loan_amount - refund
+    );
+    assert!(loan_amount - refund >= xlm_bid);
+}
@@ -551,14 +551,12 @@ fn test_pool_config() {
     );
     assert!(result.is_err());
 
-    // Update pool config (admin only)
-    let backstop_take_rate: u32 = 0_0500000;
-    pool_fixture.pool.update_pool(&backstop_take_rate, &6);
-    let event_data: soroban_sdk::Vec<Val> = vec![
-        &fixture.env,
-        backstop_take_rate.into_val(&fixture.env),
-        6u32.into_val(&fixture.env),
-    ];
+    // Queue pool config update (admin only)
+    let mut pool_config = fixture.read_pool_config(0);
+    pool_config.bstop_rate = 0_0500000;
+    pool_config.max_positions = 6;
+    pool_config.max_price_age = 3600;
+    pool_fixture.pool.queue_update_pool(&pool_config);
     assert_eq!(
         fixture.env.auths()[0],
         (
@@ -566,15 +564,13 @@ fn test_pool_config() {
             AuthorizedInvocation {
                 function: AuthorizedFunction::Contract((
                     pool_fixture.pool.address.clone(),
-                    Symbol::new(&fixture.env, "update_pool"),
-                    event_data.into_val(&fixture.env)
+                    Symbol::new(&fixture.env, "queue_update_pool"),
+                    vec![&fixture.env, pool_config.into_val(&fixture.env)]
                 )),
                 sub_invocations: std::vec![]
             }
         )
     );
-    let new_pool_config = fixture.read_pool_config(0);
-    assert_eq!(new_pool_config.bstop_rate, 0_0500000);
     let event = vec![&fixture.env, fixture.env.events().all().last_unchecked()];
     assert_eq!(
         event,
@@ -583,11 +579,32 @@ fn test_pool_config() {
             (
                 pool_fixture.pool.address.clone(),
                 (
-                    Symbol::new(&fixture.env, "update_pool"),
+                    Symbol::new(&fixture.env, "queue_update_pool"),
                     fixture.bombadil.clone()
                 )
                     .into_val(&fixture.env),
-                event_data.into_val(&fixture.env)
+                pool_config.into_val(&fixture.env)
+            )
+        ]
+    );
+
+    // Update pool config
+    fixture.jump(604800); // 1 week
+    pool_fixture.pool.update_pool();
+    assert_eq!(fixture.env.auths().len(), 0);
+    let new_pool_config = fixture.read_pool_config(0);
+    assert_eq!(new_pool_config.bstop_rate, 0_0500000);
+    assert_eq!(new_pool_config.max_positions, 6);
+    assert_eq!(new_pool_config.max_price_age, 3600);
+    let event = vec![&fixture.env, fixture.env.events().all().last_unchecked()];
+    assert_eq!(
+        event,
+        vec![
+            &fixture.env,
+            (
+                pool_fixture.pool.address.clone(),
+                (Symbol::new(&fixture.env, "update_pool"),).into_val(&fixture.env),
+                new_pool_config.into_val(&fixture.env)
             )
         ]
     );
@@ -696,9 +713,9 @@ fn test_pool_config() {
         ]
     );
 
-    // Set admin (admin only)
+    // Propose admin (admin only)
     let new_admin = Address::generate(&fixture.env);
-    pool_fixture.pool.set_admin(&new_admin);
+    pool_fixture.pool.propose_admin(&new_admin);
     assert_eq!(
         fixture.env.auths()[0],
         (
@@ -706,22 +723,41 @@ fn test_pool_config() {
             AuthorizedInvocation {
                 function: AuthorizedFunction::Contract((
                     pool_fixture.pool.address.clone(),
-                    Symbol::new(&fixture.env, "set_admin"),
+                    Symbol::new(&fixture.env, "propose_admin"),
                     vec![&fixture.env, new_admin.to_val(),]
                 )),
                 sub_invocations: std::vec![]
             }
         )
     );
+    let event = vec![&fixture.env, fixture.env.events().all().last_unchecked()];
     assert_eq!(
-        fixture.env.auths()[1],
+        event,
+        vec![
+            &fixture.env,
+            (
+                pool_fixture.pool.address.clone(),
+                (
+                    Symbol::new(&fixture.env, "propose_admin"),
+                    fixture.bombadil.clone()
+                )
+                    .into_val(&fixture.env),
+                new_admin.into_val(&fixture.env)
+            )
+        ]
+    );
+
+    // Accept admin (proposed admin only)
+    pool_fixture.pool.accept_admin();
+    assert_eq!(
+        fixture.env.auths()[0],
         (
             new_admin.clone(),
             AuthorizedInvocation {
                 function: AuthorizedFunction::Contract((
                     pool_fixture.pool.address.clone(),
-                    Symbol::new(&fixture.env, "set_admin"),
-                    vec![&fixture.env, new_admin.to_val(),]
+                    Symbol::new(&fixture.env, "accept_admin"),
+                    vec![&fixture.env]
                 )),
                 sub_invocations: std::vec![]
             }
@@ -805,6 +841,56 @@ fn test_pool_config() {
         ]
     );
 
+    // Set guardian (admin only)
+    let guardian = Address::generate(&fixture.env);
+    pool_fixture.pool.set_guardian(&guardian);
+    assert_eq!(
+        fixture.env.auths()[0],
+        (
+            new_admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    pool_fixture.pool.address.clone(),
+                    Symbol::new(&fixture.env, "set_guardian"),
+                    vec![&fixture.env, guardian.to_val()]
+                )),
+                sub_invocations: std::vec![]
+            }
+        )
+    );
+
+    // Freeze (guardian only)
+    pool_fixture.pool.freeze();
+    assert_eq!(
+        fixture.env.auths()[0],
+        (
+            guardian.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    pool_fixture.pool.address.clone(),
+                    Symbol::new(&fixture.env, "freeze"),
+                    vec![&fixture.env]
+                )),
+                sub_invocations: std::vec![]
+            }
+        )
+    );
+    let new_pool_config = fixture.read_pool_config(0);
+    assert_eq!(new_pool_config.status, 4);
+    let event = vec![&fixture.env, fixture.env.events().all().last_unchecked()];
+    assert_eq!(
+        event,
+        vec![
+            &fixture.env,
+            (
+                pool_fixture.pool.address.clone(),
+                (Symbol::new(&fixture.env, "set_status"), guardian.clone()).into_val(&fixture.env),
+                4u32.into_val(&fixture.env)
+            )
+        ]
+    );
+    pool_fixture.pool.set_status(&0);
+
     // Queue 50% of backstop for withdrawal
     fixture.backstop.queue_withdrawal(
         &fixture.users[0],